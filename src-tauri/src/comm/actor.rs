@@ -1,23 +1,36 @@
-use crate::comm::{proto, serial, tcp};
+use crate::comm::{
+    alarm, auth, capture, fragment, methods, outbox, payload, proto, queue, rpc, schema, sequence,
+    session, tls, transport, CommShared,
+};
 use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::oneshot;
+use tokio::time::Instant;
 
 const COMM_EVENT_NAME: &str = "comm-event";
 const HMIP_EVENT_NAME: &str = "hmip-event";
 
 const READ_BUFFER_SIZE: usize = 4096;
+// 面向消息的传输按最大 UDP 数据报分配读缓冲，避免数据报被截断
+const MESSAGE_BUFFER_SIZE: usize = 64 * 1024;
 const WRITE_TIMEOUT_MS: u64 = 2000;
 
 // 为了避免“把大 payload 直接塞进前端事件”造成 UI 卡顿，
 // 默认仅在 payload 较小时携带 base64（用于调试/对接）。
 const HMIP_PAYLOAD_EMIT_MAX: usize = 2048;
 
-const RECONNECT_MIN_DELAY_MS: u64 = 200;
-const RECONNECT_MAX_DELAY_MS: u64 = 5000;
+// 同一连接上同时处理中的设备 Request 上限，超出时直接回 BUSY
+const MAX_SERVING_REQUESTS: usize = 8;
+
+// UDP 任意来源模式下最多保留的来源数，超出时淘汰最久没有数据的来源
+const MAX_RX_SOURCES: usize = 256;
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 // snake_case 在反序列化时使用蛇形命名，但在 Rust 代码中仍然使用驼峰命名，比如 CommPriority::High 会被序列化为 "high"
@@ -44,6 +57,16 @@ pub enum CommEvent {
         transport: String,
         timestamp_ms: u64,
     },
+    /// HELLO/HELLO_ACK 握手完成（仅在启用 handshake 时出现）
+    SessionEstablished {
+        transport: String,
+        peer_name: String,
+        peer_capabilities: u32,
+        capabilities: u32,
+        /// 协商出的帧头版本（1/2）
+        wire_version: u8,
+        timestamp_ms: u64,
+    },
     Reconnecting {
         transport: String,
         attempt: u32,
@@ -55,6 +78,9 @@ pub enum CommEvent {
         data_base64: String,
         text: Option<String>,
         size: usize,
+        /// 数据报来源地址（UDP 等面向数据报的传输）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
         timestamp_ms: u64,
     },
     Tx {
@@ -65,6 +91,30 @@ pub enum CommEvent {
     Error {
         transport: String,
         message: String,
+        /// 可区分的失败原因（如 TLS 握手的 `tls_certificate_expired`），普通 IO 错误不带该字段
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<&'static str>,
+        timestamp_ms: u64,
+    },
+    /// 监听模式已开始接受连接（对端接入/断开以 `tcp_server:<addr>` 的 Connected/Disconnected 上报）
+    ServerListening {
+        transport: String,
+        local_addr: String,
+        max_clients: usize,
+        timestamp_ms: u64,
+    },
+    /// 对端接入被拒绝（超过 max_clients）
+    PeerRejected {
+        transport: String,
+        peer: String,
+        reason: String,
+        timestamp_ms: u64,
+    },
+    /// 写队列中的帧未发出即被丢弃（队列满/过期/清空/连接关闭）
+    TxDropped {
+        transport: String,
+        #[serde(flatten)]
+        dropped: queue::TxDrop,
         timestamp_ms: u64,
     },
 }
@@ -78,8 +128,92 @@ pub enum HmipEvent {
         dropped_bytes: usize,
         timestamp_ms: u64,
     },
+    /// 认证失败（标签错误/缺少认证/本端未配置密钥），帧已丢弃
+    AuthRejected {
+        transport: String,
+        channel: u8,
+        seq: u32,
+        msg_type: u8,
+        reason: String,
+        timestamp_ms: u64,
+    },
+    /// 认证通过但 seq 落在防重放窗口之外或已见过，帧已丢弃
+    ReplayRejected {
+        transport: String,
+        channel: u8,
+        seq: u32,
+        msg_type: u8,
+        highest_seq: u32,
+        timestamp_ms: u64,
+    },
+    /// 同一 channel 上 seq 跳变（中间 missing 个帧未收到）
+    SeqGap {
+        transport: String,
+        channel: u8,
+        seq: u32,
+        expected: u32,
+        missing: u32,
+        timestamp_ms: u64,
+    },
+    /// 最近窗口内已收到过相同 seq
+    SeqDuplicate {
+        transport: String,
+        channel: u8,
+        seq: u32,
+        timestamp_ms: u64,
+    },
+    /// seq 小于期望值（迟到/乱序）
+    SeqOutOfOrder {
+        transport: String,
+        channel: u8,
+        seq: u32,
+        expected: u32,
+        timestamp_ms: u64,
+    },
+    /// 对端 seq 重新开始（如对端重启），该 channel 从当前 seq 重新跟踪
+    SeqReset {
+        transport: String,
+        channel: u8,
+        seq: u32,
+        previous: u32,
+        timestamp_ms: u64,
+    },
+    /// 收到不支持的帧头版本（整帧已跳过，decoder 从下一个 MAGIC 继续）
+    UnsupportedVersion {
+        transport: String,
+        version: u8,
+        dropped_bytes: usize,
+        timestamp_ms: u64,
+    },
+    /// 分片重组失败（超时/超限/非法分片），整条消息被丢弃
+    FragmentDropped {
+        transport: String,
+        channel: u8,
+        msg_type: u8,
+        fragment_id: u32,
+        reason: String,
+        dropped_bytes: usize,
+        timestamp_ms: u64,
+    },
+    /// 开始回放抓包（其后的事件按原 transport 推送，直到 ReplayFinished）
+    ReplayStarted {
+        path: String,
+        captured_at_ms: u64,
+        speed: f64,
+        timestamp_ms: u64,
+    },
+    /// 回放结束：records 为已回放的 rx 记录数；stopped 表示被手动停止
+    ReplayFinished {
+        path: String,
+        records: u64,
+        stopped: bool,
+        error: Option<String>,
+        timestamp_ms: u64,
+    },
     Message {
         transport: String,
+        /// 帧头版本（1/2）
+        version: u8,
         channel: u8,
         seq: u32,
         flags: u8,
@@ -88,6 +222,9 @@ pub enum HmipEvent {
         payload_crc32: Option<u32>,
         timestamp_ms: u64,
         summary: HmipMessageSummary,
+        /// payload 超出预览上限时的完整数据句柄（`fetch_hmip_payload`）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        payload_handle: Option<payload::PayloadHandle>,
     },
 }
 
@@ -112,6 +249,9 @@ pub enum HmipMessageSummary {
         body_len: usize,
         body_base64: Option<String>,
         body_truncated: bool,
+        /// 按 schema 解码的 body（未注册 schema 时省略）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decoded: Option<schema::DecodedBody>,
     },
     Response {
         request_id: u32,
@@ -119,6 +259,9 @@ pub enum HmipMessageSummary {
         body_len: usize,
         body_base64: Option<String>,
         body_truncated: bool,
+        /// 按 schema 解码的 body（未注册 schema 时省略）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decoded: Option<schema::DecodedBody>,
     },
     Event {
         event_id: u16,
@@ -126,6 +269,9 @@ pub enum HmipMessageSummary {
         body_len: usize,
         body_base64: Option<String>,
         body_truncated: bool,
+        /// 按 schema 解码的 body（未注册 schema 时省略）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decoded: Option<schema::DecodedBody>,
     },
     Error {
         code: u16,
@@ -136,17 +282,140 @@ pub enum HmipMessageSummary {
         payload_len: usize,
         payload_base64: Option<String>,
         payload_truncated: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decoded: Option<schema::DecodedBody>,
     },
 }
 
 pub struct CommActorHandle {
-    pub tx_high: mpsc::Sender<Vec<u8>>,
-    pub tx_normal: mpsc::Sender<Vec<u8>>,
+    /// 发送队列（按 `hmip.tx_queues` 配置加权调度，跨重连保留）
+    pub tx_queues: Arc<queue::TxQueues>,
+    next_request_id: Arc<AtomicU32>,
+    next_fragment_id: AtomicU32,
+    fragmentation: Option<fragment::FragmentConfig>,
+    crc_policy: session::CrcPolicy,
+    tx_seq: Arc<sequence::SeqGenerator>,
+    session: Arc<Mutex<Option<session::SessionInfo>>>,
+    auth: Option<Arc<auth::FrameAuth>>,
+    seq_stats: Arc<Mutex<sequence::SeqStats>>,
+    capture: capture::CaptureSlot,
+    outbox: Option<Arc<outbox::Outbox>>,
+    kind: &'static str,
+    target: String,
+    link_up: Arc<AtomicBool>,
+    created_at_ms: u64,
     shutdown_tx: oneshot::Sender<()>,
     join: tauri::async_runtime::JoinHandle<()>,
 }
 
 impl CommActorHandle {
+    /// 分配本连接内唯一的 request_id（用于 Request/Response 关联）
+    pub fn next_request_id(&self) -> u32 {
+        self.next_request_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 可在释放连接锁后使用的 Request 客户端（共享本连接的 request_id 与 seq 分配）
+    pub fn rpc_client(&self, label: &str) -> rpc::RpcClient {
+        rpc::RpcClient::new(
+            label.to_string(),
+            self.tx_queues.clone(),
+            self.next_request_id.clone(),
+            self.tx_seq.clone(),
+        )
+    }
+
+    /// 分配分片消息 ID（同一连接内唯一）
+    pub fn next_fragment_id(&self) -> u32 {
+        self.next_fragment_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 发送端分片配置（None 表示不拆分）
+    pub fn fragmentation(&self) -> Option<&fragment::FragmentConfig> {
+        self.fragmentation.as_ref()
+    }
+
+    /// 类型化发送的 CRC 策略
+    pub fn crc_policy(&self) -> session::CrcPolicy {
+        self.crc_policy
+    }
+
+    /// 为本连接 channel 上的下一帧分配 seq
+    pub fn next_seq(&self, channel: u8) -> u32 {
+        self.tx_seq.next(channel)
+    }
+
+    /// 发送侧 seq 分配器（供命令在释放连接锁后继续分配分片 seq）
+    pub fn tx_seq(&self) -> Arc<sequence::SeqGenerator> {
+        self.tx_seq.clone()
+    }
+
+    /// 帧认证器（未配置密钥时为 None）
+    pub fn auth(&self) -> Option<Arc<auth::FrameAuth>> {
+        self.auth.clone()
+    }
+
+    /// 接收侧各 channel 的 seq 统计（跨重连累计）
+    pub fn seq_stats(&self) -> Vec<sequence::ChannelSeqStats> {
+        self.seq_stats
+            .lock()
+            .map(|stats| stats.snapshot())
+            .unwrap_or_default()
+    }
+
+    /// 当前握手会话（未启用握手、握手未完成或已断线时为 None）
+    pub fn session(&self) -> Option<session::SessionInfo> {
+        self.session.lock().ok().and_then(|guard| guard.clone())
+    }
+
+    /// 发送帧应使用的帧头版本（未协商 v2 时为 v1）
+    pub fn wire_version(&self) -> u8 {
+        self.session()
+            .map(|info| info.wire_version)
+            .unwrap_or(proto::VERSION_1)
+    }
+
+    /// 开始把本连接的收发字节与完整帧写入抓包文件（已在抓包时切换到新文件）
+    pub fn start_capture(&self, path: &std::path::Path) -> Result<(), String> {
+        let writer = capture::CaptureWriter::create(path, now_ms())?;
+        let mut guard = self
+            .capture
+            .lock()
+            .map_err(|_| "Capture state is poisoned".to_string())?;
+        // 旧的写线程在落盘剩余记录后自行结束
+        *guard = Some(capture::CaptureSession::start(writer));
+        Ok(())
+    }
+
+    /// 停止抓包，等待剩余记录落盘后返回已写入的记录数（未在抓包时为 None）
+    pub async fn stop_capture(&self) -> Option<u64> {
+        let session = self.capture.lock().ok()?.take()?;
+        Some(session.finish().await)
+    }
+
+    /// 持久化发件箱（未配置 `hmip.outbox` 时为 None）
+    pub fn outbox(&self) -> Option<Arc<outbox::Outbox>> {
+        self.outbox.clone()
+    }
+
+    /// 传输类型（serial / tcp / tcp_server）
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// 连接目标描述（见 `Transport::describe`）
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// 链路当前是否已建立（重连期间为 false）
+    pub fn is_link_up(&self) -> bool {
+        self.link_up.load(Ordering::Relaxed)
+    }
+
+    pub fn created_at_ms(&self) -> u64 {
+        self.created_at_ms
+    }
+
     pub async fn shutdown(self) {
         let _ = self.shutdown_tx.send(());
         if let Err(err) = self.join.await {
@@ -155,19 +424,13 @@ impl CommActorHandle {
     }
 }
 
-fn now_ms() -> u64 {
+pub fn now_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis() as u64
 }
 
-fn compute_backoff_ms(attempt: u32) -> u64 {
-    let exp = attempt.min(6);
-    let base = RECONNECT_MIN_DELAY_MS.saturating_mul(1u64 << exp);
-    base.min(RECONNECT_MAX_DELAY_MS)
-}
-
 fn maybe_utf8_preview(bytes: &[u8]) -> Option<String> {
     let s = std::str::from_utf8(bytes).ok()?;
     let trimmed = s.trim_matches('\0').trim();
@@ -193,7 +456,7 @@ fn maybe_utf8_preview(bytes: &[u8]) -> Option<String> {
     Some(preview)
 }
 
-fn emit_event(app: &AppHandle, event: &CommEvent) -> bool {
+pub fn emit_event(app: &AppHandle, event: &CommEvent) -> bool {
     match app.emit(COMM_EVENT_NAME, event) {
         Ok(_) => true,
         Err(err) => {
@@ -213,6 +476,98 @@ fn emit_hmip_event(app: &AppHandle, event: &HmipEvent) -> bool {
     }
 }
 
+fn fragment_drop_event(transport: &str, drop: fragment::FragmentDrop) -> HmipEvent {
+    HmipEvent::FragmentDropped {
+        transport: transport.to_string(),
+        channel: drop.channel,
+        msg_type: drop.msg_type,
+        fragment_id: drop.fragment_id,
+        reason: drop.reason,
+        dropped_bytes: drop.dropped_bytes,
+        timestamp_ms: now_ms(),
+    }
+}
+
+/// 解码错误上报：版本不支持单独成事件，便于前端区分“对端协议过新”与链路噪声
+fn decode_error_event(transport: &str, err: proto::DecodeError) -> HmipEvent {
+    match err.kind {
+        proto::DecodeErrorKind::UnsupportedVersion(version) => HmipEvent::UnsupportedVersion {
+            transport: transport.to_string(),
+            version,
+            dropped_bytes: err.dropped_bytes,
+            timestamp_ms: now_ms(),
+        },
+        _ => HmipEvent::DecodeError {
+            transport: transport.to_string(),
+            message: err.message,
+            dropped_bytes: err.dropped_bytes,
+            timestamp_ms: now_ms(),
+        },
+    }
+}
+
+fn auth_reject_event(
+    transport: &str,
+    header: &proto::FrameHeader,
+    reject: auth::AuthReject,
+) -> HmipEvent {
+    match reject {
+        auth::AuthReject::Auth(reason) => HmipEvent::AuthRejected {
+            transport: transport.to_string(),
+            channel: header.channel,
+            seq: header.seq,
+            msg_type: header.msg_type,
+            reason,
+            timestamp_ms: now_ms(),
+        },
+        auth::AuthReject::Replay { highest_seq } => HmipEvent::ReplayRejected {
+            transport: transport.to_string(),
+            channel: header.channel,
+            seq: header.seq,
+            msg_type: header.msg_type,
+            highest_seq,
+            timestamp_ms: now_ms(),
+        },
+    }
+}
+
+fn seq_anomaly_event(
+    transport: &str,
+    header: &proto::FrameHeader,
+    anomaly: sequence::SeqAnomaly,
+) -> HmipEvent {
+    match anomaly {
+        sequence::SeqAnomaly::Gap { expected, missing } => HmipEvent::SeqGap {
+            transport: transport.to_string(),
+            channel: header.channel,
+            seq: header.seq,
+            expected,
+            missing,
+            timestamp_ms: now_ms(),
+        },
+        sequence::SeqAnomaly::Duplicate => HmipEvent::SeqDuplicate {
+            transport: transport.to_string(),
+            channel: header.channel,
+            seq: header.seq,
+            timestamp_ms: now_ms(),
+        },
+        sequence::SeqAnomaly::OutOfOrder { expected } => HmipEvent::SeqOutOfOrder {
+            transport: transport.to_string(),
+            channel: header.channel,
+            seq: header.seq,
+            expected,
+            timestamp_ms: now_ms(),
+        },
+        sequence::SeqAnomaly::Reset { previous } => HmipEvent::SeqReset {
+            transport: transport.to_string(),
+            channel: header.channel,
+            seq: header.seq,
+            previous,
+            timestamp_ms: now_ms(),
+        },
+    }
+}
+
 fn base64_preview(bytes: &[u8]) -> (Option<String>, bool) {
     if bytes.is_empty() {
         return (None, false);
@@ -226,217 +581,794 @@ fn base64_preview(bytes: &[u8]) -> (Option<String>, bool) {
     )
 }
 
+fn decode_body(shared: &CommShared, kind: schema::BodyKind, body: &[u8]) -> Option<schema::DecodedBody> {
+    shared.schemas.read().ok()?.decode(kind, body, HMIP_PAYLOAD_EMIT_MAX)
+}
+
+/// 超出事件预览上限的 payload 转存到共享缓存，返回供前端拉取的句柄
+fn store_payload(
+    shared: &CommShared,
+    transport: &str,
+    seq: u32,
+    payload: &bytes::Bytes,
+) -> Option<payload::PayloadHandle> {
+    if payload.len() <= HMIP_PAYLOAD_EMIT_MAX {
+        return None;
+    }
+    shared.payloads.lock().ok()?.insert(transport, seq, payload.clone())
+}
+
+/// 完整帧 → `HmipEvent::Message`（实时连接与抓包回放共用）
+fn message_event(
+    shared: &CommShared,
+    transport: &str,
+    frame: &proto::Frame,
+    decoded: Result<proto::Message, proto::MessageDecodeError>,
+    response_method: Option<u16>,
+) -> HmipEvent {
+    let header = frame.header;
+    let summary = match decoded {
+        Ok(proto::Message::Hello(v)) => HmipMessageSummary::Hello {
+            role: match v.role {
+                proto::Role::Client => "client".to_string(),
+                proto::Role::Server => "server".to_string(),
+            },
+            capabilities: v.capabilities,
+            name: v.name,
+        },
+        Ok(proto::Message::HelloAck(v)) => HmipMessageSummary::HelloAck {
+            capabilities: v.capabilities,
+            name: v.name,
+        },
+        Ok(proto::Message::Heartbeat(v)) => HmipMessageSummary::Heartbeat {
+            timestamp_ms: v.timestamp_ms,
+        },
+        Ok(proto::Message::Request(v)) => {
+            let (b64, truncated) = base64_preview(&v.body);
+            HmipMessageSummary::Request {
+                request_id: v.request_id,
+                method: v.method,
+                body_len: v.body.len(),
+                body_base64: b64,
+                body_truncated: truncated,
+                decoded: decode_body(shared, schema::BodyKind::Request(v.method), &v.body),
+            }
+        }
+        Ok(proto::Message::Response(v)) => {
+            let (b64, truncated) = base64_preview(&v.body);
+            HmipMessageSummary::Response {
+                request_id: v.request_id,
+                status: v.status,
+                body_len: v.body.len(),
+                body_base64: b64,
+                body_truncated: truncated,
+                decoded: response_method
+                    .and_then(|method| decode_body(shared, schema::BodyKind::Response(method), &v.body)),
+            }
+        }
+        Ok(proto::Message::Event(v)) => {
+            let (b64, truncated) = base64_preview(&v.body);
+            HmipMessageSummary::Event {
+                event_id: v.event_id,
+                timestamp_ms: v.timestamp_ms,
+                body_len: v.body.len(),
+                body_base64: b64,
+                body_truncated: truncated,
+                decoded: decode_body(shared, schema::BodyKind::Event(v.event_id), &v.body),
+            }
+        }
+        Ok(proto::Message::Error(v)) => HmipMessageSummary::Error {
+            code: v.code,
+            message: v.message,
+        },
+        Ok(proto::Message::Raw { msg_type, payload }) => {
+            let (b64, truncated) = base64_preview(&payload);
+            HmipMessageSummary::Raw {
+                msg_type,
+                payload_len: payload.len(),
+                payload_base64: b64,
+                payload_truncated: truncated,
+                decoded: decode_body(shared, schema::BodyKind::Raw(msg_type), &payload),
+            }
+        }
+        Err(_err) => {
+            let (b64, truncated) = base64_preview(&frame.payload);
+            HmipMessageSummary::Raw {
+                msg_type: header.msg_type,
+                payload_len: frame.payload.len(),
+                payload_base64: b64,
+                payload_truncated: truncated,
+                decoded: None,
+            }
+        }
+    };
+
+    HmipEvent::Message {
+        transport: transport.to_string(),
+        version: header.version,
+        channel: header.channel,
+        seq: header.seq,
+        flags: header.flags,
+        msg_type: header.msg_type,
+        payload_len: header.payload_len,
+        payload_crc32: header.payload_crc32,
+        timestamp_ms: now_ms(),
+        summary,
+        payload_handle: store_payload(shared, transport, header.seq, &frame.payload),
+    }
+}
+
+/// 接收流水线的输出（保持到达顺序）
+enum RxItem {
+    /// 需要上报的异常（解码错误/认证拒绝/seq 异常/分片丢弃）
+    Event(HmipEvent),
+    /// 通过认证并完成重组的完整帧
+    Frame(proto::Frame),
+}
+
+/// 接收流水线：bytes → 帧解码 → 认证/防重放 → seq 统计 → 分片重组
+///
+/// 实时连接与抓包回放共用同一实现，保证回放时上报的事件与现场一致
+struct RxPipeline<'a> {
+    decoder: proto::FrameDecoder,
+    reassembler: fragment::Reassembler,
+    verifier: auth::FrameVerifier<'a>,
+    seq_tracker: sequence::SeqTracker,
+    // 回放时为 None：不计入实时连接的统计
+    seq_stats: Option<Arc<Mutex<sequence::SeqStats>>>,
+}
+
+impl<'a> RxPipeline<'a> {
+    fn new(
+        fragmentation: fragment::FragmentConfig,
+        auth: Option<&'a auth::FrameAuth>,
+        seq_stats: Option<Arc<Mutex<sequence::SeqStats>>>,
+    ) -> Self {
+        Self {
+            decoder: proto::FrameDecoder::new(proto::DecoderConfig::default()),
+            reassembler: fragment::Reassembler::new(fragmentation),
+            verifier: auth::FrameVerifier::new(auth),
+            seq_tracker: sequence::SeqTracker::default(),
+            seq_stats,
+        }
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.reassembler.next_deadline()
+    }
+
+    fn expire(&mut self, transport: &str, now: Instant) -> Vec<HmipEvent> {
+        self.reassembler
+            .expire(now)
+            .into_iter()
+            .map(|drop| fragment_drop_event(transport, drop))
+            .collect()
+    }
+
+    fn push(&mut self, transport: &str, bytes: &[u8], now: Instant) -> Vec<RxItem> {
+        let mut items = Vec::new();
+        if let Err(err) = self.decoder.push(bytes) {
+            items.push(RxItem::Event(decode_error_event(transport, err)));
+            return items;
+        }
+        loop {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => self.accept(transport, frame, now, &mut items),
+                Ok(None) => break,
+                // 继续尝试解析后续帧（decoder 内部已重同步）
+                Err(err) => items.push(RxItem::Event(decode_error_event(transport, err))),
+            }
+        }
+        items
+    }
+
+    /// 一个数据报处理完毕：丢弃残留的不完整帧
+    fn end_datagram(&mut self, transport: &str) -> Option<HmipEvent> {
+        self.decoder
+            .discard_partial()
+            .map(|err| decode_error_event(transport, err))
+    }
+
+    fn accept(&mut self, transport: &str, frame: proto::Frame, now: Instant, items: &mut Vec<RxItem>) {
+        // 认证/防重放在最前：未通过的帧不参与重组、RPC 与会话处理
+        let header = frame.header;
+        let frame = match self.verifier.verify(frame) {
+            Ok(frame) => frame,
+            Err(reject) => {
+                items.push(RxItem::Event(auth_reject_event(transport, &header, reject)));
+                return;
+            }
+        };
+
+        // seq 连续性按线上帧统计（分片逐帧计入），异常只上报不丢帧
+        let anomaly = self.seq_tracker.observe(header.channel, header.seq);
+        if let Some(Ok(mut stats)) = self.seq_stats.as_ref().map(|stats| stats.lock()) {
+            stats.record(header.channel, header.seq, anomaly);
+        }
+        if let Some(anomaly) = anomaly {
+            items.push(RxItem::Event(seq_anomaly_event(transport, &header, anomaly)));
+        }
+
+        // 分片帧先进入重组，完整后再按普通帧处理
+        if frame.header.flags & proto::FLAG_FRAGMENT == 0 {
+            items.push(RxItem::Frame(frame));
+            return;
+        }
+        match self.reassembler.push(frame, now) {
+            Ok(Some(full)) => items.push(RxItem::Frame(full)),
+            Ok(None) => {}
+            Err(drop) => items.push(RxItem::Event(fragment_drop_event(transport, drop))),
+        }
+    }
+}
+
+/// 按数据报来源划分的接收流水线
+///
+/// UDP 任意来源模式下各来源的 seq 连续性、防重放窗口与分片重组互不干扰；
+/// 其它传输（以及已 connect 的 UDP）只有一个来源（None 或固定地址）
+struct RxSources<'a> {
+    fragmentation: fragment::FragmentConfig,
+    auth: Option<&'a auth::FrameAuth>,
+    seq_stats: Arc<Mutex<sequence::SeqStats>>,
+    pipelines: HashMap<Option<SocketAddr>, (RxPipeline<'a>, Instant)>,
+}
+
+impl<'a> RxSources<'a> {
+    fn new(
+        fragmentation: fragment::FragmentConfig,
+        auth: Option<&'a auth::FrameAuth>,
+        seq_stats: Arc<Mutex<sequence::SeqStats>>,
+    ) -> Self {
+        Self {
+            fragmentation,
+            auth,
+            seq_stats,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// 取来源对应的流水线（首次出现时创建）并记录活跃时间
+    fn get(&mut self, source: Option<SocketAddr>, now: Instant) -> &mut RxPipeline<'a> {
+        if !self.pipelines.contains_key(&source) && self.pipelines.len() >= MAX_RX_SOURCES {
+            let idle = self
+                .pipelines
+                .iter()
+                .min_by_key(|(_, (_, active))| *active)
+                .map(|(source, _)| *source);
+            if let Some(idle) = idle {
+                self.pipelines.remove(&idle);
+            }
+        }
+        let (rx, active) = self.pipelines.entry(source).or_insert_with(|| {
+            let rx = RxPipeline::new(self.fragmentation.clone(), self.auth, Some(self.seq_stats.clone()));
+            (rx, now)
+        });
+        *active = now;
+        rx
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.pipelines
+            .values()
+            .filter_map(|(rx, _)| rx.next_deadline())
+            .min()
+    }
+
+    fn expire(&mut self, transport: &str, now: Instant) -> Vec<HmipEvent> {
+        self.pipelines
+            .values_mut()
+            .flat_map(|(rx, _)| rx.expire(transport, now))
+            .collect()
+    }
+}
+
+/// actor 的全部输入队列（跨重连保持不变）
+struct ActorInbox {
+    queues: Arc<queue::TxQueues>,
+    outbox: Option<Arc<outbox::Outbox>>,
+    shutdown_rx: oneshot::Receiver<()>,
+}
+
+/// actor 跨重连保留的状态
+struct ActorState {
+    pending: rpc::PendingRequests,
+    session: Arc<Mutex<Option<session::SessionInfo>>>,
+    auth: Option<Arc<auth::FrameAuth>>,
+    seq_stats: Arc<Mutex<sequence::SeqStats>>,
+    tx_seq: Arc<sequence::SeqGenerator>,
+    capture: capture::CaptureSlot,
+    shared: CommShared,
+    /// 设备 Request 的应答按 high 优先级入队
+    queues: Arc<queue::TxQueues>,
+    serving: Arc<AtomicUsize>,
+}
+
+impl ActorState {
+    fn new(
+        session: Arc<Mutex<Option<session::SessionInfo>>>,
+        auth: Option<Arc<auth::FrameAuth>>,
+        seq_stats: Arc<Mutex<sequence::SeqStats>>,
+        tx_seq: Arc<sequence::SeqGenerator>,
+        capture: capture::CaptureSlot,
+        shared: CommShared,
+        queues: Arc<queue::TxQueues>,
+    ) -> Self {
+        Self {
+            pending: rpc::PendingRequests::default(),
+            session,
+            auth,
+            seq_stats,
+            tx_seq,
+            capture,
+            shared,
+            queues,
+            serving: Arc::default(),
+        }
+    }
+
+    /// 应答设备发来的 Request：处理函数在阻塞线程池执行，不阻塞收发循环
+    ///
+    /// `source` 为请求所在数据报的来源，应答发回该地址（UDP 任意来源模式下不随最近来源漂移）
+    fn serve_request(
+        &self,
+        transport: &str,
+        source: Option<SocketAddr>,
+        channel: u8,
+        request: proto::Request,
+        version: u8,
+        flags: u8,
+    ) {
+        let registry = self.shared.methods.clone();
+        let tx_seq = self.tx_seq.clone();
+        let queues = self.queues.clone();
+        let serving = self.serving.clone();
+        let transport = transport.to_string();
+
+        let busy = serving.fetch_add(1, Ordering::AcqRel) >= MAX_SERVING_REQUESTS;
+        tauri::async_runtime::spawn(async move {
+            let request_id = request.request_id;
+            let method = request.method;
+            let reply = if busy {
+                methods::Reply::status(proto::status::BUSY)
+            } else {
+                let handler_transport = transport.clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let ctx = methods::RequestContext {
+                        transport: &handler_transport,
+                        request_id,
+                        method,
+                    };
+                    registry.dispatch(&ctx, &request.body)
+                })
+                .await
+                .unwrap_or_else(|err| {
+                    log::warn!("HMIP method 0x{:04X} handler failed: {}", method, err);
+                    methods::Reply::status(proto::status::INTERNAL_ERROR)
+                })
+            };
+            serving.fetch_sub(1, Ordering::AcqRel);
+
+            let payload = proto::encode_response(&proto::Response {
+                request_id,
+                status: reply.status,
+                body: reply.body.into(),
+            });
+            let frame = queue::HmipFrame {
+                msg_type: proto::msg_type::RESPONSE,
+                flags,
+                channel,
+                seq: tx_seq.assign(channel),
+                version: Some(version),
+                destination: source,
+                payload,
+            };
+            let target = queue::QueueTarget {
+                priority: CommPriority::High,
+                ..queue::QueueTarget::default()
+            };
+            if let Err(err) = queues.try_push(target, queue::Outbound::Hmip(frame)) {
+                log::warn!(
+                    "Dropped HMIP response to request {}: {}",
+                    request_id,
+                    err.message(&transport)
+                );
+            }
+        });
+    }
+
+    fn set_session(&self, info: Option<session::SessionInfo>) {
+        if let Ok(mut guard) = self.session.lock() {
+            *guard = info;
+        }
+    }
+
+    /// 连接结束（断线/关闭）：结束未完成请求并清空会话
+    fn on_connection_end(&mut self, reason: &str) {
+        self.pending.fail_all(reason);
+        self.set_session(None);
+    }
+}
+
 enum ConnectionExit {
     Shutdown,
-    IoError(String),
+    IoError(transport::TransportError),
+}
+
+/// 写队列丢弃通知：以 `comm-event` 的 `tx_dropped` 推送（可能在发送命令或 actor 中调用）
+fn tx_drop_sink(app: AppHandle, transport: &str) -> queue::DropSink {
+    let transport = transport.to_string();
+    Box::new(move |dropped| {
+        let _ = emit_event(
+            &app,
+            &CommEvent::TxDropped {
+                transport: transport.clone(),
+                dropped,
+                timestamp_ms: now_ms(),
+            },
+        );
+    })
+}
+
+/// 等待到 deadline；None 表示永不触发（配合 select! 的可选定时器分支）
+async fn sleep_until_opt(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 等待发件箱的下一条消息；未启用发件箱时永不就绪
+async fn next_outbox_entry(outbox: Option<&outbox::Outbox>) -> outbox::OutboxEntry {
+    match outbox {
+        Some(outbox) => outbox.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn write_outbound<W: AsyncWrite + Unpin>(
+    app: &AppHandle,
+    transport: &str,
+    capture: &capture::CaptureSlot,
+    writer: &mut W,
+    data: &[u8],
+) -> Result<(), ConnectionExit> {
+    match tokio::time::timeout(Duration::from_millis(WRITE_TIMEOUT_MS), writer.write_all(data)).await {
+        Ok(Ok(())) => {
+            capture::record_raw(capture, now_ms(), capture::Direction::Tx, transport, data);
+            let event = CommEvent::Tx {
+                transport: transport.to_string(),
+                size: data.len(),
+                timestamp_ms: now_ms(),
+            };
+            if !emit_event(app, &event) {
+                return Err(ConnectionExit::Shutdown);
+            }
+            Ok(())
+        }
+        Ok(Err(err)) => Err(ConnectionExit::IoError(transport::TransportError::new(
+            format!("Write failed: {}", err),
+            tls::error_reason(&err),
+        ))),
+        Err(_) => Err(ConnectionExit::IoError(
+            format!("Write timeout ({}ms)", WRITE_TIMEOUT_MS).into(),
+        )),
+    }
+}
+
+/// 连接建立时的发送侧准备：按 link.seq.on_reconnect 决定是否从头计数，client 角色返回要先发出的 HELLO
+///
+/// HELLO 在排队帧之前取 seq；重置前入队的帧出队封帧时才改用新一代 seq（`HmipFrame::encode`），
+/// 因此线上顺序为 HELLO 之后按出队顺序继续递增
+fn begin_connection(
+    link: &session::HmipLinkConfig,
+    auth: Option<&auth::FrameAuth>,
+    tx_seq: &sequence::SeqGenerator,
+) -> Option<Vec<u8>> {
+    tx_seq.on_connected();
+    let handshake = link.handshake.as_ref().filter(|handshake| !handshake.is_server())?;
+    Some(session::encode_hello_frame(handshake, auth, tx_seq.next(0)))
 }
 
 async fn run_io_loop<S: AsyncRead + AsyncWrite + Unpin>(
     app: &AppHandle,
     transport: &str,
     stream: S,
-    high_rx: &mut mpsc::Receiver<Vec<u8>>,
-    normal_rx: &mut mpsc::Receiver<Vec<u8>>,
-    shutdown_rx: &mut oneshot::Receiver<()>,
+    link: &session::HmipLinkConfig,
+    meta: Option<transport::MessageMeta>,
+    inbox: &mut ActorInbox,
+    state: &mut ActorState,
 ) -> ConnectionExit {
     let (mut reader, mut writer) = tokio::io::split(stream);
-    let mut buf = vec![0u8; READ_BUFFER_SIZE];
-    let mut hmip_decoder = proto::FrameDecoder::new(proto::DecoderConfig::default());
+    let datagram = meta.as_ref().is_some_and(transport::MessageMeta::is_datagram);
+    let buf_len = if meta.is_some() {
+        MESSAGE_BUFFER_SIZE
+    } else {
+        READ_BUFFER_SIZE
+    };
+    let mut buf = vec![0u8; buf_len];
+    // 每个连接派生新的会话随机数，旧连接上的认证帧无法在本连接重放
+    let frame_auth = state.auth.as_deref().map(auth::FrameAuth::session);
+    let mut rx = RxSources::new(
+        link.fragmentation.clone().unwrap_or_default(),
+        frame_auth.as_ref(),
+        state.seq_stats.clone(),
+    );
+
+    // 握手：client 先发 HELLO 等待 HELLO_ACK，server 等待对端 HELLO 后应答；
+    // 会话建立前暂停业务队列，超时则按断线处理（进入重连）
+    if let Some(hello) = begin_connection(link, frame_auth.as_ref(), &state.tx_seq) {
+        if let Err(exit) = write_outbound(app, transport, &state.capture, &mut writer, &hello).await {
+            return exit;
+        }
+    }
+    let mut handshake_deadline = link
+        .handshake
+        .as_ref()
+        .map(|handshake| Instant::now() + Duration::from_millis(handshake.timeout_ms));
+
+    // 发送侧帧头版本：握手协商出 v2 前一律使用 v1（接收侧始终兼容两种版本）
+    let mut wire_version = proto::VERSION_1;
+
+    // 心跳：会话就绪（无握手时即刻；有握手时在 HELLO_ACK 之后）才开始计时
+    let mut keepalive: Option<session::Keepalive> = match (&link.heartbeat, handshake_deadline) {
+        (Some(heartbeat), None) => Some(session::Keepalive::start(heartbeat, Instant::now())),
+        _ => None,
+    };
 
     loop {
         tokio::select! {
             // 当多个分支同时准备就绪时，Tokio 会优先选择前面声明的分支执行
             biased;
 
-            _ = &mut *shutdown_rx => {
+            _ = &mut inbox.shutdown_rx => {
                 return ConnectionExit::Shutdown;
             }
 
-            Some(data) = high_rx.recv() => {
-                let size = data.len();
-                match tokio::time::timeout(Duration::from_millis(WRITE_TIMEOUT_MS), writer.write_all(&data)).await {
-                    Ok(Ok(())) => {
-                        let event = CommEvent::Tx {
-                            transport: transport.to_string(),
-                            size,
-                            timestamp_ms: now_ms(),
-                        };
-                        if !emit_event(app, &event) {
-                            return ConnectionExit::Shutdown;
-                        }
-                    }
-                    Ok(Err(err)) => {
-                        return ConnectionExit::IoError(format!("Write failed: {}", err));
-                    }
-                    Err(_) => {
-                        return ConnectionExit::IoError(format!("Write timeout ({}ms)", WRITE_TIMEOUT_MS));
+            _ = sleep_until_opt(keepalive.as_ref().map(|k| k.peer_deadline())), if keepalive.is_some() => {
+                let timeout_ms = keepalive.as_ref().map(|k| k.peer_timeout().as_millis()).unwrap_or_default();
+                return ConnectionExit::IoError(
+                    format!("Heartbeat timeout: no peer heartbeat for {}ms", timeout_ms).into(),
+                );
+            }
+
+            _ = sleep_until_opt(keepalive.as_ref().map(|k| k.next_send())), if keepalive.is_some() => {
+                let frame = session::encode_heartbeat_frame(
+                    frame_auth.as_ref(),
+                    wire_version,
+                    state.tx_seq.next(0),
+                    now_ms(),
+                );
+                if let Err(exit) = write_outbound(app, transport, &state.capture, &mut writer, &frame).await {
+                    return exit;
+                }
+                if let Some(k) = keepalive.as_mut() {
+                    k.on_sent(Instant::now());
+                }
+            }
+
+            _ = sleep_until_opt(rx.next_deadline()), if rx.next_deadline().is_some() => {
+                for ev in rx.expire(transport, Instant::now()) {
+                    if !emit_hmip_event(app, &ev) {
+                        return ConnectionExit::Shutdown;
                     }
                 }
             }
 
-            Some(data) = normal_rx.recv() => {
-                let size = data.len();
-                match tokio::time::timeout(Duration::from_millis(WRITE_TIMEOUT_MS), writer.write_all(&data)).await {
-                    Ok(Ok(())) => {
-                        let event = CommEvent::Tx {
-                            transport: transport.to_string(),
-                            size,
-                            timestamp_ms: now_ms(),
-                        };
-                        if !emit_event(app, &event) {
-                            return ConnectionExit::Shutdown;
-                        }
+            _ = sleep_until_opt(handshake_deadline), if handshake_deadline.is_some() => {
+                let timeout_ms = link.handshake.as_ref().map(|h| h.timeout_ms).unwrap_or_default();
+                return ConnectionExit::IoError(
+                    format!("Handshake timeout ({}ms)", timeout_ms).into(),
+                );
+            }
+
+            item = inbox.queues.pop(), if handshake_deadline.is_none() => {
+                let (data, destination) = match item {
+                    queue::Outbound::Frame(data) => (data, None),
+                    queue::Outbound::Hmip(mut frame) => {
+                        (frame.encode(frame_auth.as_ref(), wire_version, &state.tx_seq), frame.destination)
                     }
-                    Ok(Err(err)) => {
-                        return ConnectionExit::IoError(format!("Write failed: {}", err));
+                    // 请求帧：先登记 pending 再写出，避免响应先于登记到达
+                    queue::Outbound::Request(mut request) => {
+                        let data = request.frame.encode(frame_auth.as_ref(), wire_version, &state.tx_seq);
+                        if !state.pending.register(request.request_id, request.method, request.reply) {
+                            continue;
+                        }
+                        (data, request.frame.destination)
                     }
-                    Err(_) => {
-                        return ConnectionExit::IoError(format!("Write timeout ({}ms)", WRITE_TIMEOUT_MS));
+                };
+                // 指定目标（对请求的应答）只作用于这一次写，之后恢复默认目标
+                if let (Some(meta), Some(addr)) = (&meta, destination) {
+                    meta.set_destination(Some(addr));
+                }
+                let written = write_outbound(app, transport, &state.capture, &mut writer, &data).await;
+                if let (Some(meta), Some(_)) = (&meta, destination) {
+                    meta.set_destination(None);
+                }
+                if let Err(exit) = written {
+                    return exit;
+                }
+            }
+
+            // 发件箱排在写队列之后：积压消息按顺序补发，不挤占实时流量
+            entry = next_outbox_entry(inbox.outbox.as_deref()), if handshake_deadline.is_none() => {
+                let seq = state.tx_seq.next(entry.channel);
+                let frame = auth::encode_frame(
+                    frame_auth.as_ref(),
+                    wire_version,
+                    proto::EncodeFrameParams {
+                        msg_type: entry.msg_type,
+                        flags: entry.flags,
+                        channel: entry.channel,
+                        seq,
+                        payload: &entry.payload,
+                    },
+                );
+                if let Err(exit) = write_outbound(app, transport, &state.capture, &mut writer, &frame).await {
+                    return exit;
+                }
+                if let Some(outbox) = &inbox.outbox {
+                    // 出队在循环内完成（下一轮 next 不会再取到这条），文件标记交给阻塞线程池
+                    if let Some(mark) = outbox.ack(entry.id, seq) {
+                        let outbox = outbox.clone();
+                        tauri::async_runtime::spawn_blocking(move || outbox.mark_delivered(mark));
                     }
                 }
             }
 
             read_res = reader.read(&mut buf) => {
                 match read_res {
+                    // 空数据报合法，不代表对端关闭
+                    Ok(0) if datagram => {}
                     Ok(0) => {
-                        return ConnectionExit::IoError("Remote closed".to_string());
+                        return ConnectionExit::IoError("Remote closed".to_string().into());
                     }
                     Ok(n) => {
-                        let bytes = &buf[..n];
+                        // 文本消息（WebSocket）以元数据中的全文为准，读缓冲里只是占位
+                        let text_message = meta.as_ref().and_then(transport::MessageMeta::take_text);
+                        let bytes = match &text_message {
+                            Some(text) => text.as_bytes(),
+                            None => &buf[..n],
+                        };
+                        let source = meta.as_ref().and_then(transport::MessageMeta::source);
+                        capture::record_raw(&state.capture, now_ms(), capture::Direction::Rx, transport, bytes);
                         let event = CommEvent::Rx {
                             transport: transport.to_string(),
                             data_base64: general_purpose::STANDARD.encode(bytes),
-                            text: maybe_utf8_preview(bytes),
-                            size: n,
+                            text: match &text_message {
+                                Some(text) => Some(text.clone()),
+                                None => maybe_utf8_preview(bytes),
+                            },
+                            size: bytes.len(),
+                            source: source.map(|addr| addr.to_string()),
                             timestamp_ms: now_ms(),
                         };
                         if !emit_event(app, &event) {
                             return ConnectionExit::Shutdown;
                         }
+                        // 文本消息不是 HMIP 数据，只以 Rx 事件上报
+                        if text_message.is_some() {
+                            continue;
+                        }
 
-                        // HMIP：bytes → frames → messages
-                        if let Err(err) = hmip_decoder.push(bytes) {
-                            let ev = HmipEvent::DecodeError {
-                                transport: transport.to_string(),
-                                message: err.message,
-                                dropped_bytes: err.dropped_bytes,
-                                timestamp_ms: now_ms(),
+                        // HMIP：bytes → frames → messages（按来源使用各自的接收状态）
+                        let now = Instant::now();
+                        for item in rx.get(source, now).push(transport, bytes, now) {
+                            let frame = match item {
+                                RxItem::Event(ev) => {
+                                    if !emit_hmip_event(app, &ev) {
+                                        return ConnectionExit::Shutdown;
+                                    }
+                                    continue;
+                                }
+                                RxItem::Frame(frame) => frame,
                             };
-                            if !emit_hmip_event(app, &ev) {
-                                return ConnectionExit::Shutdown;
+                            capture::record_frame(&state.capture, now_ms(), capture::Direction::Rx, transport, &frame);
+                            let decoded = proto::decode_message(&frame);
+
+                            // Response：唤醒等待中的 send_hmip_request 调用方（事件仍照常推送）
+                            let mut response_method = None;
+                            if let Ok(proto::Message::Response(v)) = &decoded {
+                                response_method = state.pending.resolve(v.clone());
                             }
-                        } else {
-                            loop {
-                                match hmip_decoder.next_frame() {
-                                    Ok(Some(frame)) => {
-                                        let header = frame.header;
-                                        let decoded = proto::decode_message(&frame);
-
-                                        let summary = match decoded {
-                                            Ok(proto::Message::Hello(v)) => HmipMessageSummary::Hello {
-                                                role: match v.role {
-                                                    proto::Role::Client => "client".to_string(),
-                                                    proto::Role::Server => "server".to_string(),
-                                                },
-                                                capabilities: v.capabilities,
-                                                name: v.name,
-                                            },
-                                            Ok(proto::Message::HelloAck(v)) => HmipMessageSummary::HelloAck {
-                                                capabilities: v.capabilities,
-                                                name: v.name,
-                                            },
-                                            Ok(proto::Message::Heartbeat(v)) => HmipMessageSummary::Heartbeat {
-                                                timestamp_ms: v.timestamp_ms,
-                                            },
-                                            Ok(proto::Message::Request(v)) => {
-                                                let (b64, truncated) = base64_preview(&v.body);
-                                                HmipMessageSummary::Request {
-                                                    request_id: v.request_id,
-                                                    method: v.method,
-                                                    body_len: v.body.len(),
-                                                    body_base64: b64,
-                                                    body_truncated: truncated,
-                                                }
-                                            }
-                                            Ok(proto::Message::Response(v)) => {
-                                                let (b64, truncated) = base64_preview(&v.body);
-                                                HmipMessageSummary::Response {
-                                                    request_id: v.request_id,
-                                                    status: v.status,
-                                                    body_len: v.body.len(),
-                                                    body_base64: b64,
-                                                    body_truncated: truncated,
-                                                }
-                                            }
-                                            Ok(proto::Message::Event(v)) => {
-                                                let (b64, truncated) = base64_preview(&v.body);
-                                                HmipMessageSummary::Event {
-                                                    event_id: v.event_id,
-                                                    timestamp_ms: v.timestamp_ms,
-                                                    body_len: v.body.len(),
-                                                    body_base64: b64,
-                                                    body_truncated: truncated,
-                                                }
-                                            }
-                                            Ok(proto::Message::Error(v)) => HmipMessageSummary::Error {
-                                                code: v.code,
-                                                message: v.message,
-                                            },
-                                            Ok(proto::Message::Raw { msg_type, payload }) => {
-                                                let (b64, truncated) = base64_preview(&payload);
-                                                HmipMessageSummary::Raw {
-                                                    msg_type,
-                                                    payload_len: payload.len(),
-                                                    payload_base64: b64,
-                                                    payload_truncated: truncated,
-                                                }
-                                            }
-                                            Err(_err) => {
-                                                let (b64, truncated) = base64_preview(&frame.payload);
-                                                HmipMessageSummary::Raw {
-                                                    msg_type: header.msg_type,
-                                                    payload_len: frame.payload.len(),
-                                                    payload_base64: b64,
-                                                    payload_truncated: truncated,
-                                                }
-                                            }
-                                        };
 
-                                        let ev = HmipEvent::Message {
-                                            transport: transport.to_string(),
-                                            channel: header.channel,
-                                            seq: header.seq,
-                                            flags: header.flags,
-                                            msg_type: header.msg_type,
-                                            payload_len: header.payload_len,
-                                            payload_crc32: header.payload_crc32,
-                                            timestamp_ms: now_ms(),
-                                            summary,
-                                        };
-                                        if !emit_hmip_event(app, &ev) {
-                                            return ConnectionExit::Shutdown;
+                            // Request：由 HMI 侧方法表自动应答（事件仍照常推送）
+                            if let Ok(proto::Message::Request(v)) = &decoded {
+                                let flags = link.crc.flags(frame_auth.is_some());
+                                let channel = frame.header.channel;
+                                state.serve_request(transport, source, channel, v.clone(), wire_version, flags);
+                            }
+
+                            if let (Some(k), Ok(proto::Message::Heartbeat(_))) = (keepalive.as_mut(), &decoded) {
+                                k.on_peer_heartbeat(Instant::now());
+                            }
+
+                            // client 只在等待期间接受 HELLO_ACK；server 应答每个 HELLO（对端重新握手时按新结果更新会话）
+                            let peer = match (&link.handshake, &decoded) {
+                                (Some(handshake), Ok(proto::Message::HelloAck(ack)))
+                                    if !handshake.is_server() && handshake_deadline.is_some() =>
+                                {
+                                    Some((handshake, ack.name.as_str(), ack.capabilities, ack.nonce))
+                                }
+                                (Some(handshake), Ok(proto::Message::Hello(hello))) if handshake.is_server() => {
+                                    Some((handshake, hello.name.as_str(), hello.capabilities, hello.nonce))
+                                }
+                                _ => None,
+                            };
+                            if let Some((handshake, peer_name, peer_capabilities, peer_nonce)) = peer {
+                                let negotiated = session::negotiate(handshake, peer_name, peer_capabilities, now_ms())
+                                    .and_then(|info| match (&frame_auth, peer_nonce) {
+                                        (Some(auth), Some(nonce)) => {
+                                            auth.set_peer_nonce(nonce);
+                                            Ok(info)
                                         }
-                                    }
-                                    Ok(None) => break,
-                                    Err(err) => {
-                                        let ev = HmipEvent::DecodeError {
+                                        (Some(_), None) => Err("peer did not send a session nonce".to_string()),
+                                        (None, _) => Ok(info),
+                                    });
+                                match negotiated {
+                                    Ok(info) => {
+                                        if handshake.is_server() {
+                                            let ack = session::encode_hello_ack_frame(
+                                                handshake,
+                                                frame_auth.as_ref(),
+                                                state.tx_seq.next(0),
+                                            );
+                                            if let Err(exit) =
+                                                write_outbound(app, transport, &state.capture, &mut writer, &ack).await
+                                            {
+                                                return exit;
+                                            }
+                                        }
+                                        handshake_deadline = None;
+                                        wire_version = info.wire_version;
+                                        keepalive = link
+                                            .heartbeat
+                                            .as_ref()
+                                            .map(|heartbeat| session::Keepalive::start(heartbeat, Instant::now()));
+                                        let ev = CommEvent::SessionEstablished {
                                             transport: transport.to_string(),
-                                            message: err.message,
-                                            dropped_bytes: err.dropped_bytes,
-                                            timestamp_ms: now_ms(),
+                                            peer_name: info.peer_name.clone(),
+                                            peer_capabilities: info.peer_capabilities,
+                                            capabilities: info.capabilities,
+                                            wire_version: info.wire_version,
+                                            timestamp_ms: info.established_at_ms,
                                         };
-                                        if !emit_hmip_event(app, &ev) {
+                                        state.set_session(Some(info));
+                                        if !emit_event(app, &ev) {
                                             return ConnectionExit::Shutdown;
                                         }
-                                        // 继续尝试解析后续帧（decoder 内部已重同步）
-                                        continue;
+                                    }
+                                    Err(reason) => {
+                                        return ConnectionExit::IoError(
+                                            format!("Handshake rejected: {}", reason).into(),
+                                        );
                                     }
                                 }
                             }
+
+                            if let Ok(msg) = &decoded {
+                                alarm::observe_message(app, &state.shared.alarms, transport, msg);
+                            }
+
+                            let ev = message_event(&state.shared, transport, &frame, decoded, response_method);
+                            if !emit_hmip_event(app, &ev) {
+                                return ConnectionExit::Shutdown;
+                            }
+                        }
+
+                        // 数据报：帧不跨数据报，残缺帧丢弃，不与下一个数据报（可能来自其它来源）拼接
+                        if datagram {
+                            if let Some(ev) = rx.get(source, now).end_datagram(transport) {
+                                if !emit_hmip_event(app, &ev) {
+                                    return ConnectionExit::Shutdown;
+                                }
+                            }
                         }
                     }
                     Err(err) => {
-                        return ConnectionExit::IoError(format!("Read failed: {}", err));
+                        return ConnectionExit::IoError(transport::TransportError::new(
+                            format!("Read failed: {}", err),
+                            tls::error_reason(&err),
+                        ));
                     }
                 }
             }
@@ -444,232 +1376,307 @@ async fn run_io_loop<S: AsyncRead + AsyncWrite + Unpin>(
     }
 }
 
-pub fn spawn_serial_actor(
+/// 抓包回放句柄（停止方式与连接 actor 一致：oneshot 通知 + 等待任务结束）
+pub struct ReplayHandle {
+    stop_tx: oneshot::Sender<()>,
+    join: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl ReplayHandle {
+    pub async fn stop(self) {
+        let _ = self.stop_tx.send(());
+        if let Err(err) = self.join.await {
+            log::warn!("HMIP replay task ended with error: {}", err);
+        }
+    }
+}
+
+/// 回放抓包：rx 原始字节重新经过接收流水线，按记录中的 transport 推送 hmip-event
+///
+/// 说明：
+/// - speed：1.0 为按原始节奏，>1 加速，0 表示不等待（尽快回放）
+/// - 只回放 rx 原始字节；tx 与完整帧记录用于离线查看，不参与回放
+/// - 回放不驱动会话/RPC（不回复对端、不影响实时连接的 seq 统计），
+///   Response 的 body 因缺少请求上下文不做 schema 解码
+pub fn spawn_replay(
     app: AppHandle,
-    config: serial::SerialConfig,
-    initial_stream: tokio_serial::SerialStream,
-) -> CommActorHandle {
-    let (tx_high, mut rx_high) = mpsc::channel::<Vec<u8>>(64);
-    let (tx_normal, mut rx_normal) = mpsc::channel::<Vec<u8>>(256);
-    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+    path: String,
+    reader: capture::CaptureReader<std::io::BufReader<std::fs::File>>,
+    speed: f64,
+    auth: Option<auth::FrameAuth>,
+    shared: CommShared,
+) -> ReplayHandle {
+    let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
 
     let join = tauri::async_runtime::spawn(async move {
-        let transport = "serial".to_string();
-        let mut attempt: u32 = 0;
-        let mut stream_opt = Some(initial_stream);
+        let _ = emit_hmip_event(
+            &app,
+            &HmipEvent::ReplayStarted {
+                path: path.clone(),
+                captured_at_ms: reader.created_ms(),
+                speed,
+                timestamp_ms: now_ms(),
+            },
+        );
 
-        loop {
-            let stream = if let Some(stream) = stream_opt.take() {
-                stream
-            } else {
-                match serial::open_stream(&config) {
-                    Ok(stream) => stream,
-                    Err(err) => {
-                        attempt = attempt.saturating_add(1);
-                        let delay_ms = compute_backoff_ms(attempt);
-                        let _ = emit_event(
-                            &app,
-                            &CommEvent::Error {
-                                transport: transport.clone(),
-                                message: err,
-                                timestamp_ms: now_ms(),
-                            },
-                        );
-                        let _ = emit_event(
-                            &app,
-                            &CommEvent::Reconnecting {
-                                transport: transport.clone(),
-                                attempt,
-                                delay_ms,
-                                timestamp_ms: now_ms(),
-                            },
-                        );
+        let mut pipelines: std::collections::HashMap<String, RxPipeline> = std::collections::HashMap::new();
+        let mut tx_decoders: std::collections::HashMap<String, proto::FrameDecoder> = std::collections::HashMap::new();
+        let new_pipeline = || RxPipeline::new(fragment::FragmentConfig::default(), auth.as_ref(), None);
+        let mut records: u64 = 0;
+        let mut stopped = false;
+        let mut error = None;
+        let mut origin: Option<(u64, Instant)> = None;
 
-                        tokio::select! {
-                            _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
-                            _ = &mut shutdown_rx => break,
-                        }
-                        continue;
-                    }
+        'replay: for record in reader {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    error = Some(err);
+                    break;
                 }
             };
+            if record.kind != capture::RecordKind::Raw {
+                continue;
+            }
+            if record.direction == capture::Direction::Tx {
+                // 本端发出的 HELLO/HELLO_ACK 带有当时连接的会话随机数，之后的认证帧按它校验
+                if let Some(auth) = auth.as_ref() {
+                    let decoder = tx_decoders
+                        .entry(record.transport.clone())
+                        .or_insert_with(|| proto::FrameDecoder::new(proto::DecoderConfig::default()));
+                    for nonce in sent_session_nonces(decoder, auth, &record.data) {
+                        pipelines
+                            .entry(record.transport.clone())
+                            .or_insert_with(new_pipeline)
+                            .verifier
+                            .rebind(nonce);
+                    }
+                }
+                continue;
+            }
 
-            attempt = 0;
-            if !emit_event(
-                &app,
-                &CommEvent::Connected {
-                    transport: transport.clone(),
-                    timestamp_ms: now_ms(),
-                },
-            ) {
+            // 按记录时间戳还原节奏（相对第一条 rx 记录）
+            let (first_ts, started) = *origin.get_or_insert((record.timestamp_ms, Instant::now()));
+            if speed > 0.0 {
+                let offset_ms = record.timestamp_ms.saturating_sub(first_ts) as f64 / speed;
+                let deadline = started + Duration::from_secs_f64(offset_ms / 1000.0);
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {}
+                    _ = &mut stop_rx => {
+                        stopped = true;
+                        break;
+                    }
+                }
+            } else if stop_rx.try_recv().is_ok() {
+                stopped = true;
                 break;
             }
 
-            match run_io_loop(
-                &app,
-                &transport,
-                stream,
-                &mut rx_high,
-                &mut rx_normal,
-                &mut shutdown_rx,
-            )
-            .await
-            {
-                ConnectionExit::Shutdown => break,
-                ConnectionExit::IoError(message) => {
-                    let _ = emit_event(
-                        &app,
-                        &CommEvent::Error {
-                            transport: transport.clone(),
-                            message,
-                            timestamp_ms: now_ms(),
-                        },
-                    );
-
-                    // 进入重连
-                    attempt = attempt.saturating_add(1);
-                    let delay_ms = compute_backoff_ms(attempt);
-                    let _ = emit_event(
-                        &app,
-                        &CommEvent::Reconnecting {
-                            transport: transport.clone(),
-                            attempt,
-                            delay_ms,
-                            timestamp_ms: now_ms(),
-                        },
-                    );
-
-                    tokio::select! {
-                        _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
-                        _ = &mut shutdown_rx => break,
+            records += 1;
+            let now = Instant::now();
+            let rx = pipelines.entry(record.transport.clone()).or_insert_with(new_pipeline);
+            let mut events = rx.expire(&record.transport, now);
+            for item in rx.push(&record.transport, &record.data, now) {
+                events.push(match item {
+                    RxItem::Event(ev) => ev,
+                    RxItem::Frame(frame) => {
+                        let decoded = proto::decode_message(&frame);
+                        message_event(&shared, &record.transport, &frame, decoded, None)
                     }
-                    continue;
+                });
+            }
+            for ev in events {
+                if !emit_hmip_event(&app, &ev) {
+                    break 'replay;
                 }
             }
+
+            // 尽快回放时让出执行权，避免长时间占用运行时线程
+            if speed <= 0.0 {
+                tokio::task::yield_now().await;
+            }
         }
 
-        let _ = emit_event(
+        let _ = emit_hmip_event(
             &app,
-            &CommEvent::Disconnected {
-                transport,
+            &HmipEvent::ReplayFinished {
+                path,
+                records,
+                stopped,
+                error,
                 timestamp_ms: now_ms(),
             },
         );
     });
 
-    CommActorHandle {
-        tx_high,
-        tx_normal,
-        shutdown_tx,
-        join,
+    ReplayHandle { stop_tx, join }
+}
+
+/// 从本端发出的字节中取出握手帧携带的会话随机数（抓包回放用）
+fn sent_session_nonces(decoder: &mut proto::FrameDecoder, auth: &auth::FrameAuth, bytes: &[u8]) -> Vec<u64> {
+    let mut nonces = Vec::new();
+    if decoder.push(bytes).is_err() {
+        return nonces;
     }
+    loop {
+        let frame = match decoder.next_frame() {
+            Ok(Some(frame)) if frame.header.flags & proto::FLAG_AUTH != 0 => match auth.open(frame, None) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            },
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        match proto::decode_message(&frame) {
+            Ok(proto::Message::Hello(v)) => nonces.extend(v.nonce),
+            Ok(proto::Message::HelloAck(v)) => nonces.extend(v.nonce),
+            _ => {}
+        }
+    }
+    nonces
 }
 
-pub fn spawn_tcp_actor(
+/// 为一条连接启动 actor：先使用已打开的 `initial_stream`，断线后按传输的重连策略重新打开
+///
+/// 说明：
+/// - 串口、TCP 客户端与监听模式的对端共用本函数与 `run_io_loop`
+/// - `on_exit` 在任务结束（主动关闭或不再重连）时调用，例如从对端表中移除自身
+pub fn spawn_actor<T: transport::Transport>(
     app: AppHandle,
-    config: tcp::TcpConfig,
-    initial_stream: tokio::net::TcpStream,
+    conn: T,
+    initial_stream: T::Stream,
+    auth: Option<Arc<auth::FrameAuth>>,
+    shared: CommShared,
+    outbox: Option<Arc<outbox::Outbox>>,
+    on_exit: Option<Box<dyn FnOnce() + Send>>,
 ) -> CommActorHandle {
-    let (tx_high, mut rx_high) = mpsc::channel::<Vec<u8>>(64);
-    let (tx_normal, mut rx_normal) = mpsc::channel::<Vec<u8>>(256);
-    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+    let link = conn.link();
+    let tx_queues = Arc::new(queue::TxQueues::new(
+        &link.tx_queues,
+        tx_drop_sink(app.clone(), conn.name()),
+    ));
+    let actor_queues = tx_queues.clone();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let mut inbox = ActorInbox {
+        queues: tx_queues.clone(),
+        outbox: outbox.clone(),
+        shutdown_rx,
+    };
+    let session = Arc::new(Mutex::new(None));
+    let session_slot = session.clone();
+    let fragmentation = link.fragmentation.clone();
+    let crc_policy = link.crc;
+    let tx_seq = Arc::new(sequence::SeqGenerator::new(link.seq));
+    let actor_tx_seq = tx_seq.clone();
+    let actor_auth = auth.clone();
+    let seq_stats = Arc::new(Mutex::new(sequence::SeqStats::default()));
+    let actor_seq_stats = seq_stats.clone();
+    let capture: capture::CaptureSlot = Arc::default();
+    let actor_capture = capture.clone();
+    let kind = conn.kind();
+    let target = conn.describe();
+    let link_up = Arc::new(AtomicBool::new(false));
+    let actor_link_up = link_up.clone();
 
     let join = tauri::async_runtime::spawn(async move {
-        let transport = "tcp".to_string();
+        let transport = conn.name().to_string();
+        let policy = conn.reconnect_policy();
+        // 握手角色未配置时按传输决定（监听模式的对端为 server）
+        let mut link = conn.link().clone();
+        if let Some(handshake) = link.handshake.as_mut() {
+            handshake.role.get_or_insert(conn.handshake_role());
+        }
         let mut attempt: u32 = 0;
         let mut stream_opt = Some(initial_stream);
+        let mut state = ActorState::new(
+            session_slot,
+            actor_auth,
+            actor_seq_stats,
+            actor_tx_seq,
+            actor_capture,
+            shared,
+            actor_queues,
+        );
 
         loop {
-            // 先使用现有连接，连接断开后再进入重连流程（如果 initial_stream 无效则直接进入重连）
-            let stream = if let Some(stream) = stream_opt.take() {
-                stream
-            } else {
-                match tcp::open_stream(&config).await {
-                    Ok(stream) => stream,
+            // 先使用现有连接，连接断开后再进入重连流程
+            let stream = match stream_opt.take() {
+                Some(stream) => Some(stream),
+                None => match conn.open().await {
+                    Ok(stream) => Some(stream),
                     Err(err) => {
-                        // 连接失败，进入重连，并有退避机制避免过于频繁的重试
-                        attempt = attempt.saturating_add(1);
-                        let delay_ms = compute_backoff_ms(attempt);
+                        log::warn!("Reconnect to {} failed: {}", conn.describe(), err.message);
                         let _ = emit_event(
                             &app,
                             &CommEvent::Error {
                                 transport: transport.clone(),
-                                message: err,
+                                message: err.message,
+                                reason: err.reason,
                                 timestamp_ms: now_ms(),
                             },
                         );
+                        None
+                    }
+                },
+            };
+
+            if let Some(stream) = stream {
+                attempt = 0;
+                if !emit_event(
+                    &app,
+                    &CommEvent::Connected {
+                        transport: transport.clone(),
+                        timestamp_ms: now_ms(),
+                    },
+                ) {
+                    break;
+                }
+                actor_link_up.store(true, Ordering::Relaxed);
+
+                let meta = conn.message_meta(&stream);
+                let exit = run_io_loop(&app, &transport, stream, &link, meta, &mut inbox, &mut state).await;
+                actor_link_up.store(false, Ordering::Relaxed);
+                match exit {
+                    ConnectionExit::Shutdown => break,
+                    ConnectionExit::IoError(err) => {
+                        state.on_connection_end(&format!("Connection lost: {}", err.message));
                         let _ = emit_event(
                             &app,
-                            &CommEvent::Reconnecting {
+                            &CommEvent::Error {
                                 transport: transport.clone(),
-                                attempt,
-                                delay_ms,
+                                message: err.message,
+                                reason: err.reason,
                                 timestamp_ms: now_ms(),
                             },
                         );
-
-                        tokio::select! {
-                            _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
-                            _ = &mut shutdown_rx => break,
-                        }
-                        continue;
                     }
                 }
-            };
+            }
 
-            attempt = 0;
-            if !emit_event(
+            // 进入重连，退避避免过于频繁的重试
+            attempt = attempt.saturating_add(1);
+            let Some(delay_ms) = policy.delay_ms(attempt) else {
+                break;
+            };
+            let _ = emit_event(
                 &app,
-                &CommEvent::Connected {
+                &CommEvent::Reconnecting {
                     transport: transport.clone(),
+                    attempt,
+                    delay_ms,
                     timestamp_ms: now_ms(),
                 },
-            ) {
-                break;
-            }
+            );
 
-            match run_io_loop(
-                &app,
-                &transport,
-                stream,
-                &mut rx_high,
-                &mut rx_normal,
-                &mut shutdown_rx,
-            )
-            .await
-            {
-                ConnectionExit::Shutdown => break,
-                ConnectionExit::IoError(message) => {
-                    let _ = emit_event(
-                        &app,
-                        &CommEvent::Error {
-                            transport: transport.clone(),
-                            message,
-                            timestamp_ms: now_ms(),
-                        },
-                    );
-
-                    attempt = attempt.saturating_add(1);
-                    let delay_ms = compute_backoff_ms(attempt);
-                    let _ = emit_event(
-                        &app,
-                        &CommEvent::Reconnecting {
-                            transport: transport.clone(),
-                            attempt,
-                            delay_ms,
-                            timestamp_ms: now_ms(),
-                        },
-                    );
-
-                    tokio::select! {
-                        _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
-                        _ = &mut shutdown_rx => break,
-                    }
-                    continue;
-                }
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
+                _ = &mut inbox.shutdown_rx => break,
             }
         }
 
+        state.on_connection_end("Connection closed");
+        inbox.queues.close();
         let _ = emit_event(
             &app,
             &CommEvent::Disconnected {
@@ -677,12 +1684,96 @@ pub fn spawn_tcp_actor(
                 timestamp_ms: now_ms(),
             },
         );
+        if let Some(on_exit) = on_exit {
+            on_exit();
+        }
     });
 
     CommActorHandle {
-        tx_high,
-        tx_normal,
+        tx_queues,
+        next_request_id: Arc::new(AtomicU32::new(1)),
+        next_fragment_id: AtomicU32::new(1),
+        fragmentation,
+        crc_policy,
+        tx_seq,
+        session,
+        auth,
+        seq_stats,
+        capture,
+        outbox,
+        kind,
+        target,
+        link_up,
+        created_at_ms: now_ms(),
         shutdown_tx,
         join,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire_seqs(frames: &[Vec<u8>]) -> Vec<(u8, u32)> {
+        let mut dec = proto::FrameDecoder::new(proto::DecoderConfig::default());
+        let mut seqs = Vec::new();
+        for bytes in frames {
+            dec.push(bytes).unwrap();
+            while let Some(frame) = dec.next_frame().unwrap() {
+                seqs.push((frame.header.msg_type, frame.header.seq));
+            }
+        }
+        seqs
+    }
+
+    #[test]
+    fn hello_precedes_frames_queued_before_reconnect() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let link = session::HmipLinkConfig {
+                handshake: Some(session::HandshakeConfig {
+                    role: Some(session::HandshakeRole::Client),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let tx_seq = sequence::SeqGenerator::new(link.seq);
+            let queues = queue::TxQueues::new(&link.tx_queues, Box::new(|_| {}));
+
+            // 第一次连接期间入队、尚未发出即断线
+            assert!(begin_connection(&link, None, &tx_seq).is_some());
+            for _ in 0..3 {
+                let frame = queue::HmipFrame {
+                    msg_type: proto::msg_type::EVENT,
+                    flags: 0,
+                    channel: 0,
+                    seq: tx_seq.assign(0),
+                    version: None,
+                    destination: None,
+                    payload: vec![0; 12],
+                };
+                assert!(queues
+                    .try_push(queue::QueueTarget::default(), queue::Outbound::Hmip(frame))
+                    .is_ok());
+            }
+
+            // 重连：HELLO 先取 1，排队帧出队时顺延为 2..4
+            let mut wire = vec![begin_connection(&link, None, &tx_seq).unwrap()];
+            for _ in 0..3 {
+                let queue::Outbound::Hmip(mut frame) = queues.pop().await else {
+                    panic!("expected queued HMIP frame");
+                };
+                wire.push(frame.encode(None, proto::VERSION_1, &tx_seq));
+            }
+
+            assert_eq!(
+                wire_seqs(&wire),
+                vec![
+                    (proto::msg_type::HELLO, 1),
+                    (proto::msg_type::EVENT, 2),
+                    (proto::msg_type::EVENT, 3),
+                    (proto::msg_type::EVENT, 4),
+                ]
+            );
+        });
+    }
+}
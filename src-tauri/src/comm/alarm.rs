@@ -0,0 +1,591 @@
+use crate::comm::{actor, proto};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+/// 后端告警：把设备上报的 HMIP ERROR / EVENT 映射为告警
+///
+/// 作用：告警的触发、清除与历史记录都在后端完成，WebView 未打开时同样生效；
+/// 前端通过 `alarm-event` 与 `list_alarms` 同步到 alarmStore。
+///
+/// 配置文件（JSON）示例：
+/// ```json
+/// {
+///   "errors": [
+///     { "code": 17, "alarm_id": "VACUUM_FAULT", "severity": "alarm", "text": "真空异常：{message}" }
+///   ],
+///   "events": [
+///     { "event_id": 100, "alarm_id": "DOOR_OPEN", "severity": "warning", "text": "腔门打开",
+///       "clear_event_id": 101 },
+///     { "event_id": 120, "alarm_id": "TEMP_HIGH", "text": "温度过高", "auto_clear_ms": 30000 }
+///   ]
+/// }
+/// ```
+///
+/// 约定：
+/// - 告警按 `(alarm_id, transport)` 区分；已激活时再次触发只累加次数、刷新 last_seen
+/// - 清除方式：`clear_event_id` 收到指定事件 / `auto_clear_ms` 超时未再触发 / 命令手动清除
+/// - text 支持 `{transport}`、`{code}`、`{message}`、`{event_id}` 占位，缺省时使用 alarm_id
+/// - 未配置规则的 code / event_id 不产生后端告警
+pub const DEFAULT_ALARM_RULES_FILE: &str = "hmip_alarms.json";
+pub const HISTORY_FILE: &str = "alarm_history.txt";
+
+const ALARM_EVENT_NAME: &str = "alarm-event";
+const EXPIRY_TICK_MS: u64 = 500;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmSeverity {
+    #[default]
+    Alarm,
+    Warning,
+    Info,
+}
+
+impl AlarmSeverity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Alarm => "alarm",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlarmRule {
+    pub alarm_id: String,
+    #[serde(default)]
+    pub severity: AlarmSeverity,
+    #[serde(default)]
+    pub text: String,
+    /// 超过该时长未再次触发则自动清除
+    pub auto_clear_ms: Option<u64>,
+    /// 收到该 event_id 时清除（同一 transport）
+    pub clear_event_id: Option<u16>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorAlarmRule {
+    pub code: u16,
+    #[serde(flatten)]
+    pub alarm: AlarmRule,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventAlarmRule {
+    pub event_id: u16,
+    #[serde(flatten)]
+    pub alarm: AlarmRule,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AlarmRuleFile {
+    #[serde(default)]
+    pub errors: Vec<ErrorAlarmRule>,
+    #[serde(default)]
+    pub events: Vec<EventAlarmRule>,
+}
+
+/// 当前生效的规则概况（`load_hmip_alarm_rules` / `get_hmip_alarm_rules_info` 返回值）
+#[derive(Debug, Clone, Serialize)]
+pub struct AlarmRulesInfo {
+    pub path: Option<String>,
+    pub errors: usize,
+    pub events: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct AlarmRules {
+    path: Option<String>,
+    errors: HashMap<u16, AlarmRule>,
+    events: HashMap<u16, AlarmRule>,
+    // clear_event_id → 被清除的 alarm_id
+    clears: HashMap<u16, Vec<String>>,
+}
+
+impl AlarmRules {
+    pub fn from_file(file: AlarmRuleFile) -> Result<Self, String> {
+        let mut rules = Self::default();
+        for rule in &file.errors {
+            validate_rule(&rule.alarm, None)?;
+        }
+        for rule in &file.events {
+            validate_rule(&rule.alarm, Some(rule.event_id))?;
+        }
+        for rule in file.errors {
+            rules.index_clear(&rule.alarm);
+            if rules.errors.insert(rule.code, rule.alarm).is_some() {
+                return Err(format!("Duplicate alarm rule for error code {}", rule.code));
+            }
+        }
+        for rule in file.events {
+            rules.index_clear(&rule.alarm);
+            if rules.events.insert(rule.event_id, rule.alarm).is_some() {
+                return Err(format!("Duplicate alarm rule for event_id {}", rule.event_id));
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read alarm rules {}: {}", path.display(), e))?;
+        let file: AlarmRuleFile = serde_json::from_str(&raw)
+            .map_err(|e| format!("Invalid alarm rules {}: {}", path.display(), e))?;
+        let mut rules = Self::from_file(file)?;
+        rules.path = Some(path.display().to_string());
+        Ok(rules)
+    }
+
+    pub fn info(&self) -> AlarmRulesInfo {
+        AlarmRulesInfo {
+            path: self.path.clone(),
+            errors: self.errors.len(),
+            events: self.events.len(),
+        }
+    }
+
+    fn index_clear(&mut self, rule: &AlarmRule) {
+        if let Some(event_id) = rule.clear_event_id {
+            self.clears.entry(event_id).or_default().push(rule.alarm_id.clone());
+        }
+    }
+}
+
+fn validate_rule(rule: &AlarmRule, event_id: Option<u16>) -> Result<(), String> {
+    if rule.alarm_id.trim().is_empty() {
+        return Err("Alarm rule alarm_id must not be empty".to_string());
+    }
+    if rule.auto_clear_ms == Some(0) {
+        return Err(format!("Alarm {} auto_clear_ms must be positive", rule.alarm_id));
+    }
+    if event_id.is_some() && rule.clear_event_id == event_id {
+        return Err(format!(
+            "Alarm {} is raised and cleared by the same event_id",
+            rule.alarm_id
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlarmSource {
+    Error { code: u16 },
+    Event { event_id: u16 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlarmRecord {
+    /// 本次激活的唯一 ID（确认/清除命令使用）
+    pub id: u64,
+    pub alarm_id: String,
+    pub transport: String,
+    pub severity: AlarmSeverity,
+    pub text: String,
+    pub source: AlarmSource,
+    pub raised_at_ms: u64,
+    pub last_seen_ms: u64,
+    /// 激活期间的触发次数（含首次）
+    pub occurrences: u64,
+    pub acknowledged: bool,
+    #[serde(skip)]
+    auto_clear_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClearReason {
+    Event,
+    Timeout,
+    Manual,
+}
+
+impl ClearReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Event => "event",
+            Self::Timeout => "timeout",
+            Self::Manual => "manual",
+        }
+    }
+}
+
+/// `alarm-event` 事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlarmEvent {
+    Raised {
+        alarm: AlarmRecord,
+    },
+    Cleared {
+        alarm: AlarmRecord,
+        reason: ClearReason,
+        timestamp_ms: u64,
+    },
+    Acknowledged {
+        alarm: AlarmRecord,
+        timestamp_ms: u64,
+    },
+}
+
+/// 后端告警表（激活中的告警 + 映射规则 + 历史文件）
+#[derive(Debug, Default)]
+pub struct AlarmManager {
+    rules: AlarmRules,
+    active: BTreeMap<u64, AlarmRecord>,
+    next_id: u64,
+    // 历史行投递到后台写线程（on_message 在收发循环中持锁调用，不能同步写文件）
+    history: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl AlarmManager {
+    /// 替换映射规则；已激活的告警保留，按原有的清除方式处理
+    pub fn set_rules(&mut self, rules: AlarmRules) {
+        self.rules = rules;
+    }
+
+    pub fn rules_info(&self) -> AlarmRulesInfo {
+        self.rules.info()
+    }
+
+    /// 设置历史文件路径（未设置时不落盘），由后台写线程追加
+    pub fn set_history_path(&mut self, path: PathBuf) {
+        self.history = Some(spawn_history_writer(path));
+    }
+
+    /// 激活中的告警（按触发先后）
+    pub fn active(&self) -> Vec<AlarmRecord> {
+        self.active.values().cloned().collect()
+    }
+
+    /// 处理一条设备消息：ERROR / EVENT 按规则触发或清除告警
+    pub fn on_message(&mut self, transport: &str, msg: &proto::Message, now_ms: u64) -> Vec<AlarmEvent> {
+        let mut events = Vec::new();
+        match msg {
+            proto::Message::Error(err) => {
+                if let Some(rule) = self.rules.errors.get(&err.code).cloned() {
+                    let text = render_text(&rule, transport, Some(err), None);
+                    events.extend(self.raise(transport, &rule, AlarmSource::Error { code: err.code }, text, now_ms));
+                }
+            }
+            proto::Message::Event(ev) => {
+                if let Some(alarm_ids) = self.rules.clears.get(&ev.event_id).cloned() {
+                    let cleared: Vec<u64> = self
+                        .active
+                        .values()
+                        .filter(|a| a.transport == transport && alarm_ids.contains(&a.alarm_id))
+                        .map(|a| a.id)
+                        .collect();
+                    for id in cleared {
+                        events.extend(self.clear_inner(id, ClearReason::Event, now_ms));
+                    }
+                }
+                if let Some(rule) = self.rules.events.get(&ev.event_id).cloned() {
+                    let text = render_text(&rule, transport, None, Some(ev.event_id));
+                    let source = AlarmSource::Event { event_id: ev.event_id };
+                    events.extend(self.raise(transport, &rule, source, text, now_ms));
+                }
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// 清除超过 auto_clear_ms 未再触发的告警
+    pub fn expire(&mut self, now_ms: u64) -> Vec<AlarmEvent> {
+        let expired: Vec<u64> = self
+            .active
+            .values()
+            .filter(|a| {
+                a.auto_clear_ms
+                    .is_some_and(|ms| now_ms.saturating_sub(a.last_seen_ms) >= ms)
+            })
+            .map(|a| a.id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.clear_inner(id, ClearReason::Timeout, now_ms))
+            .collect()
+    }
+
+    pub fn acknowledge(&mut self, id: u64, now_ms: u64) -> Result<AlarmEvent, String> {
+        let alarm = self
+            .active
+            .get_mut(&id)
+            .ok_or_else(|| format!("Alarm {} is not active", id))?;
+        alarm.acknowledged = true;
+        let alarm = alarm.clone();
+        self.write_history(now_ms, "ACK", &alarm, None);
+        Ok(AlarmEvent::Acknowledged {
+            alarm,
+            timestamp_ms: now_ms,
+        })
+    }
+
+    pub fn clear(&mut self, id: u64, now_ms: u64) -> Result<AlarmEvent, String> {
+        self.clear_inner(id, ClearReason::Manual, now_ms)
+            .ok_or_else(|| format!("Alarm {} is not active", id))
+    }
+
+    fn raise(
+        &mut self,
+        transport: &str,
+        rule: &AlarmRule,
+        source: AlarmSource,
+        text: String,
+        now_ms: u64,
+    ) -> Option<AlarmEvent> {
+        if let Some(alarm) = self
+            .active
+            .values_mut()
+            .find(|a| a.alarm_id == rule.alarm_id && a.transport == transport)
+        {
+            alarm.occurrences += 1;
+            alarm.last_seen_ms = now_ms;
+            return None;
+        }
+
+        self.next_id += 1;
+        let alarm = AlarmRecord {
+            id: self.next_id,
+            alarm_id: rule.alarm_id.clone(),
+            transport: transport.to_string(),
+            severity: rule.severity,
+            text,
+            source,
+            raised_at_ms: now_ms,
+            last_seen_ms: now_ms,
+            occurrences: 1,
+            acknowledged: false,
+            auto_clear_ms: rule.auto_clear_ms,
+        };
+        self.write_history(now_ms, "RAISE", &alarm, None);
+        self.active.insert(alarm.id, alarm.clone());
+        Some(AlarmEvent::Raised { alarm })
+    }
+
+    fn clear_inner(&mut self, id: u64, reason: ClearReason, now_ms: u64) -> Option<AlarmEvent> {
+        let alarm = self.active.remove(&id)?;
+        self.write_history(now_ms, "CLEAR", &alarm, Some(reason));
+        Some(AlarmEvent::Cleared {
+            alarm,
+            reason,
+            timestamp_ms: now_ms,
+        })
+    }
+
+    // 每行一条，Tab 分隔：ts_ms  动作  severity  alarm_id  transport  text  [清除原因]
+    fn write_history(&self, now_ms: u64, action: &str, alarm: &AlarmRecord, reason: Option<ClearReason>) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        let mut line = format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            now_ms,
+            action,
+            alarm.severity.as_str(),
+            alarm.alarm_id,
+            alarm.transport,
+            alarm.text.replace(['\t', '\r', '\n'], " ")
+        );
+        if let Some(reason) = reason {
+            line.push('\t');
+            line.push_str(reason.as_str());
+        }
+        line.push('\n');
+        // 写线程退出（替换了历史路径）时丢弃即可
+        let _ = history.send(line);
+    }
+}
+
+// 历史文件写线程：逐行追加，发送端全部释放后退出
+fn spawn_history_writer(path: PathBuf) -> mpsc::UnboundedSender<String> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    tauri::async_runtime::spawn_blocking(move || {
+        while let Some(line) = rx.blocking_recv() {
+            let result = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(line.as_bytes()));
+            if let Err(err) = result {
+                log::warn!("Failed to write alarm history {}: {}", path.display(), err);
+            }
+        }
+    });
+    tx
+}
+
+fn render_text(
+    rule: &AlarmRule,
+    transport: &str,
+    err: Option<&proto::ProtoError>,
+    event_id: Option<u16>,
+) -> String {
+    if rule.text.is_empty() {
+        return rule.alarm_id.clone();
+    }
+    let mut text = rule.text.replace("{transport}", transport);
+    if let Some(err) = err {
+        text = text
+            .replace("{code}", &err.code.to_string())
+            .replace("{message}", &err.message);
+    }
+    if let Some(event_id) = event_id {
+        text = text.replace("{event_id}", &event_id.to_string());
+    }
+    text
+}
+
+pub type SharedAlarms = Arc<Mutex<AlarmManager>>;
+
+pub fn emit_alarm_events(app: &AppHandle, events: &[AlarmEvent]) {
+    for ev in events {
+        if let Err(err) = app.emit(ALARM_EVENT_NAME, ev) {
+            log::warn!("Failed to emit alarm event (window may be closed): {}", err);
+        }
+    }
+}
+
+/// 实时链路收到消息后调用（回放不经过这里，避免历史抓包影响现场告警）
+pub fn observe_message(app: &AppHandle, alarms: &SharedAlarms, transport: &str, msg: &proto::Message) {
+    if !matches!(msg, proto::Message::Error(_) | proto::Message::Event(_)) {
+        return;
+    }
+    let events = match alarms.lock() {
+        Ok(mut manager) => manager.on_message(transport, msg, actor::now_ms()),
+        Err(_) => return,
+    };
+    emit_alarm_events(app, &events);
+}
+
+/// 后台定时处理 auto_clear_ms（随应用常驻）
+pub fn spawn_expiry(app: AppHandle, alarms: SharedAlarms) {
+    tauri::async_runtime::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_millis(EXPIRY_TICK_MS));
+        loop {
+            tick.tick().await;
+            let events = match alarms.lock() {
+                Ok(mut manager) => manager.expire(actor::now_ms()),
+                Err(_) => return,
+            };
+            emit_alarm_events(&app, &events);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn rules(json: &str) -> AlarmRules {
+        AlarmRules::from_file(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    fn event(event_id: u16) -> proto::Message {
+        proto::Message::Event(proto::Event {
+            event_id,
+            timestamp_ms: 0,
+            body: Bytes::new(),
+        })
+    }
+
+    #[test]
+    fn error_raises_once_and_event_clears() {
+        let mut manager = AlarmManager::default();
+        manager.set_rules(rules(
+            r#"{
+                "errors": [{ "code": 17, "alarm_id": "VAC", "text": "{transport}: {message} ({code})",
+                             "clear_event_id": 5 }]
+            }"#,
+        ));
+        let err = proto::Message::Error(proto::ProtoError {
+            code: 17,
+            message: "pressure".to_string(),
+        });
+
+        let raised = manager.on_message("tcp", &err, 100);
+        let Some(AlarmEvent::Raised { alarm }) = raised.first() else {
+            panic!("expected raised event");
+        };
+        assert_eq!(alarm.text, "tcp: pressure (17)");
+        assert_eq!(alarm.severity, AlarmSeverity::Alarm);
+
+        // 重复触发只累加次数；其他 transport 独立
+        assert!(manager.on_message("tcp", &err, 200).is_empty());
+        assert_eq!(manager.on_message("serial", &err, 200).len(), 1);
+        assert_eq!(manager.active()[0].occurrences, 2);
+
+        let cleared = manager.on_message("tcp", &event(5), 300);
+        assert!(matches!(
+            cleared.as_slice(),
+            [AlarmEvent::Cleared { reason: ClearReason::Event, .. }]
+        ));
+        assert_eq!(manager.active().len(), 1);
+        assert_eq!(manager.active()[0].transport, "serial");
+    }
+
+    #[test]
+    fn auto_clear_and_manual_commands() {
+        let mut manager = AlarmManager::default();
+        manager.set_rules(rules(
+            r#"{ "events": [
+                { "event_id": 1, "alarm_id": "TEMP", "severity": "warning", "auto_clear_ms": 1000 },
+                { "event_id": 2, "alarm_id": "DOOR" }
+            ] }"#,
+        ));
+        manager.on_message("tcp", &event(1), 0);
+        manager.on_message("tcp", &event(2), 0);
+        manager.on_message("tcp", &event(1), 800);
+
+        assert!(manager.expire(1_500).is_empty());
+        assert_eq!(manager.expire(1_800).len(), 1);
+
+        let door = manager.active()[0].id;
+        assert!(manager.acknowledge(door, 2_000).is_ok());
+        assert!(manager.active()[0].acknowledged);
+        assert!(manager.clear(door, 2_100).is_ok());
+        assert!(manager.clear(door, 2_200).is_err());
+    }
+
+    #[test]
+    fn history_is_written_by_background_writer() {
+        let path = std::env::temp_dir().join(format!("hmip_alarm_history_{}.txt", std::process::id()));
+        std::fs::remove_file(&path).ok();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut manager = AlarmManager::default();
+            manager.set_rules(rules(r#"{ "events": [{ "event_id": 1, "alarm_id": "DOOR", "clear_event_id": 2 }] }"#));
+            manager.set_history_path(path.clone());
+            manager.on_message("tcp", &event(1), 10);
+            manager.on_message("tcp", &event(2), 20);
+        });
+        // 释放 manager 后写线程收尾；runtime 关闭时等待其结束
+        drop(rt);
+
+        let history = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("10\tRAISE\talarm\tDOOR\ttcp"));
+        assert!(lines[1].ends_with("\tevent"));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let dup = r#"{ "errors": [{ "code": 1, "alarm_id": "A" }, { "code": 1, "alarm_id": "B" }] }"#;
+        assert!(AlarmRules::from_file(serde_json::from_str(dup).unwrap()).is_err());
+
+        let self_clear = r#"{ "events": [{ "event_id": 3, "alarm_id": "A", "clear_event_id": 3 }] }"#;
+        assert!(AlarmRules::from_file(serde_json::from_str(self_clear).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,587 @@
+use crate::comm::proto::{self, EncodeFrameParams, Frame};
+use bytes::Bytes;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use chacha20poly1305::aead::rand_core::RngCore;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// HMIP 帧认证/加密（FLAGS.AUTH=1）
+///
+/// 认证帧 payload 结构：
+/// - hmac_sha256：      [BODY][TAG(16)]            TAG = HMAC-SHA256(key, AAD || BODY) 截断前 16 字节
+/// - chacha20_poly1305：[NONCE(12)][CIPHERTEXT][TAG(16)]  AAD 作为附加数据参与认证
+///
+/// AAD = VERSION(u8) MSG_TYPE(u8) FLAGS(u8) CHANNEL(u8) SEQ(u32 LE) [SESSION_NONCE(u64 LE)]，
+/// 头部关键字段被篡改同样会导致校验失败。
+///
+/// 说明：
+/// - 每个连接一个预共享密钥（32 字节，hex 配置）；认证在分片之前逐帧进行
+/// - 防重放：按 channel 维护 seq 滑动窗口（窗口内已见过或早于窗口的 seq 一律拒绝）
+/// - 窗口随连接重建而清空（对端重启后 seq 会从头开始）；为防止旧连接的帧在新连接上被重放，
+///   每个连接生成随机的会话随机数并在 HELLO/HELLO_ACK 中交换，除握手帧外的认证帧都按
+///   接收方的随机数写入 AAD，换了连接的旧帧因随机数不同而校验失败
+pub const TAG_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+pub const KEY_LEN: usize = 32;
+
+// 滑动窗口用 u64 位图实现，窗口上限即 64
+const MAX_REPLAY_WINDOW: u32 = 64;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// 仅认证（payload 明文）
+    HmacSha256,
+    /// 认证 + 加密
+    #[serde(rename = "chacha20_poly1305")]
+    ChaCha20Poly1305,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub mode: AuthMode,
+    /// 预共享密钥（64 个十六进制字符）
+    pub key_hex: String,
+    /// 每个 channel 的防重放窗口大小（1..=64）
+    pub replay_window: u32,
+    /// 为 true 时丢弃未带 FLAGS.AUTH 的帧
+    pub require_auth: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            mode: AuthMode::HmacSha256,
+            key_hex: String::new(),
+            replay_window: MAX_REPLAY_WINDOW,
+            require_auth: true,
+        }
+    }
+}
+
+// 避免密钥随配置一起出现在日志里
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("mode", &self.mode)
+            .field("key_hex", &"<redacted>")
+            .field("replay_window", &self.replay_window)
+            .field("require_auth", &self.require_auth)
+            .finish()
+    }
+}
+
+/// 已解析密钥的认证器（连接建立前构造，密钥非法时 connect 直接报错）
+///
+/// 由配置构造的实例不绑定会话；每个连接经 `session()` 派生一份带本端随机数的实例，
+/// 握手收到对端随机数后用 `set_peer_nonce` 记下，之后发送的帧按对端随机数绑定
+pub struct FrameAuth {
+    mode: AuthMode,
+    key: [u8; KEY_LEN],
+    replay_window: u32,
+    require_auth: bool,
+    local_nonce: Option<u64>,
+    peer_nonce: Mutex<Option<u64>>,
+}
+
+impl FrameAuth {
+    pub fn from_config(cfg: &AuthConfig) -> Result<Self, String> {
+        let key = parse_key_hex(&cfg.key_hex)?;
+        Ok(Self {
+            mode: cfg.mode,
+            key,
+            replay_window: cfg.replay_window.clamp(1, MAX_REPLAY_WINDOW),
+            require_auth: cfg.require_auth,
+            local_nonce: None,
+            peer_nonce: Mutex::new(None),
+        })
+    }
+
+    /// 为一个新连接派生认证器：同一密钥，新的本端会话随机数
+    pub fn session(&self) -> Self {
+        Self {
+            mode: self.mode,
+            key: self.key,
+            replay_window: self.replay_window,
+            require_auth: self.require_auth,
+            local_nonce: Some(OsRng.next_u64()),
+            peer_nonce: Mutex::new(None),
+        }
+    }
+
+    /// 本端会话随机数（随 HELLO/HELLO_ACK 发给对端）
+    pub fn local_nonce(&self) -> Option<u64> {
+        self.local_nonce
+    }
+
+    pub fn set_peer_nonce(&self, nonce: u64) {
+        if let Ok(mut peer) = self.peer_nonce.lock() {
+            *peer = Some(nonce);
+        }
+    }
+
+    fn peer_nonce(&self) -> Option<u64> {
+        self.peer_nonce.lock().ok().and_then(|peer| *peer)
+    }
+
+    /// 封帧并附加认证（FLAGS.AUTH 自动置位；握手后按对端随机数绑定）
+    pub fn seal(&self, version: u8, params: EncodeFrameParams<'_>) -> Vec<u8> {
+        let flags = params.flags | proto::FLAG_AUTH;
+        let binding = session_binding(params.msg_type, self.peer_nonce());
+        let aad = build_aad(version, params.msg_type, flags, params.channel, params.seq, binding);
+        let sealed = match self.mode {
+            AuthMode::HmacSha256 => {
+                let mut out = Vec::with_capacity(params.payload.len() + TAG_LEN);
+                out.extend_from_slice(params.payload);
+                out.extend_from_slice(&self.hmac_tag(&aad, params.payload));
+                out
+            }
+            AuthMode::ChaCha20Poly1305 => {
+                let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let ciphertext = cipher
+                    .encrypt(
+                        &nonce,
+                        Payload {
+                            msg: params.payload,
+                            aad: &aad,
+                        },
+                    )
+                    .expect("chacha20poly1305 encryption is infallible for in-memory buffers");
+                let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+                out.extend_from_slice(&nonce);
+                out.extend_from_slice(&ciphertext);
+                out
+            }
+        };
+
+        proto::encode_frame_for(
+            version,
+            EncodeFrameParams {
+                flags,
+                payload: &sealed,
+                ..params
+            },
+        )
+    }
+
+    /// 校验认证帧并还原明文 payload（header.payload_len 同步为明文长度，FLAGS 保留 AUTH 位作为标记）
+    ///
+    /// `nonce` 为接收方的会话随机数（握手帧不参与绑定）
+    pub fn open(&self, frame: Frame, nonce: Option<u64>) -> Result<Frame, String> {
+        let header = frame.header;
+        let aad = build_aad(
+            header.version,
+            header.msg_type,
+            header.flags,
+            header.channel,
+            header.seq,
+            session_binding(header.msg_type, nonce),
+        );
+        let wire = frame.payload;
+
+        let plain: Bytes = match self.mode {
+            AuthMode::HmacSha256 => {
+                if wire.len() < TAG_LEN {
+                    return Err(format!("Auth payload too short (len={})", wire.len()));
+                }
+                let (body, tag) = wire.split_at(wire.len() - TAG_LEN);
+                self.hmac(&aad, body)
+                    .verify_truncated_left(tag)
+                    .map_err(|_| "Authentication tag mismatch".to_string())?;
+                wire.slice(..body.len())
+            }
+            AuthMode::ChaCha20Poly1305 => {
+                if wire.len() < NONCE_LEN + TAG_LEN {
+                    return Err(format!("Auth payload too short (len={})", wire.len()));
+                }
+                let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+                let nonce = Nonce::from_slice(&wire[..NONCE_LEN]);
+                cipher
+                    .decrypt(
+                        nonce,
+                        Payload {
+                            msg: &wire[NONCE_LEN..],
+                            aad: &aad,
+                        },
+                    )
+                    .map_err(|_| "Authentication tag mismatch".to_string())?
+                    .into()
+            }
+        };
+
+        let mut header = header;
+        header.payload_len = plain.len() as u32;
+        Ok(Frame {
+            header,
+            ext: frame.ext,
+            payload: plain,
+        })
+    }
+
+    fn hmac(&self, aad: &[u8], body: &[u8]) -> HmacSha256 {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(aad);
+        mac.update(body);
+        mac
+    }
+
+    fn hmac_tag(&self, aad: &[u8], body: &[u8]) -> [u8; TAG_LEN] {
+        let full = self.hmac(aad, body).finalize().into_bytes();
+        let mut tag = [0u8; TAG_LEN];
+        tag.copy_from_slice(&full[..TAG_LEN]);
+        tag
+    }
+}
+
+/// 按需封帧：未配置认证时等价于 `proto::encode_frame_for`
+pub fn encode_frame(auth: Option<&FrameAuth>, version: u8, params: EncodeFrameParams<'_>) -> Vec<u8> {
+    match auth {
+        Some(auth) => auth.seal(version, params),
+        None => proto::encode_frame_for(version, params),
+    }
+}
+
+/// 接收侧拒绝原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthReject {
+    /// 标签错误/缺少认证/本端未配置密钥
+    Auth(String),
+    /// seq 已见过或早于窗口
+    Replay { highest_seq: u32 },
+}
+
+/// 单个 channel 的防重放窗口（bit i 表示 highest - i 已收到）
+#[derive(Debug, Clone, Copy, Default)]
+struct ReplayWindow {
+    highest: u32,
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    fn accept(&mut self, seq: u32, size: u32) -> Result<(), u32> {
+        if self.bitmap == 0 {
+            self.highest = seq;
+            self.bitmap = 1;
+            return Ok(());
+        }
+
+        // 按序列号算术比较（RFC 1982）：u32 回绕后 0 仍视为在 u32::MAX 之后
+        let ahead = seq.wrapping_sub(self.highest);
+        if (ahead as i32) > 0 {
+            self.bitmap = if ahead >= 64 { 0 } else { self.bitmap << ahead };
+            self.bitmap |= 1;
+            self.highest = seq;
+            return Ok(());
+        }
+
+        let offset = self.highest.wrapping_sub(seq);
+        if offset >= size {
+            return Err(self.highest);
+        }
+        let bit = 1u64 << offset;
+        if self.bitmap & bit != 0 {
+            return Err(self.highest);
+        }
+        self.bitmap |= bit;
+        Ok(())
+    }
+}
+
+/// 接收侧校验器（每个连接一份，断线重建）
+pub struct FrameVerifier<'a> {
+    auth: Option<&'a FrameAuth>,
+    nonce: Option<u64>,
+    windows: HashMap<u8, ReplayWindow>,
+}
+
+impl<'a> FrameVerifier<'a> {
+    /// 按 `auth` 的本端会话随机数校验（未派生会话的认证器不做绑定）
+    pub fn new(auth: Option<&'a FrameAuth>) -> Self {
+        Self {
+            auth,
+            nonce: auth.and_then(FrameAuth::local_nonce),
+            windows: HashMap::new(),
+        }
+    }
+
+    /// 切换到另一个连接的会话随机数并清空窗口（抓包回放跨越重连时使用）
+    pub fn rebind(&mut self, nonce: u64) {
+        if self.nonce != Some(nonce) {
+            self.nonce = Some(nonce);
+            self.windows.clear();
+        }
+    }
+
+    /// 校验一帧：先验标签，通过后才推进防重放窗口（伪造帧不能污染窗口）
+    pub fn verify(&mut self, frame: Frame) -> Result<Frame, AuthReject> {
+        let authenticated = frame.header.flags & proto::FLAG_AUTH != 0;
+        let Some(auth) = self.auth else {
+            if authenticated {
+                return Err(AuthReject::Auth(
+                    "Authenticated frame received but no key is configured".to_string(),
+                ));
+            }
+            return Ok(frame);
+        };
+
+        if !authenticated {
+            if auth.require_auth {
+                return Err(AuthReject::Auth("Unauthenticated frame rejected".to_string()));
+            }
+            return Ok(frame);
+        }
+
+        let frame = auth.open(frame, self.nonce).map_err(AuthReject::Auth)?;
+        let header = frame.header;
+        self.windows
+            .entry(header.channel)
+            .or_default()
+            .accept(header.seq, auth.replay_window)
+            .map_err(|highest_seq| AuthReject::Replay { highest_seq })?;
+        Ok(frame)
+    }
+}
+
+// HELLO/HELLO_ACK 用于交换随机数，本身不绑定
+fn session_binding(msg_type: u8, nonce: Option<u64>) -> Option<u64> {
+    match msg_type {
+        proto::msg_type::HELLO | proto::msg_type::HELLO_ACK => None,
+        _ => nonce,
+    }
+}
+
+fn build_aad(version: u8, msg_type: u8, flags: u8, channel: u8, seq: u32, nonce: Option<u64>) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(16);
+    aad.extend_from_slice(&[version, msg_type, flags, channel]);
+    aad.extend_from_slice(&seq.to_le_bytes());
+    if let Some(nonce) = nonce {
+        aad.extend_from_slice(&nonce.to_le_bytes());
+    }
+    aad
+}
+
+fn parse_key_hex(hex: &str) -> Result<[u8; KEY_LEN], String> {
+    let hex = hex.trim();
+    if hex.len() != KEY_LEN * 2 {
+        return Err(format!(
+            "Auth key must be {} hex characters (got {})",
+            KEY_LEN * 2,
+            hex.len()
+        ));
+    }
+    // 先逐字符校验：非 ASCII 字符会让下面按字节切片时落在字符中间而 panic，
+    // from_str_radix 也会接受 "+f" 这类带符号的写法
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Auth key contains non-hex characters".to_string());
+    }
+    let mut key = [0u8; KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| "Auth key contains non-hex characters".to_string())?;
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn auth(mode: AuthMode) -> FrameAuth {
+        FrameAuth::from_config(&AuthConfig {
+            mode,
+            key_hex: KEY.to_string(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn decode_one(bytes: &[u8]) -> Frame {
+        let mut dec = proto::FrameDecoder::new(proto::DecoderConfig::default());
+        dec.push(bytes).unwrap();
+        dec.next_frame().unwrap().unwrap()
+    }
+
+    fn sealed(auth: &FrameAuth, channel: u8, seq: u32, payload: &[u8]) -> Vec<u8> {
+        auth.seal(
+            proto::VERSION_1,
+            EncodeFrameParams {
+                msg_type: proto::msg_type::REQUEST,
+                flags: proto::FLAG_CRC32,
+                channel,
+                seq,
+                payload,
+            },
+        )
+    }
+
+    #[test]
+    fn seal_open_roundtrip_for_both_modes() {
+        for mode in [AuthMode::HmacSha256, AuthMode::ChaCha20Poly1305] {
+            let auth = auth(mode);
+            let frame = decode_one(&sealed(&auth, 1, 10, b"start"));
+            assert_ne!(frame.header.flags & proto::FLAG_AUTH, 0);
+            if mode == AuthMode::ChaCha20Poly1305 {
+                assert!(!frame.payload.windows(5).any(|w| w == b"start"));
+            }
+
+            let mut verifier = FrameVerifier::new(Some(&auth));
+            let opened = verifier.verify(frame).unwrap();
+            assert_eq!(opened.payload.as_ref(), b"start");
+            assert_eq!(opened.header.payload_len, 5);
+        }
+    }
+
+    #[test]
+    fn tampered_payload_and_header_are_rejected() {
+        let auth = auth(AuthMode::HmacSha256);
+        let mut verifier = FrameVerifier::new(Some(&auth));
+
+        let mut frame = decode_one(&sealed(&auth, 0, 1, b"abc"));
+        let mut payload = frame.payload.to_vec();
+        payload[0] ^= 0xFF;
+        frame.payload = payload.into();
+        assert!(matches!(verifier.verify(frame), Err(AuthReject::Auth(_))));
+
+        let mut frame = decode_one(&sealed(&auth, 0, 2, b"abc"));
+        frame.header.channel = 3;
+        assert!(matches!(verifier.verify(frame), Err(AuthReject::Auth(_))));
+    }
+
+    #[test]
+    fn unauthenticated_frames_follow_require_auth() {
+        let plain = decode_one(&proto::encode_frame(EncodeFrameParams {
+            msg_type: proto::msg_type::REQUEST,
+            flags: 0,
+            channel: 0,
+            seq: 1,
+            payload: b"x",
+        }));
+
+        let strict = auth(AuthMode::HmacSha256);
+        assert!(FrameVerifier::new(Some(&strict)).verify(plain.clone()).is_err());
+
+        let lenient = FrameAuth::from_config(&AuthConfig {
+            key_hex: KEY.to_string(),
+            require_auth: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(FrameVerifier::new(Some(&lenient)).verify(plain).is_ok());
+
+        let frame = decode_one(&sealed(&strict, 0, 1, b"x"));
+        assert!(FrameVerifier::new(None).verify(frame).is_err());
+    }
+
+    #[test]
+    fn replay_window_rejects_duplicates_and_stale_seq() {
+        let auth = FrameAuth::from_config(&AuthConfig {
+            key_hex: KEY.to_string(),
+            replay_window: 4,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut verifier = FrameVerifier::new(Some(&auth));
+        let mut check = |channel: u8, seq: u32| {
+            verifier.verify(decode_one(&sealed(&auth, channel, seq, b"p")))
+        };
+
+        assert!(check(0, 10).is_ok());
+        assert!(check(0, 12).is_ok());
+        // 窗口内乱序到达：接受
+        assert!(check(0, 11).is_ok());
+        // 重复：拒绝
+        assert_eq!(check(0, 11).unwrap_err(), AuthReject::Replay { highest_seq: 12 });
+        // 早于窗口：拒绝
+        assert!(check(0, 20).is_ok());
+        assert!(matches!(check(0, 16), Err(AuthReject::Replay { .. })));
+        // 其他 channel 独立计数
+        assert!(check(1, 11).is_ok());
+    }
+
+    #[test]
+    fn frames_from_another_session_are_rejected() {
+        let base = auth(AuthMode::ChaCha20Poly1305);
+        let (client, server) = (base.session(), base.session());
+        client.set_peer_nonce(server.local_nonce().unwrap());
+
+        let bytes = sealed(&client, 0, 1, b"cmd");
+        assert!(FrameVerifier::new(Some(&server)).verify(decode_one(&bytes)).is_ok());
+
+        // 同一帧重放到新连接：窗口已清空，但随机数不同
+        let reconnected = base.session();
+        assert!(matches!(
+            FrameVerifier::new(Some(&reconnected)).verify(decode_one(&bytes)),
+            Err(AuthReject::Auth(_))
+        ));
+
+        // 握手帧不绑定，新连接上仍能校验
+        let hello = client.seal(
+            proto::VERSION_1,
+            EncodeFrameParams {
+                msg_type: proto::msg_type::HELLO,
+                flags: 0,
+                channel: 0,
+                seq: 2,
+                payload: b"hi",
+            },
+        );
+        assert!(FrameVerifier::new(Some(&reconnected)).verify(decode_one(&hello)).is_ok());
+    }
+
+    #[test]
+    fn replay_window_follows_seq_wraparound() {
+        let auth = auth(AuthMode::HmacSha256);
+        let mut verifier = FrameVerifier::new(Some(&auth));
+        let mut check = |seq: u32| verifier.verify(decode_one(&sealed(&auth, 0, seq, b"p")));
+
+        assert!(check(u32::MAX - 1).is_ok());
+        assert!(check(u32::MAX).is_ok());
+        // 回绕后继续接受
+        assert!(check(0).is_ok());
+        assert!(check(1).is_ok());
+        // 回绕前的帧仍在窗口内：重复拒绝，未见过的接受
+        assert_eq!(check(u32::MAX).unwrap_err(), AuthReject::Replay { highest_seq: 1 });
+        assert!(check(u32::MAX - 2).is_ok());
+        assert!(matches!(check(u32::MAX - 100), Err(AuthReject::Replay { .. })));
+    }
+
+    #[test]
+    fn key_must_be_32_hex_bytes() {
+        let short = AuthConfig {
+            key_hex: "abcd".to_string(),
+            ..Default::default()
+        };
+        assert!(FrameAuth::from_config(&short).is_err());
+
+        let bad = AuthConfig {
+            key_hex: "zz".repeat(KEY_LEN),
+            ..Default::default()
+        };
+        assert!(FrameAuth::from_config(&bad).is_err());
+
+        // 多字节字符：字节长度凑够 64 也不能 panic
+        let non_ascii = AuthConfig {
+            key_hex: format!("é{}", "a".repeat(KEY_LEN * 2 - 2)),
+            ..Default::default()
+        };
+        assert!(FrameAuth::from_config(&non_ascii).is_err());
+
+        let signed = AuthConfig {
+            key_hex: format!("+f{}", "a".repeat(KEY_LEN * 2 - 2)),
+            ..Default::default()
+        };
+        assert!(FrameAuth::from_config(&signed).is_err());
+    }
+}
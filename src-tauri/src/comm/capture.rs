@@ -0,0 +1,513 @@
+use bytes::Bytes;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use super::proto;
+
+/// HMIP 抓包文件（*.hmipcap）
+///
+/// 文件头（20 字节，小端）：
+/// - magic: b"HMIPCAP\0"
+/// - version: u16
+/// - reserved: u16
+/// - created_ms: u64
+///
+/// 每条记录：
+/// - timestamp_ms: u64
+/// - direction: u8（0=rx, 1=tx）
+/// - kind: u8（0=原始字节, 1=完整帧）
+/// - transport_len: u8
+/// - reserved: u8
+/// - data_len: u32
+/// - transport: [u8; transport_len]（UTF-8，如 "serial"/"tcp"）
+/// - data: [u8; data_len]
+///
+/// 完整帧记录的 data：version u8 + msg_type u8 + flags u8 + channel u8 + seq u32 + payload
+/// （认证解开、分片重组之后的帧，便于离线直接查看业务内容）
+pub const MAGIC: &[u8; 8] = b"HMIPCAP\0";
+pub const FORMAT_VERSION: u16 = 1;
+pub const FILE_EXTENSION: &str = "hmipcap";
+
+const FILE_HEADER_LEN: usize = 20;
+const RECORD_HEADER_LEN: usize = 16;
+const FRAME_RECORD_HEADER_LEN: usize = 8;
+// 单条记录 data 上限：高于解码器默认单帧上限（8 MiB），防止损坏/伪造文件的长度字段触发超大分配
+const MAX_RECORD_DATA_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx,
+    Tx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Raw,
+    Frame,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    pub timestamp_ms: u64,
+    pub direction: Direction,
+    pub kind: RecordKind,
+    pub transport: String,
+    pub data: Bytes,
+}
+
+/// 完整帧记录解析结果（回放只用原始字节，目前仅测试用于核对写入格式）
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRecord {
+    pub version: u8,
+    pub msg_type: u8,
+    pub flags: u8,
+    pub channel: u8,
+    pub seq: u32,
+    pub payload: Bytes,
+}
+
+#[cfg(test)]
+impl CaptureRecord {
+    pub fn frame(&self) -> Option<FrameRecord> {
+        if self.kind != RecordKind::Frame || self.data.len() < FRAME_RECORD_HEADER_LEN {
+            return None;
+        }
+        let d = &self.data;
+        Some(FrameRecord {
+            version: d[0],
+            msg_type: d[1],
+            flags: d[2],
+            channel: d[3],
+            seq: u32::from_le_bytes([d[4], d[5], d[6], d[7]]),
+            payload: self.data.slice(FRAME_RECORD_HEADER_LEN..),
+        })
+    }
+}
+
+pub struct CaptureWriter {
+    out: BufWriter<File>,
+    records: u64,
+}
+
+impl CaptureWriter {
+    pub fn create(path: &Path, created_ms: u64) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create capture file: {}", e))?;
+        let mut out = BufWriter::new(file);
+        let mut header = [0u8; FILE_HEADER_LEN];
+        header[..8].copy_from_slice(MAGIC);
+        header[8..10].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header[12..20].copy_from_slice(&created_ms.to_le_bytes());
+        out.write_all(&header)
+            .and_then(|_| out.flush())
+            .map_err(|e| format!("Failed to write capture header: {}", e))?;
+        Ok(Self { out, records: 0 })
+    }
+
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    pub fn write_raw(
+        &mut self,
+        timestamp_ms: u64,
+        direction: Direction,
+        transport: &str,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.write_record(timestamp_ms, direction, RecordKind::Raw, transport, &[], data)
+    }
+
+    pub fn write_frame(
+        &mut self,
+        timestamp_ms: u64,
+        direction: Direction,
+        transport: &str,
+        frame: &proto::Frame,
+    ) -> Result<(), String> {
+        let h = &frame.header;
+        let mut prefix = [0u8; FRAME_RECORD_HEADER_LEN];
+        prefix[0] = h.version;
+        prefix[1] = h.msg_type;
+        prefix[2] = h.flags;
+        prefix[3] = h.channel;
+        prefix[4..8].copy_from_slice(&h.seq.to_le_bytes());
+        self.write_record(timestamp_ms, direction, RecordKind::Frame, transport, &prefix, &frame.payload)
+    }
+
+    fn write_record(
+        &mut self,
+        timestamp_ms: u64,
+        direction: Direction,
+        kind: RecordKind,
+        transport: &str,
+        prefix: &[u8],
+        data: &[u8],
+    ) -> Result<(), String> {
+        let transport = transport.as_bytes();
+        let transport = &transport[..transport.len().min(u8::MAX as usize)];
+        let data_len = prefix.len() + data.len();
+        if data_len > MAX_RECORD_DATA_LEN {
+            return Err(format!("Capture record too large ({} bytes)", data_len));
+        }
+        let data_len = data_len as u32;
+
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        header[..8].copy_from_slice(&timestamp_ms.to_le_bytes());
+        header[8] = match direction {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+        };
+        header[9] = match kind {
+            RecordKind::Raw => 0,
+            RecordKind::Frame => 1,
+        };
+        header[10] = transport.len() as u8;
+        header[12..16].copy_from_slice(&data_len.to_le_bytes());
+
+        // 每条记录落盘后立即 flush：现场异常退出时抓包仍然可用
+        self.out
+            .write_all(&header)
+            .and_then(|_| self.out.write_all(transport))
+            .and_then(|_| self.out.write_all(prefix))
+            .and_then(|_| self.out.write_all(data))
+            .and_then(|_| self.out.flush())
+            .map_err(|e| format!("Failed to write capture record: {}", e))?;
+        self.records += 1;
+        Ok(())
+    }
+}
+
+/// 连接上的抓包开关（actor 与命令共享）
+pub type CaptureSlot = Arc<Mutex<Option<CaptureSession>>>;
+
+// 写线程落后时最多积压的记录数，超出的记录丢弃（不阻塞收发循环）
+const CAPTURE_QUEUE_LEN: usize = 1024;
+
+enum PendingRecord {
+    Raw {
+        timestamp_ms: u64,
+        direction: Direction,
+        transport: String,
+        data: Vec<u8>,
+    },
+    Frame {
+        timestamp_ms: u64,
+        direction: Direction,
+        transport: String,
+        frame: proto::Frame,
+    },
+}
+
+/// 进行中的抓包：actor 只把记录投递到通道，由后台写线程落盘
+pub struct CaptureSession {
+    tx: mpsc::Sender<PendingRecord>,
+    dropped: Arc<AtomicU64>,
+    join: tauri::async_runtime::JoinHandle<u64>,
+}
+
+impl CaptureSession {
+    pub fn start(mut writer: CaptureWriter) -> Self {
+        let (tx, mut rx) = mpsc::channel::<PendingRecord>(CAPTURE_QUEUE_LEN);
+        let join = tauri::async_runtime::spawn_blocking(move || {
+            while let Some(record) = rx.blocking_recv() {
+                let result = match record {
+                    PendingRecord::Raw {
+                        timestamp_ms,
+                        direction,
+                        transport,
+                        data,
+                    } => writer.write_raw(timestamp_ms, direction, &transport, &data),
+                    PendingRecord::Frame {
+                        timestamp_ms,
+                        direction,
+                        transport,
+                        frame,
+                    } => writer.write_frame(timestamp_ms, direction, &transport, &frame),
+                };
+                // 写失败时结束写线程：后续投递失败，actor 随即关闭抓包
+                if let Err(err) = result {
+                    log::warn!("HMIP capture stopped: {}", err);
+                    break;
+                }
+            }
+            writer.records()
+        });
+        Self {
+            tx,
+            dropped: Arc::default(),
+            join,
+        }
+    }
+
+    /// 结束抓包：等待已投递的记录全部落盘，返回写入的记录数
+    pub async fn finish(self) -> u64 {
+        let Self { tx, dropped, join } = self;
+        drop(tx);
+        let dropped = dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            log::warn!("HMIP capture dropped {} records (writer fell behind)", dropped);
+        }
+        join.await.unwrap_or_default()
+    }
+
+    fn send(&self, record: PendingRecord) -> Result<(), ()> {
+        match self.tx.try_send(record) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(()),
+        }
+    }
+}
+
+/// 投递一条记录；写线程已因错误退出时关闭抓包，不影响链路本身
+pub fn record_raw(slot: &CaptureSlot, timestamp_ms: u64, direction: Direction, transport: &str, data: &[u8]) {
+    with_session(slot, || PendingRecord::Raw {
+        timestamp_ms,
+        direction,
+        transport: transport.to_string(),
+        data: data.to_vec(),
+    });
+}
+
+pub fn record_frame(
+    slot: &CaptureSlot,
+    timestamp_ms: u64,
+    direction: Direction,
+    transport: &str,
+    frame: &proto::Frame,
+) {
+    with_session(slot, || PendingRecord::Frame {
+        timestamp_ms,
+        direction,
+        transport: transport.to_string(),
+        frame: frame.clone(),
+    });
+}
+
+fn with_session(slot: &CaptureSlot, record: impl FnOnce() -> PendingRecord) {
+    let Ok(mut guard) = slot.lock() else {
+        return;
+    };
+    let Some(session) = guard.as_ref() else {
+        return;
+    };
+    if session.send(record()).is_err() {
+        *guard = None;
+    }
+}
+
+pub struct CaptureReader<R: Read> {
+    input: R,
+    created_ms: u64,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open capture file: {}", e))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut input: R) -> Result<Self, String> {
+        let mut header = [0u8; FILE_HEADER_LEN];
+        input
+            .read_exact(&mut header)
+            .map_err(|_| "Capture file header is truncated".to_string())?;
+        if &header[..8] != MAGIC {
+            return Err("Not an HMIP capture file".to_string());
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version != FORMAT_VERSION {
+            return Err(format!("Unsupported capture format version: {}", version));
+        }
+        let created_ms = u64::from_le_bytes(header[12..20].try_into().unwrap_or_default());
+        Ok(Self { input, created_ms })
+    }
+
+    pub fn created_ms(&self) -> u64 {
+        self.created_ms
+    }
+
+    /// 读取下一条记录；文件正常结束返回 Ok(None)，末尾残缺记录视为错误
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>, String> {
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        match read_full(&mut self.input, &mut header) {
+            Ok(0) => return Ok(None),
+            Ok(n) if n == RECORD_HEADER_LEN => {}
+            Ok(_) => return Err("Capture record header is truncated".to_string()),
+            Err(err) => return Err(format!("Failed to read capture file: {}", err)),
+        }
+
+        let timestamp_ms = u64::from_le_bytes(header[..8].try_into().unwrap_or_default());
+        let direction = match header[8] {
+            0 => Direction::Rx,
+            1 => Direction::Tx,
+            other => return Err(format!("Invalid capture direction: {}", other)),
+        };
+        let kind = match header[9] {
+            0 => RecordKind::Raw,
+            1 => RecordKind::Frame,
+            other => return Err(format!("Invalid capture record kind: {}", other)),
+        };
+        let transport_len = header[10] as usize;
+        let data_len = u32::from_le_bytes([header[12], header[13], header[14], header[15]]) as usize;
+        if data_len > MAX_RECORD_DATA_LEN {
+            return Err(format!("Capture record too large ({} bytes)", data_len));
+        }
+
+        // 经 take 读取：长度字段与实际内容不符时按残缺处理，而不是按声明长度预先分配
+        let body_len = transport_len + data_len;
+        let mut body = Vec::new();
+        (&mut self.input)
+            .take(body_len as u64)
+            .read_to_end(&mut body)
+            .map_err(|e| format!("Failed to read capture file: {}", e))?;
+        if body.len() != body_len {
+            return Err("Capture record body is truncated".to_string());
+        }
+        let data = Bytes::from(body.split_off(transport_len));
+        let transport = String::from_utf8(body).map_err(|_| "Invalid capture transport name".to_string())?;
+
+        Ok(Some(CaptureRecord {
+            timestamp_ms,
+            direction,
+            kind,
+            transport,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+// 与 read_exact 不同：返回实际读到的字节数，用于区分“文件结束”与“记录残缺”
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comm::proto::EncodeFrameParams;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.{}", name, std::process::id(), FILE_EXTENSION))
+    }
+
+    #[test]
+    fn writes_and_reads_raw_and_frame_records() {
+        let path = temp_path("hmip_capture_roundtrip");
+        let encoded = proto::encode_frame(EncodeFrameParams {
+            msg_type: proto::msg_type::EVENT,
+            flags: proto::FLAG_CRC32,
+            channel: 2,
+            seq: 42,
+            payload: b"payload",
+        });
+        let mut decoder = proto::FrameDecoder::new(proto::DecoderConfig::default());
+        decoder.push(&encoded).unwrap();
+        let frame = decoder.next_frame().unwrap().unwrap();
+
+        let mut writer = CaptureWriter::create(&path, 1_000).unwrap();
+        writer.write_raw(1_001, Direction::Rx, "tcp", &encoded).unwrap();
+        writer.write_frame(1_002, Direction::Rx, "tcp", &frame).unwrap();
+        writer.write_raw(1_003, Direction::Tx, "serial", b"").unwrap();
+        assert_eq!(writer.records(), 3);
+        drop(writer);
+
+        let reader = CaptureReader::open(&path).unwrap();
+        assert_eq!(reader.created_ms(), 1_000);
+        let records: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].kind, RecordKind::Raw);
+        assert_eq!(records[0].data.as_ref(), &encoded[..]);
+        assert_eq!(records[0].transport, "tcp");
+
+        let frame_record = records[1].frame().unwrap();
+        assert_eq!((frame_record.channel, frame_record.seq), (2, 42));
+        assert_eq!(frame_record.msg_type, proto::msg_type::EVENT);
+        assert_eq!(frame_record.payload.as_ref(), b"payload");
+
+        assert_eq!(records[2].direction, Direction::Tx);
+        assert_eq!(records[2].transport, "serial");
+        assert!(records[2].data.is_empty());
+    }
+
+    #[test]
+    fn session_writes_records_in_background() {
+        let path = temp_path("hmip_capture_session");
+        let slot: CaptureSlot = Arc::default();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let written = rt.block_on(async {
+            let writer = CaptureWriter::create(&path, 0).unwrap();
+            *slot.lock().unwrap() = Some(CaptureSession::start(writer));
+            for i in 0..10u64 {
+                record_raw(&slot, i, Direction::Rx, "udp", &[i as u8; 4]);
+            }
+            let session = slot.lock().unwrap().take().unwrap();
+            session.finish().await
+        });
+        let records: Vec<_> = CaptureReader::open(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(written, 10);
+        assert_eq!(records.len(), 10);
+        assert_eq!(records[9].timestamp_ms, 9);
+        assert_eq!(records[9].data.as_ref(), &[9u8; 4]);
+    }
+
+    #[test]
+    fn rejects_foreign_and_truncated_files() {
+        assert!(CaptureReader::new(&b"NOTACAPTUREFILE....."[..]).is_err());
+
+        let path = temp_path("hmip_capture_truncated");
+        let mut writer = CaptureWriter::create(&path, 0).unwrap();
+        writer.write_raw(1, Direction::Rx, "tcp", b"abcdef").unwrap();
+        drop(writer);
+        let mut bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        bytes.truncate(bytes.len() - 2);
+
+        let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+        assert!(reader.next_record().is_err());
+
+        // 记录头声明 4 GiB data：直接报错，不按声明长度分配
+        let mut oversized = bytes[..FILE_HEADER_LEN].to_vec();
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        header[10] = 3;
+        header[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        oversized.extend_from_slice(&header);
+        oversized.extend_from_slice(b"tcp");
+        let err = CaptureReader::new(&oversized[..]).unwrap().next_record().unwrap_err();
+        assert!(err.contains("too large"));
+    }
+}
@@ -0,0 +1,468 @@
+use crate::comm::proto::{self, Frame, FrameHeader};
+use crate::comm::queue::HmipFrame;
+use crate::comm::sequence::TxSeq;
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// HMIP 分片（FLAGS.FRAGMENT=1）
+///
+/// 分片帧 payload 结构（小端）：
+/// [0..4)  FRAGMENT_ID (u32)  同一条大消息的所有分片相同
+/// [4..6)  INDEX (u16)        从 0 开始
+/// [6..8)  TOTAL (u16)        分片总数（>=1）
+/// [..]    chunk bytes
+///
+/// 说明：
+/// - 每个分片是独立的 HMIP 帧（独立 seq/CRC），msg_type/channel 与原消息一致
+/// - 分片之间可以穿插其他帧（如 high 优先级控制帧），避免大数据独占链路
+/// - 接收端按 (channel, msg_type, fragment_id) 重组，重组完成后按普通帧处理
+pub const FRAGMENT_PREFIX_LEN: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FragmentConfig {
+    /// 单个分片携带的最大 chunk 字节数（不含 8 字节前缀）；发送端超过该值才拆分
+    pub max_fragment_payload: usize,
+    /// 单条消息的重组超时（以最后一个分片到达时间起算）
+    pub reassembly_timeout_ms: u64,
+    /// 同时重组的消息数上限
+    pub max_streams: usize,
+    /// 单条重组消息的最大字节数
+    pub max_message_len: usize,
+    /// 全部重组缓冲的总字节数上限
+    pub max_buffered_bytes: usize,
+}
+
+impl Default for FragmentConfig {
+    fn default() -> Self {
+        Self {
+            max_fragment_payload: 1024,
+            reassembly_timeout_ms: 5000,
+            max_streams: 16,
+            max_message_len: 8 * 1024 * 1024,
+            max_buffered_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+pub struct FragmentParams<'a> {
+    pub msg_type: u8,
+    pub flags: u8,
+    pub channel: u8,
+    pub fragment_id: u32,
+    pub max_fragment_payload: usize,
+    pub payload: &'a [u8],
+}
+
+/// 把一条消息拆成若干分片帧；每帧通过 next_seq 分配独立序号，帧头（版本/认证）在出队时生成。
+pub fn encode_fragments(
+    params: FragmentParams<'_>,
+    mut next_seq: impl FnMut() -> TxSeq,
+) -> Result<Vec<HmipFrame>, String> {
+    let chunk_len = params.max_fragment_payload.max(1);
+    let total = params.payload.len().div_ceil(chunk_len).max(1);
+    if total > u16::MAX as usize {
+        return Err(format!(
+            "Payload too large to fragment (len={}, max_fragment_payload={})",
+            params.payload.len(),
+            chunk_len
+        ));
+    }
+
+    let mut frames = Vec::with_capacity(total);
+    for index in 0..total {
+        let start = index * chunk_len;
+        let end = (start + chunk_len).min(params.payload.len());
+        let chunk = &params.payload[start..end];
+
+        let mut payload = Vec::with_capacity(FRAGMENT_PREFIX_LEN + chunk.len());
+        payload.extend_from_slice(&params.fragment_id.to_le_bytes());
+        payload.extend_from_slice(&(index as u16).to_le_bytes());
+        payload.extend_from_slice(&(total as u16).to_le_bytes());
+        payload.extend_from_slice(chunk);
+
+        frames.push(HmipFrame {
+            msg_type: params.msg_type,
+            flags: params.flags | proto::FLAG_FRAGMENT,
+            channel: params.channel,
+            seq: next_seq(),
+            version: None,
+            destination: None,
+            payload,
+        });
+    }
+    Ok(frames)
+}
+
+/// 被丢弃的重组流（用于上报 `hmip-event`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FragmentDrop {
+    pub channel: u8,
+    pub msg_type: u8,
+    pub fragment_id: u32,
+    pub reason: String,
+    pub dropped_bytes: usize,
+}
+
+type StreamKey = (u8, u8, u32);
+
+struct Stream {
+    // index 0 分片的帧头：重组结果的 seq/flags/version 取自它，与分片到达顺序无关
+    head: Option<FrameHeader>,
+    total: usize,
+    // 按 index 存放已收到的分片：total 来自对端，不据此预分配
+    parts: BTreeMap<usize, Bytes>,
+    bytes: usize,
+    deadline: Instant,
+}
+
+pub struct Reassembler {
+    cfg: FragmentConfig,
+    streams: HashMap<StreamKey, Stream>,
+    buffered: usize,
+}
+
+impl Reassembler {
+    pub fn new(cfg: FragmentConfig) -> Self {
+        Self {
+            cfg,
+            streams: HashMap::new(),
+            buffered: 0,
+        }
+    }
+
+    /// 接收一个分片帧。
+    ///
+    /// - Ok(None)：分片已缓存，消息尚未完整
+    /// - Ok(Some(frame))：重组完成（FLAGS 已去掉 FRAGMENT/CRC32 位）
+    /// - Err(drop)：分片非法或超出限制，对应流已整体丢弃
+    pub fn push(&mut self, frame: Frame, now: Instant) -> Result<Option<Frame>, FragmentDrop> {
+        let header = frame.header;
+        if frame.payload.len() < FRAGMENT_PREFIX_LEN {
+            return Err(FragmentDrop {
+                channel: header.channel,
+                msg_type: header.msg_type,
+                fragment_id: 0,
+                reason: "Fragment payload too short".to_string(),
+                dropped_bytes: frame.payload.len(),
+            });
+        }
+
+        let fragment_id = u32::from_le_bytes(frame.payload[0..4].try_into().unwrap());
+        let index = u16::from_le_bytes(frame.payload[4..6].try_into().unwrap()) as usize;
+        let total = u16::from_le_bytes(frame.payload[6..8].try_into().unwrap()) as usize;
+        let chunk = frame.payload.slice(FRAGMENT_PREFIX_LEN..);
+        let key = (header.channel, header.msg_type, fragment_id);
+
+        if total == 0 || index >= total {
+            let dropped = self.drop_stream(&key) + chunk.len();
+            return Err(self.drop_info(
+                key,
+                format!("Invalid fragment index {} of {}", index, total),
+                dropped,
+            ));
+        }
+
+        if !self.streams.contains_key(&key) {
+            if self.streams.len() >= self.cfg.max_streams {
+                return Err(self.drop_info(
+                    key,
+                    format!("Too many reassembly streams (max={})", self.cfg.max_streams),
+                    chunk.len(),
+                ));
+            }
+            self.streams.insert(
+                key,
+                Stream {
+                    head: None,
+                    total,
+                    parts: BTreeMap::new(),
+                    bytes: 0,
+                    deadline: now,
+                },
+            );
+        }
+
+        let stream = self.streams.get_mut(&key).expect("stream inserted above");
+        if stream.total != total {
+            let dropped = self.drop_stream(&key) + chunk.len();
+            return Err(self.drop_info(key, "Fragment total changed mid-stream".to_string(), dropped));
+        }
+        if stream.parts.contains_key(&index) {
+            // 重复分片（链路重传等）：忽略即可，不影响重组
+            return Ok(None);
+        }
+        if stream.bytes + chunk.len() > self.cfg.max_message_len {
+            let dropped = self.drop_stream(&key) + chunk.len();
+            return Err(self.drop_info(
+                key,
+                format!("Reassembled message too large (max={})", self.cfg.max_message_len),
+                dropped,
+            ));
+        }
+        if self.buffered + chunk.len() > self.cfg.max_buffered_bytes {
+            let dropped = self.drop_stream(&key) + chunk.len();
+            return Err(self.drop_info(
+                key,
+                format!(
+                    "Reassembly buffer full (max_buffered_bytes={})",
+                    self.cfg.max_buffered_bytes
+                ),
+                dropped,
+            ));
+        }
+
+        let stream = self.streams.get_mut(&key).expect("stream checked above");
+        stream.bytes += chunk.len();
+        stream.deadline = now + Duration::from_millis(self.cfg.reassembly_timeout_ms);
+        stream.parts.insert(index, chunk.clone());
+        if index == 0 {
+            stream.head = Some(header);
+        }
+        self.buffered += chunk.len();
+
+        if stream.parts.len() < total {
+            return Ok(None);
+        }
+
+        let stream = self.streams.remove(&key).expect("stream complete");
+        self.buffered -= stream.bytes;
+
+        let mut payload = BytesMut::with_capacity(stream.bytes);
+        for part in stream.parts.into_values() {
+            payload.extend_from_slice(&part);
+        }
+
+        // parts 覆盖 0..total，index 0 必然已到达
+        let first = stream.head.expect("fragment 0 received");
+        Ok(Some(Frame {
+            header: FrameHeader {
+                version: first.version,
+                msg_type: first.msg_type,
+                flags: first.flags & !(proto::FLAG_FRAGMENT | proto::FLAG_CRC32),
+                channel: first.channel,
+                seq: first.seq,
+                payload_len: stream.bytes as u32,
+                payload_crc32: None,
+            },
+            ext: Bytes::new(),
+            payload: payload.freeze(),
+        }))
+    }
+
+    /// 清理超时未完成的流
+    pub fn expire(&mut self, now: Instant) -> Vec<FragmentDrop> {
+        let expired: Vec<StreamKey> = self
+            .streams
+            .iter()
+            .filter(|(_, stream)| stream.deadline <= now)
+            .map(|(key, _)| *key)
+            .collect();
+
+        expired
+            .into_iter()
+            .map(|key| {
+                let received = self.streams.get(&key).map(|s| (s.parts.len(), s.total));
+                let dropped = self.drop_stream(&key);
+                let (received, total) = received.unwrap_or_default();
+                self.drop_info(
+                    key,
+                    format!(
+                        "Reassembly timeout ({}ms, received {} of {} fragments)",
+                        self.cfg.reassembly_timeout_ms, received, total
+                    ),
+                    dropped,
+                )
+            })
+            .collect()
+    }
+
+    /// 最近一个流的超时时间点（无未完成流时为 None）
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.streams.values().map(|stream| stream.deadline).min()
+    }
+
+    fn drop_stream(&mut self, key: &StreamKey) -> usize {
+        match self.streams.remove(key) {
+            Some(stream) => {
+                self.buffered -= stream.bytes;
+                stream.bytes
+            }
+            None => 0,
+        }
+    }
+
+    fn drop_info(&self, key: StreamKey, reason: String, dropped_bytes: usize) -> FragmentDrop {
+        FragmentDrop {
+            channel: key.0,
+            msg_type: key.1,
+            fragment_id: key.2,
+            reason,
+            dropped_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comm::sequence::{SeqConfig, SeqGenerator};
+
+    fn decode_all(frames: &[Vec<u8>]) -> Vec<Frame> {
+        let mut dec = proto::FrameDecoder::new(proto::DecoderConfig::default());
+        let mut out = Vec::new();
+        for bytes in frames {
+            dec.push(bytes).unwrap();
+            while let Some(frame) = dec.next_frame().unwrap() {
+                out.push(frame);
+            }
+        }
+        out
+    }
+
+    fn fragments(payload: &[u8], fragment_id: u32, max_fragment_payload: usize) -> Vec<Frame> {
+        let mut seq = 0u32;
+        let frames = encode_fragments(
+            FragmentParams {
+                msg_type: proto::msg_type::EVENT,
+                flags: proto::FLAG_CRC32,
+                channel: 3,
+                fragment_id,
+                max_fragment_payload,
+                payload,
+            },
+            || {
+                seq += 1;
+                TxSeq::Explicit(seq)
+            },
+        )
+        .unwrap();
+        let tx_seq = SeqGenerator::new(SeqConfig::default());
+        let frames: Vec<Vec<u8>> = frames
+            .into_iter()
+            .map(|mut frame| frame.encode(None, proto::VERSION_1, &tx_seq))
+            .collect();
+        decode_all(&frames)
+    }
+
+    #[test]
+    fn split_and_reassemble_roundtrip() {
+        let payload: Vec<u8> = (0..2500u32).map(|v| v as u8).collect();
+        let frames = fragments(&payload, 9, 1000);
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|f| f.header.flags & proto::FLAG_FRAGMENT != 0));
+        assert_eq!(
+            frames.iter().map(|f| f.header.seq).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        let mut re = Reassembler::new(FragmentConfig::default());
+        let now = Instant::now();
+        assert_eq!(re.push(frames[0].clone(), now).unwrap(), None);
+        assert_eq!(re.push(frames[1].clone(), now).unwrap(), None);
+        let full = re.push(frames[2].clone(), now).unwrap().unwrap();
+
+        assert_eq!(full.header.msg_type, proto::msg_type::EVENT);
+        assert_eq!(full.header.channel, 3);
+        assert_eq!(full.header.seq, 1);
+        assert_eq!(full.header.flags & proto::FLAG_FRAGMENT, 0);
+        assert_eq!(full.payload.as_ref(), payload.as_slice());
+        assert_eq!(re.next_deadline(), None);
+    }
+
+    #[test]
+    fn out_of_order_and_duplicate_fragments() {
+        let payload = b"abcdefghij".to_vec();
+        let frames = fragments(&payload, 1, 4);
+        assert_eq!(frames.len(), 3);
+
+        let mut re = Reassembler::new(FragmentConfig::default());
+        let now = Instant::now();
+        assert_eq!(re.push(frames[2].clone(), now).unwrap(), None);
+        assert_eq!(re.push(frames[0].clone(), now).unwrap(), None);
+        assert_eq!(re.push(frames[0].clone(), now).unwrap(), None);
+        let full = re.push(frames[1].clone(), now).unwrap().unwrap();
+        assert_eq!(full.payload.as_ref(), payload.as_slice());
+        // 帧头取自 index 0 分片，而非最先到达的分片
+        assert_eq!(full.header.seq, 1);
+    }
+
+    #[test]
+    fn incomplete_stream_expires() {
+        let frames = fragments(&[0u8; 100], 5, 40);
+        let cfg = FragmentConfig {
+            reassembly_timeout_ms: 100,
+            ..Default::default()
+        };
+        let mut re = Reassembler::new(cfg);
+        let t0 = Instant::now();
+        re.push(frames[0].clone(), t0).unwrap();
+
+        assert!(re.expire(t0 + Duration::from_millis(50)).is_empty());
+        let drops = re.expire(t0 + Duration::from_millis(100));
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].fragment_id, 5);
+        assert_eq!(drops[0].dropped_bytes, 40);
+        assert!(drops[0].reason.contains("timeout"));
+        assert_eq!(re.next_deadline(), None);
+    }
+
+    #[test]
+    fn message_len_limit_drops_stream() {
+        let frames = fragments(&[0u8; 100], 2, 40);
+        let cfg = FragmentConfig {
+            max_message_len: 64,
+            ..Default::default()
+        };
+        let mut re = Reassembler::new(cfg);
+        let now = Instant::now();
+        re.push(frames[0].clone(), now).unwrap();
+
+        let drop = re.push(frames[1].clone(), now).unwrap_err();
+        assert!(drop.reason.contains("too large"));
+        assert_eq!(drop.dropped_bytes, 80);
+        assert_eq!(re.next_deadline(), None);
+    }
+
+    #[test]
+    fn stream_limit_rejects_new_streams() {
+        let cfg = FragmentConfig {
+            max_streams: 1,
+            ..Default::default()
+        };
+        let mut re = Reassembler::new(cfg);
+        let now = Instant::now();
+        re.push(fragments(&[0u8; 10], 1, 4)[0].clone(), now).unwrap();
+
+        let drop = re
+            .push(fragments(&[0u8; 10], 2, 4)[0].clone(), now)
+            .unwrap_err();
+        assert_eq!(drop.fragment_id, 2);
+        assert!(drop.reason.contains("Too many"));
+    }
+
+    #[test]
+    fn huge_total_does_not_preallocate() {
+        // total=65535 的单个分片只占用自身 chunk 的缓冲
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&7u32.to_le_bytes());
+        payload.extend_from_slice(&0u16.to_le_bytes());
+        payload.extend_from_slice(&u16::MAX.to_le_bytes());
+        payload.extend_from_slice(b"tiny");
+        let bytes = proto::encode_frame(proto::EncodeFrameParams {
+            msg_type: proto::msg_type::EVENT,
+            flags: proto::FLAG_FRAGMENT,
+            channel: 0,
+            seq: 1,
+            payload: &payload,
+        });
+
+        let mut re = Reassembler::new(FragmentConfig::default());
+        assert_eq!(re.push(decode_all(&[bytes])[0].clone(), Instant::now()).unwrap(), None);
+        assert_eq!(re.buffered, 4);
+        assert!(re.streams.values().all(|s| s.parts.len() == 1));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::comm::proto::status;
+use crate::system;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// HMI 侧 RPC 方法注册表：应答设备主动发来的 Request
+///
+/// 说明：
+/// - actor 收到 Request 后按 method 查表，在阻塞线程池中执行处理函数，
+///   把 status + body 编码为 Response 回给同一连接（同一 channel，高优先级队列）
+/// - 未注册的 method 回 `status::NOT_IMPLEMENTED`（body 为空）
+/// - Request 仍照常以 `hmip-event` 推送给前端
+///
+/// 内置方法（0xFF00 段保留给 HMI 侧；请求 body 须为空，应答 body 均为小端，字符串为 u16 长度前缀 + UTF-8）：
+/// - `HMI_INFO`：`version string + now_ms u64`
+/// - `READ_SYSTEM_OVERVIEW`：`uptime_s u64 + cpu f32 + memory f32 + disk f32 + temperature f32`
+///   （百分比 / 摄氏度；温度不可用时为 NaN）
+/// - `READ_OPERATOR`：`id string + name string + role string`；无登录用户时回 `status::UNAVAILABLE`
+pub mod method {
+    pub const HMI_INFO: u16 = 0xFF00;
+    pub const READ_SYSTEM_OVERVIEW: u16 = 0xFF01;
+    pub const READ_OPERATOR: u16 = 0xFF02;
+}
+
+pub struct RequestContext<'a> {
+    pub transport: &'a str,
+    pub request_id: u32,
+    pub method: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    pub fn ok(body: Vec<u8>) -> Self {
+        Self {
+            status: status::OK,
+            body,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            body: Vec::new(),
+        }
+    }
+}
+
+/// 处理函数在阻塞线程池中执行，可以做文件/系统调用；不要长时间占用（设备端通常有超时）
+pub type MethodHandler = Arc<dyn Fn(&RequestContext<'_>, &[u8]) -> Reply + Send + Sync>;
+
+/// 当前登录的操作员（前端登录/登出时通过 `set_hmi_operator` 同步）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorInfo {
+    pub id: String,
+    pub name: String,
+    pub role: String,
+}
+
+/// `list_hmi_methods` 返回值
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodInfo {
+    pub method: u16,
+    pub name: String,
+}
+
+struct Entry {
+    name: String,
+    handler: MethodHandler,
+}
+
+pub struct MethodRegistry {
+    handlers: RwLock<BTreeMap<u16, Entry>>,
+    operator: Arc<RwLock<Option<OperatorInfo>>>,
+}
+
+impl Default for MethodRegistry {
+    fn default() -> Self {
+        let registry = Self {
+            handlers: RwLock::new(BTreeMap::new()),
+            operator: Arc::default(),
+        };
+        registry.register_builtins();
+        registry
+    }
+}
+
+impl MethodRegistry {
+    /// 注册（或替换）method 的处理函数
+    pub fn register(&self, method: u16, name: impl Into<String>, handler: MethodHandler) {
+        if let Ok(mut handlers) = self.handlers.write() {
+            handlers.insert(
+                method,
+                Entry {
+                    name: name.into(),
+                    handler,
+                },
+            );
+        }
+    }
+
+    pub fn methods(&self) -> Vec<MethodInfo> {
+        let Ok(handlers) = self.handlers.read() else {
+            return Vec::new();
+        };
+        handlers
+            .iter()
+            .map(|(method, entry)| MethodInfo {
+                method: *method,
+                name: entry.name.clone(),
+            })
+            .collect()
+    }
+
+    pub fn set_operator(&self, operator: Option<OperatorInfo>) {
+        if let Ok(mut guard) = self.operator.write() {
+            *guard = operator;
+        }
+    }
+
+    /// 执行 method 对应的处理函数；未注册时回 NOT_IMPLEMENTED
+    pub fn dispatch(&self, ctx: &RequestContext<'_>, body: &[u8]) -> Reply {
+        // 先取出 handler 再执行，避免处理期间持有读锁
+        let handler = match self.handlers.read() {
+            Ok(handlers) => handlers.get(&ctx.method).map(|entry| entry.handler.clone()),
+            Err(_) => return Reply::status(status::INTERNAL_ERROR),
+        };
+        match handler {
+            Some(handler) => handler(ctx, body),
+            None => Reply::status(status::NOT_IMPLEMENTED),
+        }
+    }
+
+    fn register_builtins(&self) {
+        self.register(
+            method::HMI_INFO,
+            "hmi_info",
+            without_body(|| {
+                let mut body = Vec::new();
+                put_string(&mut body, env!("CARGO_PKG_VERSION"));
+                body.extend_from_slice(&crate::comm::actor::now_ms().to_le_bytes());
+                Reply::ok(body)
+            }),
+        );
+
+        self.register(
+            method::READ_SYSTEM_OVERVIEW,
+            "read_system_overview",
+            without_body(|| match system::read_system_overview() {
+                Ok(overview) => Reply::ok(encode_system_overview(&overview)),
+                Err(err) => {
+                    log::warn!("HMIP read_system_overview failed: {}", err);
+                    Reply::status(status::UNAVAILABLE)
+                }
+            }),
+        );
+
+        let operator = self.operator.clone();
+        self.register(
+            method::READ_OPERATOR,
+            "read_operator",
+            without_body(move || {
+                let current = operator.read().ok().and_then(|guard| guard.clone());
+                let Some(current) = current else {
+                    return Reply::status(status::UNAVAILABLE);
+                };
+                let mut body = Vec::new();
+                put_string(&mut body, &current.id);
+                put_string(&mut body, &current.name);
+                put_string(&mut body, &current.role);
+                Reply::ok(body)
+            }),
+        );
+    }
+}
+
+// 内置方法均不带参数：body 非空时回 INVALID_REQUEST
+fn without_body(f: impl Fn() -> Reply + Send + Sync + 'static) -> MethodHandler {
+    Arc::new(move |_, body| {
+        if body.is_empty() {
+            f()
+        } else {
+            Reply::status(status::INVALID_REQUEST)
+        }
+    })
+}
+
+fn encode_system_overview(overview: &system::SystemOverview) -> Vec<u8> {
+    let mut body = Vec::with_capacity(24);
+    body.extend_from_slice(&overview.uptime.to_le_bytes());
+    body.extend_from_slice(&(overview.cpu_usage as f32).to_le_bytes());
+    body.extend_from_slice(&(overview.memory_usage as f32).to_le_bytes());
+    body.extend_from_slice(&(overview.disk_usage as f32).to_le_bytes());
+    let temperature = overview.temperature.map(|t| t as f32).unwrap_or(f32::NAN);
+    body.extend_from_slice(&temperature.to_le_bytes());
+    body
+}
+
+/// u16 长度前缀 + UTF-8（与 schema 中 string 的默认编码一致），超长部分截断
+pub fn put_string(out: &mut Vec<u8>, s: &str) {
+    let mut end = s.len().min(u16::MAX as usize);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    out.extend_from_slice(&(end as u16).to_le_bytes());
+    out.extend_from_slice(&s.as_bytes()[..end]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(method: u16) -> RequestContext<'static> {
+        RequestContext {
+            transport: "tcp",
+            request_id: 1,
+            method,
+        }
+    }
+
+    #[test]
+    fn unknown_method_is_not_implemented() {
+        let registry = MethodRegistry::default();
+        assert_eq!(registry.dispatch(&ctx(0x1234), &[]), Reply::status(status::NOT_IMPLEMENTED));
+        assert!(registry
+            .methods()
+            .iter()
+            .any(|m| m.method == method::READ_SYSTEM_OVERVIEW));
+
+        registry.register(0x1234, "echo", Arc::new(|_, body| Reply::ok(body.to_vec())));
+        assert_eq!(registry.dispatch(&ctx(0x1234), b"hi"), Reply::ok(b"hi".to_vec()));
+    }
+
+    #[test]
+    fn operator_follows_login_state() {
+        let registry = MethodRegistry::default();
+        assert_eq!(
+            registry.dispatch(&ctx(method::READ_OPERATOR), &[]).status,
+            status::UNAVAILABLE
+        );
+
+        registry.set_operator(Some(OperatorInfo {
+            id: "op1".to_string(),
+            name: "张三".to_string(),
+            role: "operator".to_string(),
+        }));
+        assert_eq!(
+            registry.dispatch(&ctx(method::READ_OPERATOR), &[1]).status,
+            status::INVALID_REQUEST
+        );
+        let reply = registry.dispatch(&ctx(method::READ_OPERATOR), &[]);
+        assert_eq!(reply.status, status::OK);
+        let mut expected = Vec::new();
+        put_string(&mut expected, "op1");
+        put_string(&mut expected, "张三");
+        put_string(&mut expected, "operator");
+        assert_eq!(reply.body, expected);
+    }
+}
@@ -1,14 +1,61 @@
 pub mod actor;
+pub mod alarm;
+pub mod auth;
+pub mod capture;
+pub mod fragment;
+pub mod methods;
+pub mod outbox;
+pub mod payload;
 pub mod proto;
+pub mod queue;
+pub mod registry;
+pub mod rpc;
+pub mod schema;
+pub mod sequence;
 pub mod serial;
+pub mod server;
+pub mod session;
 pub mod tcp;
+pub mod tls;
+pub mod transfer;
+pub mod transport;
+pub mod udp;
+pub mod unix;
+pub mod ws;
 
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
 
 /// Communication state managed by Tauri
 #[derive(Default)]
 pub struct CommState {
-    pub serial: Arc<Mutex<Option<actor::CommActorHandle>>>,
-    pub tcp: Arc<Mutex<Option<actor::CommActorHandle>>>,
+    /// 串口/TCP 客户端连接，按连接 ID 索引（`serial`/`tcp` 为 connect_serial/connect_tcp 所用）
+    pub connections: registry::ConnectionRegistry,
+    /// TCP 监听模式（设备主动接入）
+    pub tcp_server: Arc<Mutex<Option<server::TcpServerHandle>>>,
+    /// 正在进行的抓包回放（同一时间只保留一个）
+    pub replay: Arc<Mutex<Option<actor::ReplayHandle>>>,
+    /// 进行中的 HMIP 文件传输
+    pub transfers: transfer::Transfers,
+    pub shared: CommShared,
+}
+
+/// 所有连接共享的后端资源（spawn actor 时整体传入）
+#[derive(Clone, Default)]
+pub struct CommShared {
+    /// HMIP body schema（可在运行期重新加载）
+    pub schemas: Arc<RwLock<schema::SchemaRegistry>>,
+    /// 被事件截断的完整 payload
+    pub payloads: Arc<std::sync::Mutex<payload::PayloadStore>>,
+    /// 由 HMIP ERROR/EVENT 映射出的后端告警
+    pub alarms: alarm::SharedAlarms,
+    /// 应答设备 Request 的 HMI 侧方法表
+    pub methods: Arc<methods::MethodRegistry>,
+}
+
+impl CommState {
+    /// 指定连接的槽（未登记的 ID 得到空槽）
+    pub fn slot(&self, id: &registry::ConnectionId) -> registry::ConnectionSlot {
+        self.connections.slot(id)
+    }
 }
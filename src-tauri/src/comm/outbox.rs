@@ -0,0 +1,593 @@
+use crate::comm::{actor, proto};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+/// 持久化发件箱（store-and-forward）
+///
+/// 说明：
+/// - 连接配置 `hmip.outbox` 后，经 `enqueue_hmip_outbox` 提交的消息先追加写入 Log 目录下的
+///   `hmip_outbox_<transport>.bin`，链路断开期间一直保留（应用重启后也会恢复）
+/// - actor 在连接建立（及握手完成）后按提交顺序逐条发出；帧头（seq/版本/认证）在发出时才编码，
+///   与当前会话一致
+/// - 写出成功即视为送达：内存队列立即出队，文件中的送达标记由阻塞线程池补写（不占用收发循环）；
+///   断线时正在写出的消息、或标记尚未落盘时应用退出，会在之后再发一次（至少一次）
+/// - 文件超过 max_bytes 时先压缩（去掉已送达记录），仍放不下则拒绝新消息
+///
+/// 记录格式（LE）：`len u32 + crc32 u32 + state u8 + body`，
+/// body = `id u64 + stored_ms u64 + msg_type u8 + flags u8 + channel u8 + payload`，
+/// crc32 覆盖 body；state 为 0（待发）/1（已送达）。文件尾部不完整或校验失败的记录在打开时截掉。
+pub const OUTBOX_EVENT_NAME: &str = "outbox-event";
+
+const DEFAULT_MAX_BYTES: u64 = 4 * 1024 * 1024;
+const HEADER_LEN: usize = 9;
+const BODY_FIXED_LEN: usize = 19;
+const STATE_OFFSET: u64 = 8;
+const STATE_PENDING: u8 = 0;
+const STATE_DELIVERED: u8 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxConfig {
+    /// 发件箱文件大小上限（字节）
+    pub max_bytes: u64,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+/// 一条待发消息（payload 已编码，帧头待发出时生成）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub stored_ms: u64,
+    pub msg_type: u8,
+    pub flags: u8,
+    pub channel: u8,
+    pub payload: Vec<u8>,
+}
+
+/// `outbox-event` 事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutboxEvent {
+    /// 打开时从磁盘恢复出未送达的消息
+    Recovered {
+        transport: String,
+        pending: usize,
+        timestamp_ms: u64,
+    },
+    Stored {
+        transport: String,
+        id: u64,
+        pending: usize,
+        timestamp_ms: u64,
+    },
+    /// 已写出到链路
+    Delivered {
+        transport: String,
+        id: u64,
+        seq: u32,
+        stored_ms: u64,
+        pending: usize,
+        timestamp_ms: u64,
+    },
+    Cleared {
+        transport: String,
+        count: usize,
+        timestamp_ms: u64,
+    },
+}
+
+/// `get_hmip_outbox` 返回值
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboxInfo {
+    pub transport: String,
+    pub path: String,
+    pub pending: usize,
+    pub pending_bytes: u64,
+    pub file_bytes: u64,
+    pub max_bytes: u64,
+    /// 本次打开以来送达的条数
+    pub delivered: u64,
+}
+
+pub type OutboxSink = Box<dyn Fn(OutboxEvent) + Send + Sync>;
+
+struct Pending {
+    entry: OutboxEntry,
+    offset: u64,
+}
+
+// 文件状态（仅命令与补写送达标记时访问；需同时持有两把锁时先锁 Store）
+struct Store {
+    file: File,
+    file_len: u64,
+}
+
+// 内存队列（收发循环只访问这一部分，不做文件 IO）
+struct Queue {
+    pending: VecDeque<Pending>,
+    next_id: u64,
+    delivered: u64,
+    // 文件被截断/压缩时递增，使之前记录的送达标记位置失效
+    generation: u64,
+}
+
+/// 已出队、尚未在文件中标记送达的消息（交给 `mark_delivered` 落盘）
+pub struct DeliveredMark {
+    offset: u64,
+    generation: u64,
+}
+
+pub struct Outbox {
+    transport: String,
+    path: PathBuf,
+    max_bytes: u64,
+    store: Mutex<Store>,
+    queue: Mutex<Queue>,
+    ready: Notify,
+    on_event: OutboxSink,
+}
+
+impl Outbox {
+    /// 打开（或创建）发件箱文件并恢复未送达的消息
+    pub fn open(
+        path: PathBuf,
+        config: &OutboxConfig,
+        transport: &str,
+        on_event: OutboxSink,
+    ) -> Result<Self, String> {
+        let mut file = open_file(&path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let (pending, valid_len) = parse_records(&data);
+        if valid_len < data.len() as u64 {
+            log::warn!(
+                "Outbox {} has a damaged tail; truncating {} bytes",
+                path.display(),
+                data.len() as u64 - valid_len
+            );
+        }
+        // 没有待发记录时直接清空文件
+        let file_len = if pending.is_empty() { 0 } else { valid_len };
+        if file_len < data.len() as u64 {
+            file.set_len(file_len)
+                .map_err(|e| format!("Failed to truncate {}: {}", path.display(), e))?;
+        }
+
+        let next_id = pending.iter().map(|p| p.entry.id + 1).max().unwrap_or(1);
+        let outbox = Self {
+            transport: transport.to_string(),
+            path,
+            max_bytes: config.max_bytes,
+            store: Mutex::new(Store { file, file_len }),
+            queue: Mutex::new(Queue {
+                pending,
+                next_id,
+                delivered: 0,
+                generation: 0,
+            }),
+            ready: Notify::new(),
+            on_event,
+        };
+        let recovered = outbox.queue()?.pending.len();
+        if recovered > 0 {
+            (outbox.on_event)(OutboxEvent::Recovered {
+                transport: outbox.transport.clone(),
+                pending: recovered,
+                timestamp_ms: actor::now_ms(),
+            });
+        }
+        Ok(outbox)
+    }
+
+    fn store(&self) -> Result<MutexGuard<'_, Store>, String> {
+        self.store
+            .lock()
+            .map_err(|_| "Outbox is poisoned".to_string())
+    }
+
+    fn queue(&self) -> Result<MutexGuard<'_, Queue>, String> {
+        self.queue
+            .lock()
+            .map_err(|_| "Outbox is poisoned".to_string())
+    }
+
+    /// 追加一条消息（落盘后返回 ID）
+    pub fn push(
+        &self,
+        msg_type: u8,
+        flags: u8,
+        channel: u8,
+        payload: Vec<u8>,
+    ) -> Result<u64, String> {
+        let (id, pending) = {
+            let mut store = self.store()?;
+            let record_len = (HEADER_LEN + BODY_FIXED_LEN + payload.len()) as u64;
+            if store.file_len + record_len > self.max_bytes {
+                self.compact(&mut store)?;
+            }
+            if store.file_len + record_len > self.max_bytes {
+                return Err(format!(
+                    "Outbox is full ({} messages pending, max_bytes={})",
+                    self.queue()?.pending.len(),
+                    self.max_bytes
+                ));
+            }
+
+            let entry = OutboxEntry {
+                id: self.queue()?.next_id,
+                stored_ms: actor::now_ms(),
+                msg_type,
+                flags,
+                channel,
+                payload,
+            };
+            let offset = store.file_len;
+            let record = encode_record(&entry, STATE_PENDING);
+            store
+                .file
+                .seek(SeekFrom::Start(offset))
+                .and_then(|_| store.file.write_all(&record))
+                .and_then(|_| store.file.sync_data())
+                .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+            store.file_len += record.len() as u64;
+
+            // 仍持有 Store 锁时入队，补写送达标记时看到的队列与文件一致
+            let mut queue = self.queue()?;
+            queue.next_id += 1;
+            let id = entry.id;
+            queue.pending.push_back(Pending { entry, offset });
+            (id, queue.pending.len())
+        };
+        self.ready.notify_one();
+        (self.on_event)(OutboxEvent::Stored {
+            transport: self.transport.clone(),
+            id,
+            pending,
+            timestamp_ms: actor::now_ms(),
+        });
+        Ok(id)
+    }
+
+    /// 等待下一条待发消息（不移除；写出成功后调用 `ack`）
+    pub async fn next(&self) -> OutboxEntry {
+        loop {
+            let ready = self.ready.notified();
+            let front = self
+                .queue()
+                .ok()
+                .and_then(|queue| queue.pending.front().map(|p| p.entry.clone()));
+            if let Some(entry) = front {
+                return entry;
+            }
+            ready.await;
+        }
+    }
+
+    /// 队首消息已写出：从内存队列移除（不做文件 IO），返回的标记交给 `mark_delivered` 落盘
+    pub fn ack(&self, id: u64, seq: u32) -> Option<DeliveredMark> {
+        let (done, pending, mark) = {
+            let mut queue = self.queue().ok()?;
+            if queue.pending.front().map(|p| p.entry.id) != Some(id) {
+                return None;
+            }
+            let done = queue.pending.pop_front()?;
+            queue.delivered += 1;
+            let mark = DeliveredMark {
+                offset: done.offset,
+                generation: queue.generation,
+            };
+            (done, queue.pending.len(), mark)
+        };
+        (self.on_event)(OutboxEvent::Delivered {
+            transport: self.transport.clone(),
+            id,
+            seq,
+            stored_ms: done.entry.stored_ms,
+            pending,
+            timestamp_ms: actor::now_ms(),
+        });
+        Some(mark)
+    }
+
+    /// 在文件中标记送达（队列已空时直接清空文件）；在阻塞线程池中调用
+    pub fn mark_delivered(&self, mark: DeliveredMark) {
+        let Ok(mut store) = self.store() else {
+            return;
+        };
+        let Ok(mut queue) = self.queue() else {
+            return;
+        };
+        let result = if queue.pending.is_empty() {
+            queue.generation += 1;
+            store.file_len = 0;
+            store.file.set_len(0)
+        } else if queue.generation == mark.generation {
+            store
+                .file
+                .seek(SeekFrom::Start(mark.offset + STATE_OFFSET))
+                .and_then(|_| store.file.write_all(&[STATE_DELIVERED]))
+        } else {
+            // 文件已压缩/清空，已送达的记录不在其中
+            Ok(())
+        };
+        if let Err(err) = result {
+            // 标记失败只会导致重启后重复发送
+            log::warn!("Failed to mark outbox entry delivered: {}", err);
+        }
+    }
+
+    /// 丢弃全部待发消息，返回条数
+    pub fn clear(&self) -> Result<usize, String> {
+        let count = {
+            let mut store = self.store()?;
+            let mut queue = self.queue()?;
+            let count = queue.pending.len();
+            queue.pending.clear();
+            queue.generation += 1;
+            store.file_len = 0;
+            store
+                .file
+                .set_len(0)
+                .map_err(|e| format!("Failed to truncate {}: {}", self.path.display(), e))?;
+            count
+        };
+        (self.on_event)(OutboxEvent::Cleared {
+            transport: self.transport.clone(),
+            count,
+            timestamp_ms: actor::now_ms(),
+        });
+        Ok(count)
+    }
+
+    pub fn info(&self) -> Result<OutboxInfo, String> {
+        let store = self.store()?;
+        let queue = self.queue()?;
+        Ok(OutboxInfo {
+            transport: self.transport.clone(),
+            path: self.path.display().to_string(),
+            pending: queue.pending.len(),
+            pending_bytes: queue
+                .pending
+                .iter()
+                .map(|p| p.entry.payload.len() as u64)
+                .sum(),
+            file_bytes: store.file_len,
+            max_bytes: self.max_bytes,
+            delivered: queue.delivered,
+        })
+    }
+
+    /// 只保留待发记录重写文件（先写临时文件再改名，避免中途掉电丢数据）
+    fn compact(&self, store: &mut Store) -> Result<(), String> {
+        let tmp = self.path.with_extension("tmp");
+        // 写文件期间持有队列锁：偏移量与 generation 必须和新文件同时生效
+        let mut queue = self.queue()?;
+        let mut data = Vec::new();
+        let mut offsets = Vec::with_capacity(queue.pending.len());
+        for pending in queue.pending.iter() {
+            offsets.push(data.len() as u64);
+            data.extend_from_slice(&encode_record(&pending.entry, STATE_PENDING));
+        }
+        std::fs::write(&tmp, &data)
+            .and_then(|_| File::open(&tmp).and_then(|f| f.sync_all()))
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| format!("Failed to compact {}: {}", self.path.display(), e))?;
+        store.file = open_file(&self.path)?;
+        store.file_len = data.len() as u64;
+        for (pending, offset) in queue.pending.iter_mut().zip(offsets) {
+            pending.offset = offset;
+        }
+        queue.generation += 1;
+        Ok(())
+    }
+}
+
+fn open_file(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+fn encode_record(entry: &OutboxEntry, state: u8) -> Vec<u8> {
+    let mut body = Vec::with_capacity(BODY_FIXED_LEN + entry.payload.len());
+    body.extend_from_slice(&entry.id.to_le_bytes());
+    body.extend_from_slice(&entry.stored_ms.to_le_bytes());
+    body.push(entry.msg_type);
+    body.push(entry.flags);
+    body.push(entry.channel);
+    body.extend_from_slice(&entry.payload);
+
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len());
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&proto::crc32_bytes(&body).to_le_bytes());
+    out.push(state);
+    out.extend_from_slice(&body);
+    out
+}
+
+/// 解析记录，返回待发消息与有效数据长度（之后的字节视为损坏）
+fn parse_records(data: &[u8]) -> (VecDeque<Pending>, u64) {
+    let mut pending = VecDeque::new();
+    let mut offset = 0usize;
+    while data.len() - offset >= HEADER_LEN {
+        let header = &data[offset..offset + HEADER_LEN];
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let state = header[8];
+        let start = offset + HEADER_LEN;
+        if len < BODY_FIXED_LEN || data.len() - start < len {
+            break;
+        }
+        let body = &data[start..start + len];
+        if proto::crc32_bytes(body) != crc {
+            break;
+        }
+        if state == STATE_PENDING {
+            pending.push_back(Pending {
+                entry: OutboxEntry {
+                    id: u64::from_le_bytes(body[0..8].try_into().unwrap_or_default()),
+                    stored_ms: u64::from_le_bytes(body[8..16].try_into().unwrap_or_default()),
+                    msg_type: body[16],
+                    flags: body[17],
+                    channel: body[18],
+                    payload: body[BODY_FIXED_LEN..].to_vec(),
+                },
+                offset: offset as u64,
+            });
+        }
+        offset = start + len;
+    }
+    (pending, offset as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("hmip_outbox_{}_{}.bin", name, std::process::id()))
+    }
+
+    fn open(path: &Path, max_bytes: u64) -> (Outbox, Arc<Mutex<Vec<OutboxEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let outbox = Outbox::open(
+            path.to_path_buf(),
+            &OutboxConfig { max_bytes },
+            "tcp",
+            Box::new(move |event| sink.lock().unwrap().push(event)),
+        )
+        .unwrap();
+        (outbox, events)
+    }
+
+    // actor 中 mark_delivered 在阻塞线程池执行，测试里同步调用
+    fn deliver(outbox: &Outbox, id: u64, seq: u32) {
+        if let Some(mark) = outbox.ack(id, seq) {
+            outbox.mark_delivered(mark);
+        }
+    }
+
+    #[test]
+    fn pending_entries_survive_reopen_in_order() {
+        let path = temp_path("reopen");
+        let _ = std::fs::remove_file(&path);
+        {
+            let (outbox, _) = open(&path, DEFAULT_MAX_BYTES);
+            assert_eq!(outbox.push(0x05, 0, 1, b"first".to_vec()).unwrap(), 1);
+            assert_eq!(outbox.push(0x05, 0, 1, b"second".to_vec()).unwrap(), 2);
+            assert_eq!(outbox.push(0x05, 0, 1, b"third".to_vec()).unwrap(), 3);
+            deliver(&outbox, 1, 10);
+            // 非队首的 ack 被忽略
+            deliver(&outbox, 3, 11);
+        }
+
+        let (outbox, events) = open(&path, DEFAULT_MAX_BYTES);
+        let info = outbox.info().unwrap();
+        assert_eq!(info.pending, 2);
+        assert!(matches!(
+            events.lock().unwrap()[0],
+            OutboxEvent::Recovered { pending: 2, .. }
+        ));
+        let pending: Vec<Vec<u8>> = outbox
+            .queue()
+            .unwrap()
+            .pending
+            .iter()
+            .map(|p| p.entry.payload.clone())
+            .collect();
+        assert_eq!(pending, vec![b"second".to_vec(), b"third".to_vec()]);
+        assert_eq!(outbox.push(0x05, 0, 1, b"fourth".to_vec()).unwrap(), 4);
+
+        deliver(&outbox, 2, 1);
+        deliver(&outbox, 3, 2);
+        deliver(&outbox, 4, 3);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn damaged_tail_is_truncated() {
+        let path = temp_path("damaged");
+        let _ = std::fs::remove_file(&path);
+        {
+            let (outbox, _) = open(&path, DEFAULT_MAX_BYTES);
+            outbox.push(0x05, 0, 0, b"ok".to_vec()).unwrap();
+        }
+        let mut data = std::fs::read(&path).unwrap();
+        let good_len = data.len();
+        data.extend_from_slice(&[0xFF; 12]);
+        std::fs::write(&path, &data).unwrap();
+
+        let (outbox, _) = open(&path, DEFAULT_MAX_BYTES);
+        assert_eq!(outbox.info().unwrap().pending, 1);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), good_len as u64);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn full_outbox_compacts_then_rejects() {
+        let path = temp_path("full");
+        let _ = std::fs::remove_file(&path);
+        let record = (HEADER_LEN + BODY_FIXED_LEN + 4) as u64;
+        let (outbox, _) = open(&path, record * 2);
+
+        outbox.push(0x05, 0, 0, vec![1; 4]).unwrap();
+        outbox.push(0x05, 0, 0, vec![2; 4]).unwrap();
+        assert!(outbox
+            .push(0x05, 0, 0, vec![3; 4])
+            .unwrap_err()
+            .contains("full"));
+
+        // 送达一条后压缩出空间
+        deliver(&outbox, 1, 1);
+        assert_eq!(outbox.push(0x05, 0, 0, vec![3; 4]).unwrap(), 3);
+        assert_eq!(outbox.info().unwrap().file_bytes, record * 2);
+        drop(outbox);
+
+        let (outbox, _) = open(&path, record * 2);
+        assert_eq!(outbox.info().unwrap().pending, 2);
+        assert_eq!(outbox.clear().unwrap(), 2);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn late_mark_after_compaction_is_ignored() {
+        let path = temp_path("late_mark");
+        let _ = std::fs::remove_file(&path);
+        let record = (HEADER_LEN + BODY_FIXED_LEN + 4) as u64;
+        let (outbox, _) = open(&path, record * 2);
+
+        outbox.push(0x05, 0, 0, vec![1; 4]).unwrap();
+        outbox.push(0x05, 0, 0, vec![2; 4]).unwrap();
+        let mark = outbox.ack(1, 1).unwrap();
+        // 标记落盘前文件被压缩：记录 2 移到偏移 0，迟到的标记不能覆盖它
+        outbox.push(0x05, 0, 0, vec![3; 4]).unwrap();
+        outbox.mark_delivered(mark);
+        drop(outbox);
+
+        let (outbox, _) = open(&path, record * 2);
+        let pending: Vec<u64> = outbox.queue().unwrap().pending.iter().map(|p| p.entry.id).collect();
+        assert_eq!(pending, vec![2, 3]);
+        std::fs::remove_file(&path).ok();
+    }
+}
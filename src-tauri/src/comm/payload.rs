@@ -0,0 +1,196 @@
+use bytes::Bytes;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+/// 近期完整 payload 缓存（按缓存分配的 id 索引）
+///
+/// 说明：
+/// - `hmip-event` 中的 body/payload 预览会被截断（HMIP_PAYLOAD_EMIT_MAX），
+///   超出部分由这里保存完整帧 payload，事件中携带 `payload_handle` 供按需拉取
+/// - 容量按条数与总字节数双重限制，超限时淘汰最早写入的条目
+/// - 每条 payload 分配唯一 id（seq 按 channel 计数、重连后从头开始，不能作为索引）
+pub const DEFAULT_MAX_ENTRIES: usize = 64;
+pub const DEFAULT_MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/// 事件中携带的句柄（`id` 作为 `fetch_hmip_payload` 的参数，transport/seq 便于对照）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PayloadHandle {
+    pub id: u64,
+    pub transport: String,
+    pub seq: u32,
+    pub len: usize,
+}
+
+struct Entry {
+    transport: String,
+    seq: u32,
+    data: Bytes,
+}
+
+/// 读取结果（一段 payload 及其所属帧）
+pub struct PayloadSlice {
+    pub transport: String,
+    pub seq: u32,
+    pub data: Bytes,
+    pub total_len: usize,
+}
+
+pub struct PayloadStore {
+    max_entries: usize,
+    max_bytes: usize,
+    entries: HashMap<u64, Entry>,
+    // 写入顺序（最早的在前），用于淘汰
+    order: VecDeque<u64>,
+    total_bytes: usize,
+    next_id: u64,
+}
+
+impl Default for PayloadStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES, DEFAULT_MAX_BYTES)
+    }
+}
+
+impl PayloadStore {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            max_bytes,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            total_bytes: 0,
+            next_id: 1,
+        }
+    }
+
+    /// 保存一份完整 payload；单条超过总容量时不保存并返回 None
+    pub fn insert(&mut self, transport: &str, seq: u32, payload: Bytes) -> Option<PayloadHandle> {
+        let len = payload.len();
+        if len > self.max_bytes {
+            return None;
+        }
+
+        while self.entries.len() >= self.max_entries || self.total_bytes + len > self.max_bytes {
+            if !self.evict_oldest() {
+                break;
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.order.push_back(id);
+        self.entries.insert(
+            id,
+            Entry {
+                transport: transport.to_string(),
+                seq,
+                data: payload,
+            },
+        );
+        self.total_bytes += len;
+
+        Some(PayloadHandle {
+            id,
+            transport: transport.to_string(),
+            seq,
+            len,
+        })
+    }
+
+    /// 读取完整 payload 或其中一段（length 缺省表示读到末尾）
+    pub fn get(&self, id: u64, offset: usize, length: Option<usize>) -> Result<PayloadSlice, String> {
+        let entry = self
+            .entries
+            .get(&id)
+            .ok_or_else(|| format!("Payload {} is no longer available", id))?;
+        let total = entry.data.len();
+        if offset > total {
+            return Err(format!("Offset {} is beyond payload length {}", offset, total));
+        }
+        let end = match length {
+            Some(length) => offset.saturating_add(length).min(total),
+            None => total,
+        };
+        Ok(PayloadSlice {
+            transport: entry.transport.clone(),
+            seq: entry.seq,
+            data: entry.data.slice(offset..end),
+            total_len: total,
+        })
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        let Some(id) = self.order.pop_front() else {
+            return false;
+        };
+        if let Some(old) = self.entries.remove(&id) {
+            self.total_bytes -= old.data.len();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(len: usize, fill: u8) -> Bytes {
+        Bytes::from(vec![fill; len])
+    }
+
+    #[test]
+    fn stores_and_reads_ranges() {
+        let mut store = PayloadStore::default();
+        let data = Bytes::from((0u8..100).collect::<Vec<_>>());
+        let handle = store.insert("tcp", 7, data).unwrap();
+        assert_eq!(handle.len, 100);
+
+        let full = store.get(handle.id, 0, None).unwrap();
+        assert_eq!((full.data.len(), full.total_len), (100, 100));
+        assert_eq!((full.transport.as_str(), full.seq), ("tcp", 7));
+
+        let part = store.get(handle.id, 90, Some(50)).unwrap();
+        assert_eq!(part.data.as_ref(), &(90u8..100).collect::<Vec<_>>()[..]);
+
+        assert!(store.get(handle.id, 101, None).is_err());
+        assert!(store.get(handle.id + 1, 0, None).is_err());
+    }
+
+    #[test]
+    fn evicts_oldest_by_count_and_bytes() {
+        let mut store = PayloadStore::new(2, 100);
+        let h1 = store.insert("tcp", 1, payload(40, 1)).unwrap();
+        let h2 = store.insert("tcp", 2, payload(40, 2)).unwrap();
+        let h3 = store.insert("tcp", 3, payload(10, 3)).unwrap();
+        assert!(store.get(h1.id, 0, None).is_err());
+
+        // 条数与字节数均超限：淘汰 h2 后即可放下，h3 保留
+        let h4 = store.insert("tcp", 4, payload(80, 4)).unwrap();
+        assert!(store.get(h2.id, 0, None).is_err());
+        assert!(store.get(h3.id, 0, None).is_ok());
+        assert_eq!(store.get(h4.id, 0, None).unwrap().total_len, 80);
+
+        // 仅字节数超限：需要连续淘汰
+        store.insert("tcp", 5, payload(95, 5));
+        assert!(store.get(h3.id, 0, None).is_err());
+        assert!(store.get(h4.id, 0, None).is_err());
+        assert_eq!(store.total_bytes, 95);
+
+        assert!(store.insert("tcp", 6, payload(101, 6)).is_none());
+    }
+
+    #[test]
+    fn same_seq_gets_distinct_entries() {
+        // 重连后 seq 从头开始：同一 (transport, seq) 的两条 payload 互不覆盖
+        let mut store = PayloadStore::new(4, 200);
+        let first = store.insert("tcp", 1, payload(50, 1)).unwrap();
+        let second = store.insert("tcp", 1, payload(60, 2)).unwrap();
+        assert_ne!(first.id, second.id);
+
+        let a = store.get(first.id, 0, Some(1)).unwrap();
+        let b = store.get(second.id, 0, Some(1)).unwrap();
+        assert_eq!((a.data[0], a.total_len), (1, 50));
+        assert_eq!((b.data[0], b.total_len), (2, 60));
+        assert_eq!(store.total_bytes, 110);
+    }
+}
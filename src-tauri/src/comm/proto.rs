@@ -1,7 +1,7 @@
 use bytes::{Buf, Bytes, BytesMut};
 use crc32fast::Hasher as Crc32;
 
-/// 协议：HMI Binary Protocol v1（HMIP）
+/// 协议：HMI Binary Protocol（HMIP），v1 与 v2 帧可在同一字节流中混用
 ///
 /// 设计目标：
 /// - 面向 TCP/Serial 等字节流：天然支持拆包/粘包
@@ -9,26 +9,48 @@ use crc32fast::Hasher as Crc32;
 /// - 可重同步：当流中出现噪声/错位时能尽快找回帧边界
 /// - 可扩展：version + msg_type/channel/flags/seq 作为长期演进主干
 ///
-/// 帧格式（小端）：
+/// v1 帧格式（小端）：
 /// [0..4)   MAGIC = "HMIP"
 /// [4]      VERSION = 1
 /// [5]      MSG_TYPE (u8)
-/// [6]      FLAGS (u8)  bit0=CRC32(payload) present
+/// [6]      FLAGS (u8)  bit0=CRC32(payload) present, bit1=FRAGMENT（见 fragment.rs）, bit2=AUTH（见 auth.rs）
 /// [7]      CHANNEL (u8)
 /// [8..12)  SEQ (u32 LE)
 /// [12..16) PAYLOAD_LEN (u32 LE)
 /// [16..20) PAYLOAD_CRC32 (u32 LE) 仅当 FLAGS.CRC32=1 存在
 /// [..]     PAYLOAD bytes
+///
+/// v2 帧格式（小端）：前 16 字节与 v1 相同（VERSION = 2），之后为：
+/// [16]     EXT_LEN (u8)  扩展字段长度
+/// [17]     reserved (=0)
+/// [18..22) HEADER_CRC32 (u32 LE) 覆盖 [0..18) + EXT
+/// [22..)   EXT bytes（EXT_LEN 字节，当前未定义具体字段，接收端透传）
+/// [..]     PAYLOAD_CRC32 (u32 LE) 仅当 FLAGS.CRC32=1 存在
+/// [..]     PAYLOAD bytes
+///
+/// v2 的头部 CRC 在信任 PAYLOAD_LEN 之前校验，避免长度字段被噪声篡改后
+/// 解码器一直等待一个并不存在的超长 payload。
 pub const MAGIC: [u8; 4] = *b"HMIP";
-pub const VERSION: u8 = 1;
+pub const VERSION_1: u8 = 1;
+pub const VERSION_2: u8 = 2;
 
 pub const FLAG_CRC32: u8 = 0x01;
+pub const FLAG_FRAGMENT: u8 = 0x02;
+pub const FLAG_AUTH: u8 = 0x04;
 
 pub const HEADER_LEN_BASE: usize = 16;
 pub const HEADER_LEN_WITH_CRC: usize = 20;
+pub const HEADER_V2_LEN_BASE: usize = 22;
+
+/// HELLO/HELLO_ACK capabilities 中由协议层保留的能力位
+pub mod capability {
+    /// 支持 v2 帧头；双方均声明时握手后切换到 v2
+    pub const HEADER_V2: u32 = 1 << 31;
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FrameHeader {
+    pub version: u8,
     pub msg_type: u8,
     pub flags: u8,
     pub channel: u8,
@@ -40,11 +62,30 @@ pub struct FrameHeader {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Frame {
     pub header: FrameHeader,
+    /// v2 扩展字段（v1 帧恒为空）
+    pub ext: Bytes,
     pub payload: Bytes,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// 缓冲区超限被清空
+    BufferOverflow,
+    /// 非帧边界数据被丢弃
+    Resync,
+    /// 遇到不支持的 VERSION（已跳过到下一处 magic）
+    UnsupportedVersion(u8),
+    /// v2 头部 CRC 不匹配
+    HeaderCrc,
+    PayloadTooLarge,
+    PayloadCrc,
+    /// 数据报末尾的不完整帧（面向数据报的传输中帧不跨数据报）
+    Truncated,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DecodeError {
+    pub kind: DecodeErrorKind,
     pub message: String,
     /// 为了重同步而丢弃的字节数（用于观测）
     pub dropped_bytes: usize,
@@ -91,6 +132,7 @@ impl FrameDecoder {
             let dropped = self.buf.len();
             self.buf.clear();
             return Err(DecodeError {
+                kind: DecodeErrorKind::BufferOverflow,
                 message: format!(
                     "Decoder buffer overflow (max_buffer_len={} bytes)",
                     self.cfg.max_buffer_len
@@ -103,6 +145,22 @@ impl FrameDecoder {
         Ok(())
     }
 
+    /// 丢弃缓冲中尚未组成完整帧的字节，返回丢弃说明（缓冲为空时为 None）
+    ///
+    /// 面向数据报的传输在每个数据报解析完后调用：残缺帧不会与下一个数据报拼接。
+    pub fn discard_partial(&mut self) -> Option<DecodeError> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let dropped = self.buf.len();
+        self.buf.clear();
+        Some(DecodeError {
+            kind: DecodeErrorKind::Truncated,
+            message: format!("Incomplete frame at end of datagram ({} bytes)", dropped),
+            dropped_bytes: dropped,
+        })
+    }
+
     /// 尝试解析一帧。
     ///
     /// - Ok(None)：数据不足
@@ -137,6 +195,7 @@ impl FrameDecoder {
                             dropped += n;
                         }
                         return Err(DecodeError {
+                            kind: DecodeErrorKind::Resync,
                             message: "Resync: magic not found".to_string(),
                             dropped_bytes: dropped,
                         });
@@ -145,6 +204,7 @@ impl FrameDecoder {
 
                 if dropped > 0 {
                     return Err(DecodeError {
+                        kind: DecodeErrorKind::Resync,
                         message: "Resync: dropped bytes before magic".to_string(),
                         dropped_bytes: dropped,
                     });
@@ -160,11 +220,43 @@ impl FrameDecoder {
 
             // 解析头部（小端）
             let version = self.buf[4];
-            if version != VERSION {
-                // 版本不匹配：丢弃当前 magic 的首字节，继续找下一处 magic
-                self.buf.advance(1);
-                dropped += 1;
-                continue;
+            let (fixed_len, ext_len) = match version {
+                VERSION_1 => (HEADER_LEN_BASE, 0usize),
+                VERSION_2 => {
+                    if self.buf.len() < HEADER_V2_LEN_BASE {
+                        return Ok(None);
+                    }
+                    (HEADER_V2_LEN_BASE, self.buf[16] as usize)
+                }
+                other => {
+                    // 版本不支持：跳到下一处 magic，并显式上报（便于灰度升级时观测）
+                    dropped += self.skip_to_next_magic();
+                    return Err(DecodeError {
+                        kind: DecodeErrorKind::UnsupportedVersion(other),
+                        message: format!("Unsupported HMIP version {}", other),
+                        dropped_bytes: dropped,
+                    });
+                }
+            };
+
+            if version == VERSION_2 {
+                // EXT_LEN 最多 255：即使该字段被篡改，最多也只多等 255 字节
+                if self.buf.len() < fixed_len + ext_len {
+                    return Ok(None);
+                }
+                let expected = u32::from_le_bytes(self.buf[18..22].try_into().unwrap());
+                let actual = header_crc32(&self.buf[..18], &self.buf[fixed_len..fixed_len + ext_len]);
+                if actual != expected {
+                    dropped += self.skip_to_next_magic();
+                    return Err(DecodeError {
+                        kind: DecodeErrorKind::HeaderCrc,
+                        message: format!(
+                            "Header CRC32 mismatch (expected={:#010x}, actual={:#010x})",
+                            expected, actual
+                        ),
+                        dropped_bytes: dropped,
+                    });
+                }
             }
 
             let msg_type = self.buf[5];
@@ -175,11 +267,8 @@ impl FrameDecoder {
                 u32::from_le_bytes(self.buf[12..16].try_into().unwrap());
 
             let has_crc = (flags & FLAG_CRC32) != 0;
-            let header_len = if has_crc {
-                HEADER_LEN_WITH_CRC
-            } else {
-                HEADER_LEN_BASE
-            };
+            let crc_offset = fixed_len + ext_len;
+            let header_len = if has_crc { crc_offset + 4 } else { crc_offset };
 
             if self.buf.len() < header_len {
                 return Ok(None);
@@ -190,6 +279,7 @@ impl FrameDecoder {
                 self.buf.advance(1);
                 dropped += 1;
                 return Err(DecodeError {
+                    kind: DecodeErrorKind::PayloadTooLarge,
                     message: format!(
                         "Payload too large (len={}, max={})",
                         payload_len, self.cfg.max_payload_len
@@ -204,11 +294,12 @@ impl FrameDecoder {
             }
 
             let frame_bytes = self.buf.split_to(frame_len).freeze();
+            let ext = frame_bytes.slice(fixed_len..fixed_len + ext_len);
             let payload = frame_bytes.slice(header_len..frame_len);
 
             let payload_crc32 = if has_crc {
                 Some(u32::from_le_bytes(
-                    frame_bytes[16..20].try_into().unwrap(),
+                    frame_bytes[crc_offset..crc_offset + 4].try_into().unwrap(),
                 ))
             } else {
                 None
@@ -218,6 +309,7 @@ impl FrameDecoder {
                 let actual = crc32_bytes(&payload);
                 if actual != expected {
                     return Err(DecodeError {
+                        kind: DecodeErrorKind::PayloadCrc,
                         message: format!(
                             "CRC32 mismatch (expected={:#010x}, actual={:#010x})",
                             expected, actual
@@ -229,6 +321,7 @@ impl FrameDecoder {
 
             return Ok(Some(Frame {
                 header: FrameHeader {
+                    version,
                     msg_type,
                     flags,
                     channel,
@@ -236,18 +329,37 @@ impl FrameDecoder {
                     payload_len,
                     payload_crc32,
                 },
+                ext,
                 payload,
             }));
         }
     }
+
+    /// 丢弃当前 magic 的首字节并跳到下一处 magic（找不到则只保留可能的半个 magic）
+    fn skip_to_next_magic(&mut self) -> usize {
+        self.buf.advance(1);
+        let n = match find_magic(&self.buf) {
+            Some(n) => n,
+            None => self.buf.len().saturating_sub(MAGIC.len() - 1),
+        };
+        self.buf.advance(n);
+        1 + n
+    }
 }
 
-fn crc32_bytes(bytes: &[u8]) -> u32 {
+pub fn crc32_bytes(bytes: &[u8]) -> u32 {
     let mut hasher = Crc32::new();
     hasher.update(bytes);
     hasher.finalize()
 }
 
+fn header_crc32(fixed: &[u8], ext: &[u8]) -> u32 {
+    let mut hasher = Crc32::new();
+    hasher.update(fixed);
+    hasher.update(ext);
+    hasher.finalize()
+}
+
 fn find_magic(buf: &BytesMut) -> Option<usize> {
     // 朴素扫描：先找 'H'，再比对后续 3 字节；避免 windows(4) 的额外开销
     let first = MAGIC[0];
@@ -284,7 +396,7 @@ pub fn encode_frame(params: EncodeFrameParams<'_>) -> Vec<u8> {
 
     let mut out = Vec::with_capacity(header_len + params.payload.len());
     out.extend_from_slice(&MAGIC);
-    out.push(VERSION);
+    out.push(VERSION_1);
     out.push(params.msg_type);
     out.push(params.flags);
     out.push(params.channel);
@@ -300,6 +412,44 @@ pub fn encode_frame(params: EncodeFrameParams<'_>) -> Vec<u8> {
     out
 }
 
+/// v2 封帧；ext 超过 255 字节的部分会被截断
+pub fn encode_frame_v2(params: EncodeFrameParams<'_>, ext: &[u8]) -> Vec<u8> {
+    let ext = &ext[..ext.len().min(u8::MAX as usize)];
+    let has_crc = (params.flags & FLAG_CRC32) != 0;
+    let header_len = HEADER_V2_LEN_BASE + ext.len() + if has_crc { 4 } else { 0 };
+
+    let mut out = Vec::with_capacity(header_len + params.payload.len());
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION_2);
+    out.push(params.msg_type);
+    out.push(params.flags);
+    out.push(params.channel);
+    out.extend_from_slice(&params.seq.to_le_bytes());
+    out.extend_from_slice(&(params.payload.len() as u32).to_le_bytes());
+    out.push(ext.len() as u8);
+    out.push(0); // reserved
+
+    let header_crc = header_crc32(&out[..18], ext);
+    out.extend_from_slice(&header_crc.to_le_bytes());
+    out.extend_from_slice(ext);
+
+    if has_crc {
+        let crc = crc32_bytes(params.payload);
+        out.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    out.extend_from_slice(params.payload);
+    out
+}
+
+/// 按连接协商出的帧头版本封帧（未知版本按 v1 处理）
+pub fn encode_frame_for(version: u8, params: EncodeFrameParams<'_>) -> Vec<u8> {
+    match version {
+        VERSION_2 => encode_frame_v2(params, &[]),
+        _ => encode_frame(params),
+    }
+}
+
 /// 协议消息类型（推荐值；也允许自定义 msg_type）
 pub mod msg_type {
     pub const HELLO: u8 = 0x01;
@@ -312,6 +462,21 @@ pub mod msg_type {
     pub const ERROR: u8 = 0x7F;
 }
 
+/// Response.status 约定值（业务可在 0x0100 以上自定义）
+pub mod status {
+    pub const OK: u16 = 0x0000;
+    /// body 格式不正确
+    pub const INVALID_REQUEST: u16 = 0x0001;
+    /// 未注册的 method
+    pub const NOT_IMPLEMENTED: u16 = 0x0002;
+    /// 暂时无法提供（如数据源不可用、无登录用户）
+    pub const UNAVAILABLE: u16 = 0x0003;
+    /// 处理过程中出错
+    pub const INTERNAL_ERROR: u16 = 0x0004;
+    /// 同时处理中的请求过多
+    pub const BUSY: u16 = 0x0005;
+}
+
 /// Hello 角色
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
@@ -338,12 +503,15 @@ pub struct Hello {
     pub role: Role,
     pub capabilities: u32,
     pub name: String,
+    /// 会话随机数（启用认证时附在 name 之后，对端据此绑定后续认证帧，见 `auth`）
+    pub nonce: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HelloAck {
     pub capabilities: u32,
     pub name: String,
+    pub nonce: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -429,6 +597,7 @@ fn decode_hello(payload: &[u8]) -> Result<Hello, MessageDecodeError> {
         role,
         capabilities,
         name,
+        nonce: decode_nonce(&payload[6 + name_len..]),
     })
 }
 
@@ -447,7 +616,16 @@ fn decode_hello_ack(payload: &[u8]) -> Result<HelloAck, MessageDecodeError> {
     let name = std::str::from_utf8(name_bytes)
         .map_err(|_| MessageDecodeError("HELLO_ACK name not utf8".to_string()))?
         .to_string();
-    Ok(HelloAck { capabilities, name })
+    Ok(HelloAck {
+        capabilities,
+        name,
+        nonce: decode_nonce(&payload[5 + name_len..]),
+    })
+}
+
+// 可选的会话随机数：旧版本对端不带，缺省或不足 8 字节时视为未提供
+fn decode_nonce(rest: &[u8]) -> Option<u64> {
+    rest.get(..8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
 }
 
 fn decode_heartbeat(payload: &[u8]) -> Result<Heartbeat, MessageDecodeError> {
@@ -525,24 +703,30 @@ fn decode_error(payload: &[u8]) -> Result<ProtoError, MessageDecodeError> {
 }
 
 pub fn encode_hello(hello: &Hello) -> Vec<u8> {
-    // role(1) + capabilities(4) + name_len(1) + name
+    // role(1) + capabilities(4) + name_len(1) + name [+ nonce(8)]
     let name_bytes = hello.name.as_bytes();
     let name_len = name_bytes.len().min(255);
-    let mut out = Vec::with_capacity(1 + 4 + 1 + name_len);
+    let mut out = Vec::with_capacity(1 + 4 + 1 + name_len + 8);
     out.push(hello.role.as_u8());
     out.extend_from_slice(&hello.capabilities.to_le_bytes());
     out.push(name_len as u8);
     out.extend_from_slice(&name_bytes[..name_len]);
+    if let Some(nonce) = hello.nonce {
+        out.extend_from_slice(&nonce.to_le_bytes());
+    }
     out
 }
 
 pub fn encode_hello_ack(ack: &HelloAck) -> Vec<u8> {
     let name_bytes = ack.name.as_bytes();
     let name_len = name_bytes.len().min(255);
-    let mut out = Vec::with_capacity(4 + 1 + name_len);
+    let mut out = Vec::with_capacity(4 + 1 + name_len + 8);
     out.extend_from_slice(&ack.capabilities.to_le_bytes());
     out.push(name_len as u8);
     out.extend_from_slice(&name_bytes[..name_len]);
+    if let Some(nonce) = ack.nonce {
+        out.extend_from_slice(&nonce.to_le_bytes());
+    }
     out
 }
 
@@ -588,6 +772,20 @@ pub fn encode_error(err: &ProtoError) -> Vec<u8> {
     out
 }
 
+/// Message → (msg_type, payload)：类型化发送的唯一编码入口（与 decode_message 互逆）
+pub fn encode_message(msg: &Message) -> (u8, Vec<u8>) {
+    match msg {
+        Message::Hello(v) => (msg_type::HELLO, encode_hello(v)),
+        Message::HelloAck(v) => (msg_type::HELLO_ACK, encode_hello_ack(v)),
+        Message::Heartbeat(v) => (msg_type::HEARTBEAT, encode_heartbeat(v)),
+        Message::Request(v) => (msg_type::REQUEST, encode_request(v)),
+        Message::Response(v) => (msg_type::RESPONSE, encode_response(v)),
+        Message::Event(v) => (msg_type::EVENT, encode_event(v)),
+        Message::Error(v) => (msg_type::ERROR, encode_error(v)),
+        Message::Raw { msg_type, payload } => (*msg_type, payload.to_vec()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -639,6 +837,7 @@ mod tests {
             role: Role::Client,
             capabilities: 0xAABBCCDD,
             name: "hmi-ui".to_string(),
+            nonce: None,
         };
         let payload = encode_hello(&hello);
         let bytes = encode_frame(EncodeFrameParams {
@@ -739,6 +938,29 @@ mod tests {
         assert!(err.message.contains("CRC32 mismatch"));
     }
 
+    #[test]
+    fn discard_partial_drops_incomplete_frame() {
+        let bytes = encode_frame(EncodeFrameParams {
+            msg_type: msg_type::EVENT,
+            flags: 0,
+            channel: 0,
+            seq: 1,
+            payload: b"hello",
+        });
+
+        let mut dec = FrameDecoder::new(DecoderConfig::default());
+        dec.push(&bytes[..bytes.len() - 2]).unwrap();
+        assert!(dec.next_frame().unwrap().is_none());
+        let err = dec.discard_partial().unwrap();
+        assert_eq!(err.kind, DecodeErrorKind::Truncated);
+        assert_eq!(err.dropped_bytes, bytes.len() - 2);
+
+        // 下一个数据报从头开始解析
+        dec.push(&bytes).unwrap();
+        assert_eq!(dec.next_frame().unwrap().unwrap().payload.as_ref(), b"hello");
+        assert!(dec.discard_partial().is_none());
+    }
+
     #[test]
     fn decoder_rejects_too_large_payload_len() {
         let mut cfg = DecoderConfig::default();
@@ -847,7 +1069,7 @@ mod tests {
             payload: &encode_heartbeat(&Heartbeat { timestamp_ms: 1 }),
         });
         // version byte at offset 4
-        bad[4] = 2;
+        bad[4] = 9;
 
         let good = encode_frame(EncodeFrameParams {
             msg_type: msg_type::HEARTBEAT,
@@ -864,11 +1086,145 @@ mod tests {
         let mut dec = FrameDecoder::new(DecoderConfig::default());
         dec.push(&bytes).unwrap();
 
-        // 先发生一次重同步（版本不匹配导致丢弃/扫描）
-        let _ = dec.next_frame();
+        // 版本不支持：显式报错并跳到下一处 magic
+        let err = dec.next_frame().unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::UnsupportedVersion(9));
+        assert_eq!(err.dropped_bytes, bad.len());
 
         // 最终应能解析到正确版本的帧
         let frame = dec.next_frame().unwrap().unwrap();
         assert_eq!(frame.header.seq, 2);
     }
+
+    #[test]
+    fn decoder_accepts_mixed_v1_and_v2_frames() {
+        let payload = b"spectrum";
+        let v1 = encode_frame(EncodeFrameParams {
+            msg_type: msg_type::EVENT,
+            flags: FLAG_CRC32,
+            channel: 1,
+            seq: 1,
+            payload,
+        });
+        let v2 = encode_frame_v2(
+            EncodeFrameParams {
+                msg_type: msg_type::EVENT,
+                flags: FLAG_CRC32,
+                channel: 1,
+                seq: 2,
+                payload,
+            },
+            &[0xAA, 0xBB],
+        );
+
+        let mut dec = FrameDecoder::new(DecoderConfig::default());
+        dec.push(&v1).unwrap();
+        dec.push(&v2).unwrap();
+
+        let f1 = dec.next_frame().unwrap().unwrap();
+        assert_eq!(f1.header.version, VERSION_1);
+        assert!(f1.ext.is_empty());
+        assert_eq!(f1.payload.as_ref(), payload);
+
+        let f2 = dec.next_frame().unwrap().unwrap();
+        assert_eq!(f2.header.version, VERSION_2);
+        assert_eq!(f2.header.seq, 2);
+        assert_eq!(f2.ext.as_ref(), &[0xAA, 0xBB]);
+        assert_eq!(f2.payload.as_ref(), payload);
+        assert_eq!(dec.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn decoder_v2_corrupted_len_does_not_stall() {
+        let mut bad = encode_frame_v2(
+            EncodeFrameParams {
+                msg_type: msg_type::REQUEST,
+                flags: 0,
+                channel: 0,
+                seq: 1,
+                payload: &[1, 2, 3],
+            },
+            &[],
+        );
+        // 篡改 PAYLOAD_LEN：v1 会一直等待 payload，v2 由头部 CRC 立即识别
+        bad[12] = 0xFF;
+
+        let good = encode_frame_v2(
+            EncodeFrameParams {
+                msg_type: msg_type::REQUEST,
+                flags: 0,
+                channel: 0,
+                seq: 2,
+                payload: &[4, 5, 6],
+            },
+            &[],
+        );
+
+        let mut dec = FrameDecoder::new(DecoderConfig::default());
+        dec.push(&bad).unwrap();
+        dec.push(&good).unwrap();
+
+        let err = dec.next_frame().unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::HeaderCrc);
+
+        let frame = dec.next_frame().unwrap().unwrap();
+        assert_eq!(frame.header.seq, 2);
+        assert_eq!(frame.payload.as_ref(), &[4, 5, 6]);
+    }
+
+    #[test]
+    fn encode_message_roundtrips_every_variant() {
+        let messages = vec![
+            Message::Hello(Hello {
+                role: Role::Server,
+                capabilities: 7,
+                name: "plc".to_string(),
+                nonce: None,
+            }),
+            Message::HelloAck(HelloAck {
+                capabilities: 3,
+                name: "hmi".to_string(),
+                nonce: Some(0x0102_0304_0506_0708),
+            }),
+            Message::Heartbeat(Heartbeat { timestamp_ms: 123 }),
+            Message::Request(Request {
+                request_id: 1,
+                method: 0x0102,
+                body: Bytes::from_static(b"req"),
+            }),
+            Message::Response(Response {
+                request_id: 1,
+                status: 0,
+                body: Bytes::from_static(b"resp"),
+            }),
+            Message::Event(Event {
+                event_id: 9,
+                timestamp_ms: 456,
+                body: Bytes::from_static(b"ev"),
+            }),
+            Message::Error(ProtoError {
+                code: 5,
+                message: "bad".to_string(),
+            }),
+            Message::Raw {
+                msg_type: 0x40,
+                payload: Bytes::from_static(b"raw"),
+            },
+        ];
+
+        for msg in messages {
+            let (msg_type, payload) = encode_message(&msg);
+            let bytes = encode_frame(EncodeFrameParams {
+                msg_type,
+                flags: FLAG_CRC32,
+                channel: 0,
+                seq: 1,
+                payload: &payload,
+            });
+            let mut decoder = FrameDecoder::new(DecoderConfig::default());
+            decoder.push(&bytes).unwrap();
+            let frame = decoder.next_frame().unwrap().unwrap();
+            assert_eq!(decode_message(&frame).unwrap(), msg);
+        }
+    }
 }
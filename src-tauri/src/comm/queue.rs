@@ -0,0 +1,873 @@
+use crate::comm::{actor::CommPriority, auth, proto, rpc, sequence};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::Notify;
+
+/// 连接发送队列：多个队列（类）之间按权重公平调度
+///
+/// 说明：
+/// - 调度采用按字节计的赤字轮询（DRR）：每轮为每个非空队列补充 `weight × QUANTUM_BYTES` 的发送额度，
+///   长期带宽占比与权重成正比；低权重队列只会变慢，不会被高权重队列饿死
+/// - 入队目标：显式 queue 名 > channel 映射 > priority（`high`/`normal` 对应同名队列，
+///   不存在时分别取第一个/最后一个队列）
+/// - 队列满时立即入队失败并计入 dropped；分片等批量发送按背压等待，不计入 dropped
+/// - 队列跨重连保留：断线期间入队的帧在重连（及握手完成）后发出
+/// - HMIP 帧以未编码形式排队，出队时按当前会话（帧头版本/认证/seq 代次）封帧
+pub const QUANTUM_BYTES: usize = 256;
+
+const DEFAULT_STARVATION_MS: u64 = 1000;
+
+/// 单个发送队列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TxQueueClass {
+    pub name: String,
+    /// 调度权重（>= 1）
+    pub weight: u32,
+    /// 最大排队帧数
+    pub capacity: usize,
+    /// 未显式指定队列时，这些 channel 上的帧进入本队列
+    pub channels: Vec<u8>,
+}
+
+impl Default for TxQueueClass {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            weight: 1,
+            capacity: 256,
+            channels: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TxQueueConfig {
+    pub classes: Vec<TxQueueClass>,
+    /// 帧排队超过该时长才发出时计为一次饥饿（starved）
+    pub starvation_ms: u64,
+}
+
+impl Default for TxQueueConfig {
+    fn default() -> Self {
+        Self {
+            classes: vec![
+                TxQueueClass {
+                    name: "high".to_string(),
+                    weight: 8,
+                    capacity: 64,
+                    channels: Vec::new(),
+                },
+                TxQueueClass {
+                    name: "normal".to_string(),
+                    weight: 1,
+                    capacity: 256,
+                    channels: Vec::new(),
+                },
+            ],
+            starvation_ms: DEFAULT_STARVATION_MS,
+        }
+    }
+}
+
+impl TxQueueConfig {
+    /// 校验队列配置（connect 前调用）
+    pub fn validate(&self) -> Result<(), String> {
+        if self.classes.is_empty() {
+            return Err("tx_queues.classes must not be empty".to_string());
+        }
+        for (i, class) in self.classes.iter().enumerate() {
+            if class.name.is_empty() {
+                return Err(format!("tx_queues.classes[{}] has an empty name", i));
+            }
+            if self.classes[..i].iter().any(|c| c.name == class.name) {
+                return Err(format!("Duplicate tx queue name: {}", class.name));
+            }
+            if class.weight == 0 || class.capacity == 0 {
+                return Err(format!(
+                    "Tx queue {} must have weight >= 1 and capacity >= 1",
+                    class.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 待封帧的 HMIP 消息（payload 已编码，帧头在出队时生成）
+pub struct HmipFrame {
+    pub msg_type: u8,
+    pub flags: u8,
+    pub channel: u8,
+    pub seq: sequence::TxSeq,
+    /// 指定帧头版本（应答沿用请求的版本）；None 时使用连接当前协商的版本
+    pub version: Option<u8>,
+    /// 指定发送目标（应答发回请求来源，仅 UDP 任意来源模式生效）；None 时使用传输的默认目标
+    pub destination: Option<SocketAddr>,
+    pub payload: Vec<u8>,
+}
+
+impl HmipFrame {
+    /// 出队时封帧：seq 分配于上一代次（重连重置前）时重新分配
+    pub fn encode(
+        &mut self,
+        auth: Option<&auth::FrameAuth>,
+        wire_version: u8,
+        tx_seq: &sequence::SeqGenerator,
+    ) -> Vec<u8> {
+        let seq = tx_seq.refresh(self.channel, &mut self.seq);
+        auth::encode_frame(
+            auth,
+            self.version.unwrap_or(wire_version),
+            proto::EncodeFrameParams {
+                msg_type: self.msg_type,
+                flags: self.flags,
+                channel: self.channel,
+                seq,
+                payload: &self.payload,
+            },
+        )
+    }
+}
+
+/// 写队列中的一项
+pub enum Outbound {
+    /// 原始字节（不经 HMIP 封帧）
+    Frame(Vec<u8>),
+    Hmip(HmipFrame),
+    /// 请求帧：actor 先登记 pending 再写出
+    Request(rpc::OutboundRequest),
+}
+
+impl Outbound {
+    // 调度按字节计：HMIP 帧取 v1 帧头 + payload 估算（认证标签等不计）
+    fn len(&self) -> usize {
+        match self {
+            Self::Frame(bytes) => bytes.len(),
+            Self::Hmip(frame) => proto::HEADER_LEN_BASE + frame.payload.len(),
+            Self::Request(request) => proto::HEADER_LEN_BASE + request.frame.payload.len(),
+        }
+    }
+
+    fn request_id(&self) -> Option<u32> {
+        match self {
+            Self::Frame(_) | Self::Hmip(_) => None,
+            Self::Request(request) => Some(request.request_id),
+        }
+    }
+
+}
+
+/// 入队目标
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueTarget<'a> {
+    /// 显式指定的队列名
+    pub queue: Option<&'a str>,
+    pub channel: Option<u8>,
+    pub priority: CommPriority,
+    /// 排队超过该时长仍未发出则丢弃（例如断线期间积压的设定值，重连后不应再下发）
+    pub ttl_ms: Option<u64>,
+}
+
+pub enum PushError {
+    Full(Outbound),
+    Closed(Outbound),
+    UnknownQueue(String),
+}
+
+impl PushError {
+    /// 错误提示（label 为连接名称，与既有 "write queue is full" 文案一致）
+    pub fn message(&self, label: &str) -> String {
+        match self {
+            Self::Full(_) => format!("{} write queue is full", label),
+            Self::Closed(_) => format!("{} connection is closed", label),
+            Self::UnknownQueue(name) => format!("{}: unknown tx queue {}", label, name),
+        }
+    }
+}
+
+/// 帧未发出即被丢弃的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// 入队时队列已满
+    QueueFull,
+    /// 超过 ttl_ms 仍未发出
+    Expired,
+    /// 被 `flush_hmip_tx_queue` 清空
+    Flushed,
+    /// 连接关闭时仍在排队
+    Closed,
+}
+
+impl DropReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::QueueFull => "queue_full",
+            Self::Expired => "expired",
+            Self::Flushed => "flushed",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+/// 一次丢弃（通过 `comm-event` 的 `tx_dropped` 推送）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TxDrop {
+    pub queue: String,
+    pub reason: DropReason,
+    /// 帧字节数
+    pub size: usize,
+    /// 丢弃前已排队的时长（queue_full 时为 0）
+    pub waited_ms: u64,
+    /// 请求帧的 request_id（调用方同时收到错误）
+    pub request_id: Option<u32>,
+}
+
+/// 丢弃通知回调（在锁外调用）
+pub type DropSink = Box<dyn Fn(TxDrop) + Send + Sync>;
+
+/// `get_hmip_tx_queues` 返回值（计数跨重连累计）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TxQueueStats {
+    pub name: String,
+    pub weight: u32,
+    pub capacity: usize,
+    pub depth: usize,
+    pub max_depth: usize,
+    pub enqueued: u64,
+    pub sent: u64,
+    pub sent_bytes: u64,
+    /// 队列满导致的丢弃
+    pub dropped: u64,
+    /// 超过 ttl_ms 被丢弃
+    pub expired: u64,
+    /// 被手动清空
+    pub flushed: u64,
+    /// 排队超过 starvation_ms 才发出的帧数
+    pub starved: u64,
+    pub max_wait_ms: u64,
+    /// 当前队首已等待的时长（队列为空时为 0）
+    pub head_wait_ms: u64,
+}
+
+struct Queued {
+    item: Outbound,
+    enqueued_at: Instant,
+    expires_at: Option<Instant>,
+}
+
+struct Class {
+    config: TxQueueClass,
+    items: VecDeque<Queued>,
+    deficit: usize,
+    stats: TxQueueStats,
+}
+
+/// 被丢弃的项：请求需要据此通知调用方
+struct Dropped {
+    info: TxDrop,
+    item: Option<Outbound>,
+}
+
+fn waited_ms(queued: &Queued, now: Instant) -> u64 {
+    now.saturating_duration_since(queued.enqueued_at)
+        .as_millis() as u64
+}
+
+/// 调度状态（纯逻辑，便于单测）
+pub struct Scheduler {
+    classes: Vec<Class>,
+    starvation_ms: u64,
+    cursor: usize,
+    /// 当前游标所在队列本轮是否已补充额度
+    refilled: bool,
+    len: usize,
+    /// 尚未通知的丢弃
+    dropped: Vec<Dropped>,
+}
+
+impl Scheduler {
+    pub fn new(config: &TxQueueConfig) -> Self {
+        let config = if config.validate().is_ok() {
+            config.clone()
+        } else {
+            TxQueueConfig::default()
+        };
+        let classes = config
+            .classes
+            .into_iter()
+            .map(|class| Class {
+                stats: TxQueueStats {
+                    name: class.name.clone(),
+                    weight: class.weight,
+                    capacity: class.capacity,
+                    ..TxQueueStats::default()
+                },
+                config: class,
+                items: VecDeque::new(),
+                deficit: 0,
+            })
+            .collect();
+        Self {
+            classes,
+            starvation_ms: config.starvation_ms,
+            cursor: 0,
+            refilled: false,
+            len: 0,
+            dropped: Vec::new(),
+        }
+    }
+
+    fn resolve(&self, target: QueueTarget<'_>) -> Result<usize, String> {
+        if let Some(name) = target.queue {
+            return self
+                .classes
+                .iter()
+                .position(|c| c.config.name == name)
+                .ok_or_else(|| name.to_string());
+        }
+        if let Some(channel) = target.channel {
+            if let Some(index) = self
+                .classes
+                .iter()
+                .position(|c| c.config.channels.contains(&channel))
+            {
+                return Ok(index);
+            }
+        }
+        let (name, fallback) = match target.priority {
+            CommPriority::High => ("high", 0),
+            CommPriority::Normal => ("normal", self.classes.len() - 1),
+        };
+        Ok(self
+            .classes
+            .iter()
+            .position(|c| c.config.name == name)
+            .unwrap_or(fallback))
+    }
+
+    /// 入队；count_drop 为 false 时队列满不计入 dropped（调用方会等待后重试）
+    pub fn push(
+        &mut self,
+        target: QueueTarget<'_>,
+        item: Outbound,
+        now: Instant,
+        count_drop: bool,
+    ) -> Result<(), PushError> {
+        let index = self.resolve(target).map_err(PushError::UnknownQueue)?;
+        self.expire(now);
+        let class = &mut self.classes[index];
+        if class.items.len() >= class.config.capacity {
+            if count_drop {
+                class.stats.dropped += 1;
+                self.dropped.push(Dropped {
+                    info: TxDrop {
+                        queue: class.config.name.clone(),
+                        reason: DropReason::QueueFull,
+                        size: item.len(),
+                        waited_ms: 0,
+                        request_id: item.request_id(),
+                    },
+                    item: None,
+                });
+            }
+            return Err(PushError::Full(item));
+        }
+        class.items.push_back(Queued {
+            item,
+            enqueued_at: now,
+            expires_at: target
+                .ttl_ms
+                .map(|ttl| now + std::time::Duration::from_millis(ttl)),
+        });
+        class.stats.enqueued += 1;
+        class.stats.max_depth = class.stats.max_depth.max(class.items.len());
+        self.len += 1;
+        Ok(())
+    }
+
+    /// 按 DRR 取出下一项（先丢弃已过期的项）
+    pub fn pop(&mut self, now: Instant) -> Option<Outbound> {
+        self.expire(now);
+        if self.len == 0 {
+            return None;
+        }
+        loop {
+            let class = &mut self.classes[self.cursor];
+            let Some(front_len) = class.items.front().map(|q| q.item.len()) else {
+                // 空队列不积累额度
+                class.deficit = 0;
+                self.advance();
+                continue;
+            };
+            if !self.refilled {
+                class.deficit += QUANTUM_BYTES * class.config.weight as usize;
+                self.refilled = true;
+            }
+            if front_len > class.deficit {
+                self.advance();
+                continue;
+            }
+
+            class.deficit -= front_len;
+            let queued = class.items.pop_front()?;
+            let wait_ms = waited_ms(&queued, now);
+            class.stats.sent += 1;
+            class.stats.sent_bytes += front_len as u64;
+            class.stats.max_wait_ms = class.stats.max_wait_ms.max(wait_ms);
+            if wait_ms > self.starvation_ms {
+                class.stats.starved += 1;
+            }
+            if class.items.is_empty() {
+                class.deficit = 0;
+                self.advance();
+            }
+            self.len -= 1;
+            return Some(queued.item);
+        }
+    }
+
+    fn advance(&mut self) {
+        self.cursor = (self.cursor + 1) % self.classes.len();
+        self.refilled = false;
+    }
+
+    /// 丢弃所有已过期的项
+    pub fn expire(&mut self, now: Instant) {
+        if self.len == 0 {
+            return;
+        }
+        for class in &mut self.classes {
+            if !class
+                .items
+                .iter()
+                .any(|q| q.expires_at.is_some_and(|at| at <= now))
+            {
+                continue;
+            }
+            let (expired, kept): (VecDeque<Queued>, VecDeque<Queued>) = class
+                .items
+                .drain(..)
+                .partition(|q| q.expires_at.is_some_and(|at| at <= now));
+            class.items = kept;
+            class.stats.expired += expired.len() as u64;
+            self.len -= expired.len();
+            for queued in expired {
+                self.dropped.push(dropped(
+                    &class.config.name,
+                    DropReason::Expired,
+                    queued,
+                    now,
+                ));
+            }
+        }
+    }
+
+    /// 清空指定队列（None 为全部），返回清掉的项数
+    pub fn flush(&mut self, queue: Option<&str>, now: Instant) -> Result<usize, String> {
+        if let Some(name) = queue {
+            if !self.classes.iter().any(|c| c.config.name == name) {
+                return Err(format!("Unknown tx queue {}", name));
+            }
+        }
+        let mut count = 0;
+        for class in &mut self.classes {
+            if queue.is_some_and(|name| name != class.config.name) {
+                continue;
+            }
+            class.deficit = 0;
+            class.stats.flushed += class.items.len() as u64;
+            count += class.items.len();
+            for queued in class.items.drain(..) {
+                self.dropped.push(dropped(
+                    &class.config.name,
+                    DropReason::Flushed,
+                    queued,
+                    now,
+                ));
+            }
+        }
+        self.len -= count;
+        Ok(count)
+    }
+
+    pub fn stats(&self, now: Instant) -> Vec<TxQueueStats> {
+        self.classes
+            .iter()
+            .map(|class| TxQueueStats {
+                depth: class.items.len(),
+                head_wait_ms: class.items.front().map(|q| waited_ms(q, now)).unwrap_or(0),
+                ..class.stats.clone()
+            })
+            .collect()
+    }
+
+    /// 关闭时丢弃全部排队项
+    fn drain(&mut self, now: Instant) {
+        self.len = 0;
+        for class in &mut self.classes {
+            for queued in class.items.drain(..) {
+                self.dropped
+                    .push(dropped(&class.config.name, DropReason::Closed, queued, now));
+            }
+        }
+    }
+
+    fn take_dropped(&mut self) -> Vec<Dropped> {
+        std::mem::take(&mut self.dropped)
+    }
+}
+
+fn dropped(queue: &str, reason: DropReason, queued: Queued, now: Instant) -> Dropped {
+    Dropped {
+        info: TxDrop {
+            queue: queue.to_string(),
+            reason,
+            size: queued.item.len(),
+            waited_ms: waited_ms(&queued, now),
+            request_id: queued.item.request_id(),
+        },
+        item: Some(queued.item),
+    }
+}
+
+/// 发送侧与 actor 共享的队列（handle 持有 Arc，跨重连保留）
+pub struct TxQueues {
+    scheduler: Mutex<Scheduler>,
+    /// 有新项入队（单一消费者：actor）
+    ready: Notify,
+    /// 有项出队（唤醒等待空位的批量发送）
+    space: Notify,
+    closed: AtomicBool,
+    on_drop: DropSink,
+}
+
+impl TxQueues {
+    pub fn new(config: &TxQueueConfig, on_drop: DropSink) -> Self {
+        Self {
+            scheduler: Mutex::new(Scheduler::new(config)),
+            ready: Notify::new(),
+            space: Notify::new(),
+            closed: AtomicBool::new(false),
+            on_drop,
+        }
+    }
+
+    /// 持锁执行调度操作，解锁后通知丢弃
+    fn with_scheduler<T>(&self, f: impl FnOnce(&mut Scheduler, Instant) -> T) -> Option<T> {
+        let (result, dropped) = {
+            let mut scheduler = self.scheduler.lock().ok()?;
+            let result = f(&mut scheduler, Instant::now());
+            (result, scheduler.take_dropped())
+        };
+        self.notify_dropped(dropped);
+        Some(result)
+    }
+
+    /// 请求的调用方收到错误，前端收到 tx_dropped 事件
+    fn notify_dropped(&self, dropped: Vec<Dropped>) {
+        if !dropped.is_empty() {
+            self.space.notify_waiters();
+        }
+        for Dropped { info, item } in dropped {
+            if let Some(Outbound::Request(request)) = item {
+                let _ = request.reply.send(Err(format!(
+                    "Request dropped from tx queue {} ({})",
+                    info.queue,
+                    info.reason.as_str()
+                )));
+            }
+            (self.on_drop)(info);
+        }
+    }
+
+    fn push_inner(
+        &self,
+        target: QueueTarget<'_>,
+        item: Outbound,
+        count_drop: bool,
+    ) -> Result<(), PushError> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(PushError::Closed(item));
+        }
+        let (result, dropped) = {
+            let Ok(mut scheduler) = self.scheduler.lock() else {
+                return Err(PushError::Closed(item));
+            };
+            let result = scheduler.push(target, item, Instant::now(), count_drop);
+            (result, scheduler.take_dropped())
+        };
+        self.notify_dropped(dropped);
+        result?;
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// 立即入队；队列满时失败
+    pub fn try_push(&self, target: QueueTarget<'_>, item: Outbound) -> Result<(), PushError> {
+        self.push_inner(target, item, true)
+    }
+
+    /// 入队；队列满时等待空位（用于分片等一次性入队多帧的场景）
+    pub async fn push(&self, target: QueueTarget<'_>, mut item: Outbound) -> Result<(), PushError> {
+        loop {
+            let space = self.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+            match self.push_inner(target, item, false) {
+                Err(PushError::Full(back)) => item = back,
+                other => return other,
+            }
+            space.await;
+        }
+    }
+
+    /// 等待并取出下一项（actor 调用；future 被取消时不会丢项）
+    pub async fn pop(&self) -> Outbound {
+        loop {
+            let ready = self.ready.notified();
+            let item = self
+                .with_scheduler(|scheduler, now| scheduler.pop(now))
+                .flatten();
+            if let Some(item) = item {
+                self.space.notify_waiters();
+                return item;
+            }
+            ready.await;
+        }
+    }
+
+    /// 队列统计（顺带丢弃已过期的项，使 depth 不含过期帧）
+    pub fn stats(&self) -> Vec<TxQueueStats> {
+        self.with_scheduler(|scheduler, now| {
+            scheduler.expire(now);
+            scheduler.stats(now)
+        })
+        .unwrap_or_default()
+    }
+
+    /// 清空指定队列（None 为全部），返回清掉的项数
+    pub fn flush(&self, queue: Option<&str>) -> Result<usize, String> {
+        self.with_scheduler(|scheduler, now| scheduler.flush(queue, now))
+            .unwrap_or_else(|| Err("Tx queue is poisoned".to_string()))
+    }
+
+    /// actor 退出时调用：拒绝后续入队，排队中的项按 closed 丢弃
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.with_scheduler(|scheduler, now| scheduler.drain(now));
+        self.space.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(len: usize) -> Outbound {
+        Outbound::Frame(vec![0; len])
+    }
+
+    fn hmip(channel: u8, seq: sequence::TxSeq) -> HmipFrame {
+        HmipFrame {
+            msg_type: proto::msg_type::EVENT,
+            flags: 0,
+            channel,
+            seq,
+            version: None,
+            destination: None,
+            payload: vec![0; 4],
+        }
+    }
+
+    fn target(queue: &str) -> QueueTarget<'_> {
+        QueueTarget {
+            queue: Some(queue),
+            ..QueueTarget::default()
+        }
+    }
+
+    fn config(classes: &[(&str, u32, &[u8])]) -> TxQueueConfig {
+        TxQueueConfig {
+            classes: classes
+                .iter()
+                .map(|(name, weight, channels)| TxQueueClass {
+                    name: name.to_string(),
+                    weight: *weight,
+                    capacity: 1000,
+                    channels: channels.to_vec(),
+                })
+                .collect(),
+            starvation_ms: DEFAULT_STARVATION_MS,
+        }
+    }
+
+    #[test]
+    fn weighted_share_does_not_starve_low_weight() {
+        let mut scheduler = Scheduler::new(&config(&[("control", 3, &[]), ("bulk", 1, &[])]));
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(scheduler
+                .push(target("control"), frame(QUANTUM_BYTES), now, true)
+                .is_ok());
+            assert!(scheduler
+                .push(target("bulk"), frame(QUANTUM_BYTES), now, true)
+                .is_ok());
+        }
+
+        for _ in 0..40 {
+            scheduler.pop(now).unwrap();
+        }
+        let stats = scheduler.stats(now);
+        assert_eq!(stats[0].sent, 30);
+        assert_eq!(stats[1].sent, 10);
+    }
+
+    #[test]
+    fn large_frames_accumulate_deficit() {
+        let mut scheduler = Scheduler::new(&config(&[("control", 1, &[]), ("bulk", 1, &[])]));
+        let now = Instant::now();
+        assert!(scheduler
+            .push(target("bulk"), frame(QUANTUM_BYTES * 4), now, true)
+            .is_ok());
+        for _ in 0..8 {
+            assert!(scheduler
+                .push(target("control"), frame(QUANTUM_BYTES), now, true)
+                .is_ok());
+        }
+
+        // bulk 需积累 4 轮额度，期间 control 每轮发 1 帧
+        let order: Vec<usize> = (0..9).map(|_| scheduler.pop(now).unwrap().len()).collect();
+        let bulk_at = order
+            .iter()
+            .position(|len| *len == QUANTUM_BYTES * 4)
+            .unwrap();
+        assert_eq!(bulk_at, 4);
+        assert!(scheduler.pop(now).is_none());
+    }
+
+    #[test]
+    fn target_resolution_and_drop_metrics() {
+        let mut cfg = config(&[("trace", 1, &[5]), ("normal", 1, &[])]);
+        cfg.classes[0].capacity = 1;
+        let mut scheduler = Scheduler::new(&cfg);
+        let now = Instant::now();
+
+        let by_channel = QueueTarget {
+            channel: Some(5),
+            ..QueueTarget::default()
+        };
+        assert!(scheduler.push(by_channel, frame(1), now, true).is_ok());
+        assert!(matches!(
+            scheduler.push(by_channel, frame(1), now, true),
+            Err(PushError::Full(_))
+        ));
+        // 没有名为 high 的队列时取第一个
+        let high = QueueTarget {
+            priority: CommPriority::High,
+            ..QueueTarget::default()
+        };
+        assert!(scheduler.push(high, frame(1), now, false).is_err());
+        assert!(scheduler
+            .push(QueueTarget::default(), frame(1), now, true)
+            .is_ok());
+        assert!(matches!(
+            scheduler.push(target("bulk"), frame(1), now, true),
+            Err(PushError::UnknownQueue(_))
+        ));
+
+        let stats = scheduler.stats(now);
+        assert_eq!((stats[0].depth, stats[0].dropped), (1, 1));
+        assert_eq!((stats[1].depth, stats[1].dropped), (1, 0));
+
+        let later = now + std::time::Duration::from_millis(DEFAULT_STARVATION_MS + 1);
+        scheduler.pop(later).unwrap();
+        assert_eq!(scheduler.stats(later)[0].starved, 1);
+
+        let dropped = scheduler.take_dropped();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].info.reason, DropReason::QueueFull);
+        assert_eq!(dropped[0].info.queue, "trace");
+    }
+
+    #[test]
+    fn expired_items_are_dropped_not_sent() {
+        let mut scheduler = Scheduler::new(&config(&[("normal", 1, &[])]));
+        let now = Instant::now();
+        let stale = QueueTarget {
+            ttl_ms: Some(100),
+            ..QueueTarget::default()
+        };
+        assert!(scheduler.push(stale, frame(3), now, true).is_ok());
+        assert!(scheduler
+            .push(QueueTarget::default(), frame(5), now, true)
+            .is_ok());
+
+        let later = now + std::time::Duration::from_millis(150);
+        assert_eq!(scheduler.pop(later).unwrap().len(), 5);
+        assert!(scheduler.pop(later).is_none());
+
+        let dropped = scheduler.take_dropped();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(
+            dropped[0].info,
+            TxDrop {
+                queue: "normal".to_string(),
+                reason: DropReason::Expired,
+                size: 3,
+                waited_ms: 150,
+                request_id: None,
+            }
+        );
+        let stats = scheduler.stats(later);
+        assert_eq!((stats[0].expired, stats[0].sent), (1, 1));
+    }
+
+    #[test]
+    fn flush_fails_queued_requests() {
+        let drops = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let sink = drops.clone();
+        let queues = TxQueues::new(
+            &config(&[("control", 1, &[]), ("bulk", 1, &[])]),
+            Box::new(move |drop| sink.lock().unwrap().push(drop)),
+        );
+
+        let (reply, mut rx) = tokio::sync::oneshot::channel();
+        let request = rpc::OutboundRequest {
+            request_id: 9,
+            method: 1,
+            frame: hmip(0, sequence::TxSeq::Explicit(1)),
+            reply,
+        };
+        assert!(queues
+            .try_push(target("bulk"), Outbound::Request(request))
+            .is_ok());
+        assert!(queues.try_push(target("control"), frame(1)).is_ok());
+
+        assert!(queues.flush(Some("missing")).is_err());
+        assert_eq!(queues.flush(Some("bulk")).unwrap(), 1);
+        assert!(rx.try_recv().unwrap().unwrap_err().contains("flushed"));
+
+        let drops = drops.lock().unwrap();
+        assert_eq!(drops.len(), 1);
+        assert_eq!(
+            (drops[0].reason, drops[0].request_id),
+            (DropReason::Flushed, Some(9))
+        );
+        assert_eq!(queues.stats()[0].depth, 1);
+    }
+
+    #[test]
+    fn validate_rejects_bad_classes() {
+        assert!(TxQueueConfig::default().validate().is_ok());
+        assert!(config(&[]).validate().is_err());
+        assert!(config(&[("a", 1, &[]), ("a", 1, &[])]).validate().is_err());
+        assert!(config(&[("a", 0, &[])]).validate().is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::comm::actor::CommActorHandle;
+use crate::comm::session::HmipLinkConfig;
+use crate::comm::{serial, server, tcp, udp, unix, ws};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+
+/// `connect_serial` / `connect_tcp` 使用的保留连接 ID
+pub const SERIAL_ID: &str = "serial";
+pub const TCP_ID: &str = "tcp";
+
+const MAX_ID_LEN: usize = 64;
+
+/// 连接 ID：命令中的 `transport` 参数，同时也是该连接事件中的 `transport` 字段
+///
+/// 说明：
+/// - `serial` / `tcp` 由 `connect_serial` / `connect_tcp` 使用，保持既有前端不变
+/// - 其余 ID 由 `connect` 命令按用户指定创建（例如 `plc`、`scale-1`），可同时存在多个串口/TCP 连接
+/// - ID 会出现在 Log 目录的文件名中，只允许字母、数字与 `_` `-` `.`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ConnectionId(String);
+
+impl ConnectionId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn serial() -> Self {
+        Self::new(SERIAL_ID)
+    }
+
+    pub fn tcp() -> Self {
+        Self::new(TCP_ID)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 面向错误提示的名称（保留 ID 与既有命令的报错文案保持一致）
+    pub fn label(&self) -> String {
+        match self.as_str() {
+            SERIAL_ID => "Serial".to_string(),
+            TCP_ID => "TCP".to_string(),
+            id => format!("Connection '{}'", id),
+        }
+    }
+
+    pub fn not_connected_message(&self) -> String {
+        match self.as_str() {
+            SERIAL_ID => "Serial port not connected".to_string(),
+            TCP_ID => "TCP not connected".to_string(),
+            id => format!("Connection '{}' not connected", id),
+        }
+    }
+
+    /// 校验用户指定的 ID（connect 前调用）
+    pub fn validate(&self) -> Result<(), String> {
+        let id = self.as_str();
+        if id.is_empty() || id.len() > MAX_ID_LEN {
+            return Err(format!(
+                "Connection ID must be 1..={} characters",
+                MAX_ID_LEN
+            ));
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            || id.starts_with('.')
+        {
+            return Err(format!(
+                "Invalid connection ID '{}': use letters, digits, '_', '-' or '.'",
+                id
+            ));
+        }
+        if id == server::SERVER_TRANSPORT {
+            return Err(format!("Connection ID '{}' is reserved", id));
+        }
+        Ok(())
+    }
+}
+
+/// `connect` 命令的连接参数（按 kind 区分传输类型）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConnectionConfig {
+    Serial(serial::SerialConfig),
+    Tcp(tcp::TcpConfig),
+    Udp(udp::UdpConfig),
+    Unix(unix::UnixConfig),
+    Ws(ws::WsConfig),
+}
+
+impl ConnectionConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Serial(_) => SERIAL_ID,
+            Self::Tcp(_) => TCP_ID,
+            Self::Udp(_) => "udp",
+            Self::Unix(_) => "unix",
+            Self::Ws(_) => "ws",
+        }
+    }
+
+    pub fn link(&self) -> &HmipLinkConfig {
+        match self {
+            Self::Serial(config) => &config.hmip,
+            Self::Tcp(config) => &config.hmip,
+            Self::Udp(config) => &config.hmip,
+            Self::Unix(config) => &config.hmip,
+            Self::Ws(config) => &config.hmip,
+        }
+    }
+
+    /// 保留 ID 只能用于对应类型的连接（前端的 serial/tcp 读模型依赖这一点）
+    pub fn check_id(&self, id: &ConnectionId) -> Result<(), String> {
+        match id.as_str() {
+            SERIAL_ID | TCP_ID if id.as_str() != self.kind() => Err(format!(
+                "Connection ID '{}' is reserved for {} connections",
+                id.as_str(),
+                id.as_str()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `list_connections` 返回的连接信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub id: ConnectionId,
+    /// 传输类型（serial / tcp / udp / unix / ws）
+    pub kind: String,
+    /// 连接目标（如 `COM3 @ 115200`、`127.0.0.1:502`）
+    pub target: String,
+    /// 链路当前是否已建立（false 表示正在重连）
+    pub connected: bool,
+    pub created_at_ms: u64,
+}
+
+pub type ConnectionSlot = Arc<Mutex<Option<CommActorHandle>>>;
+
+/// 按连接 ID 索引的连接表
+///
+/// 每个连接一个槽：命令只在取用期间锁住自己的槽，不同连接之间互不阻塞。
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    slots: RwLock<BTreeMap<ConnectionId, ConnectionSlot>>,
+}
+
+impl ConnectionRegistry {
+    /// 取连接槽；ID 未登记时返回一个空槽（不登记，后续按“未连接”处理）
+    pub fn slot(&self, id: &ConnectionId) -> ConnectionSlot {
+        self.slots
+            .read()
+            .ok()
+            .and_then(|slots| slots.get(id).cloned())
+            .unwrap_or_default()
+    }
+
+    /// 取连接槽，不存在时登记一个新槽（connect 时使用）
+    pub fn slot_or_insert(&self, id: &ConnectionId) -> ConnectionSlot {
+        match self.slots.write() {
+            Ok(mut slots) => slots.entry(id.clone()).or_default().clone(),
+            Err(_) => ConnectionSlot::default(),
+        }
+    }
+
+    /// 注销连接并取出其句柄（由调用方 shutdown）
+    pub async fn remove(&self, id: &ConnectionId) -> Option<CommActorHandle> {
+        let slot = self.slots.write().ok()?.remove(id)?;
+        let handle = slot.lock().await.take();
+        handle
+    }
+
+    pub async fn list(&self) -> Vec<ConnectionInfo> {
+        let slots: Vec<(ConnectionId, ConnectionSlot)> = match self.slots.read() {
+            Ok(slots) => slots
+                .iter()
+                .map(|(id, slot)| (id.clone(), slot.clone()))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+
+        let mut infos = Vec::with_capacity(slots.len());
+        for (id, slot) in slots {
+            if let Some(handle) = slot.lock().await.as_ref() {
+                infos.push(ConnectionInfo {
+                    id,
+                    kind: handle.kind().to_string(),
+                    target: handle.target().to_string(),
+                    connected: handle.is_link_up(),
+                    created_at_ms: handle.created_at_ms(),
+                });
+            }
+        }
+        infos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_user_ids() {
+        assert!(ConnectionId::new("plc").validate().is_ok());
+        assert!(ConnectionId::new("scale-1.A_2").validate().is_ok());
+        assert!(ConnectionId::new("").validate().is_err());
+        assert!(ConnectionId::new("../x").validate().is_err());
+        assert!(ConnectionId::new("a:b").validate().is_err());
+        assert!(ConnectionId::new(".hidden").validate().is_err());
+        assert!(ConnectionId::new("tcp_server").validate().is_err());
+        assert!(ConnectionId::new("x".repeat(65)).validate().is_err());
+    }
+
+    #[test]
+    fn reserved_ids_keep_their_kind() {
+        let tcp = ConnectionConfig::Tcp(tcp::TcpConfig::default());
+        assert!(tcp.check_id(&ConnectionId::tcp()).is_ok());
+        assert!(tcp.check_id(&ConnectionId::new("plc")).is_ok());
+        assert!(tcp.check_id(&ConnectionId::serial()).is_err());
+        assert_eq!(ConnectionId::new("plc").label(), "Connection 'plc'");
+        assert_eq!(ConnectionId::serial().label(), "Serial");
+    }
+
+    #[test]
+    fn connection_config_is_tagged_by_kind() {
+        let config: ConnectionConfig = serde_json::from_str(
+            r#"{"kind":"tcp","host":"10.0.0.2","port":502,"timeout_ms":1000}"#,
+        )
+        .unwrap();
+        assert_eq!(config.kind(), "tcp");
+        assert!(config.link().outbox.is_none());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::comm::{actor::CommPriority, proto, queue, sequence};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// HMIP 请求/响应关联：request_id → 等待中的调用方
+///
+/// 设计要点：
+/// - pending 表只存在于 actor 任务内部（单线程访问，无需加锁）
+/// - 超时由调用方（command）控制：超时后 receiver 被 drop，actor 在后续登记时顺带清理
+/// - 连接断开/actor 退出时，所有未完成请求统一以错误结束，避免调用方悬挂到超时
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 5000;
+
+// 防止对端长期不回包导致 pending 表无限增长
+const MAX_PENDING_REQUESTS: usize = 256;
+
+pub type RequestReply = oneshot::Sender<Result<proto::Response, String>>;
+
+/// 从 command 投递到 actor 的请求（payload 已编码，actor 出队时封帧）
+pub struct OutboundRequest {
+    pub request_id: u32,
+    /// 记录 method，收到 Response 时据此选择 body schema
+    pub method: u16,
+    pub frame: queue::HmipFrame,
+    pub reply: RequestReply,
+}
+
+struct Waiting {
+    method: u16,
+    reply: RequestReply,
+}
+
+#[derive(Default)]
+pub struct PendingRequests {
+    waiting: HashMap<u32, Waiting>,
+}
+
+impl PendingRequests {
+    /// 登记一个等待响应的请求。
+    ///
+    /// 返回 false 表示登记失败（已通过 reply 通知调用方），此时不应再发送该请求帧。
+    pub fn register(&mut self, request_id: u32, method: u16, reply: RequestReply) -> bool {
+        // 调用方已超时/取消的条目没有必要继续保留
+        self.waiting.retain(|_, waiting| !waiting.reply.is_closed());
+
+        if self.waiting.contains_key(&request_id) {
+            let _ = reply.send(Err(format!(
+                "Duplicate pending request_id {}",
+                request_id
+            )));
+            return false;
+        }
+        if self.waiting.len() >= MAX_PENDING_REQUESTS {
+            let _ = reply.send(Err(format!(
+                "Too many pending requests (max={})",
+                MAX_PENDING_REQUESTS
+            )));
+            return false;
+        }
+
+        self.waiting.insert(request_id, Waiting { method, reply });
+        true
+    }
+
+    /// 用收到的 Response 唤醒对应调用方；返回登记时的 method（未登记时为 None）。
+    pub fn resolve(&mut self, response: proto::Response) -> Option<u16> {
+        let waiting = self.waiting.remove(&response.request_id)?;
+        // 调用方已超时放弃时 send 失败，但 method 仍可用于解码 body
+        let _ = waiting.reply.send(Ok(response));
+        Some(waiting.method)
+    }
+
+    /// 以同一原因结束全部未完成请求（断线/关闭）。
+    pub fn fail_all(&mut self, reason: &str) {
+        for (_, waiting) in self.waiting.drain() {
+            let _ = waiting.reply.send(Err(reason.to_string()));
+        }
+    }
+}
+
+/// 一次 Request 调用的参数
+#[derive(Debug, Clone)]
+pub struct RpcCall {
+    pub method: u16,
+    pub body: Vec<u8>,
+    pub channel: u8,
+    pub flags: u8,
+    /// None 时由连接的 seq 分配器分配
+    pub seq: Option<u32>,
+    /// 发送队列名；None 时按 channel 映射，未映射时进入 normal 队列
+    pub queue: Option<String>,
+    /// 排队超过该时长未发出则丢弃（调用方收到错误）
+    pub ttl_ms: Option<u64>,
+    pub timeout: Duration,
+}
+
+impl RpcCall {
+    pub fn new(method: u16, body: Vec<u8>) -> Self {
+        Self {
+            method,
+            body,
+            channel: 0,
+            flags: proto::FLAG_CRC32,
+            seq: None,
+            queue: None,
+            ttl_ms: None,
+            timeout: Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MS),
+        }
+    }
+}
+
+/// 脱离连接锁发起 Request 的客户端（从 `CommActorHandle::rpc_client` 获取）
+///
+/// 说明：
+/// - 只持有 actor 的请求队列与分配器，可在后台任务中长期使用
+/// - 帧头版本在每次调用时按当前握手结果选择（重连后可能变化）
+/// - actor 退出后调用返回 "connection is closed"
+#[derive(Clone)]
+pub struct RpcClient {
+    label: String,
+    tx_queues: Arc<queue::TxQueues>,
+    next_request_id: Arc<AtomicU32>,
+    tx_seq: Arc<sequence::SeqGenerator>,
+}
+
+impl RpcClient {
+    pub fn new(
+        label: String,
+        tx_queues: Arc<queue::TxQueues>,
+        next_request_id: Arc<AtomicU32>,
+        tx_seq: Arc<sequence::SeqGenerator>,
+    ) -> Self {
+        Self {
+            label,
+            tx_queues,
+            next_request_id,
+            tx_seq,
+        }
+    }
+
+    /// 发送 Request 并等待匹配 request_id 的 Response，返回（帧 seq，Response）
+    ///
+    /// 返回的 seq 为入队时分配的值；seq 计数器在请求发出前因重连被重置时，实际发出的 seq 会重新分配。
+    pub async fn call(&self, call: RpcCall) -> Result<(u32, proto::Response), String> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let seq = match call.seq {
+            Some(seq) => sequence::TxSeq::Explicit(seq),
+            None => self.tx_seq.assign(call.channel),
+        };
+
+        let payload = proto::encode_request(&proto::Request {
+            request_id,
+            method: call.method,
+            body: call.body.into(),
+        });
+        let frame = queue::HmipFrame {
+            msg_type: proto::msg_type::REQUEST,
+            flags: call.flags,
+            channel: call.channel,
+            seq,
+            version: None,
+            destination: None,
+            payload,
+        };
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let target = queue::QueueTarget {
+            queue: call.queue.as_deref(),
+            channel: Some(call.channel),
+            priority: CommPriority::Normal,
+            ttl_ms: call.ttl_ms,
+        };
+        let request = OutboundRequest {
+            request_id,
+            method: call.method,
+            frame,
+            reply: reply_tx,
+        };
+        self.tx_queues
+            .try_push(target, queue::Outbound::Request(request))
+            .map_err(|err| err.message(&self.label))?;
+
+        let response = tokio::time::timeout(call.timeout, reply_rx)
+            .await
+            .map_err(|_| {
+                format!(
+                    "HMIP request {} timed out ({}ms)",
+                    request_id,
+                    call.timeout.as_millis()
+                )
+            })?
+            .map_err(|_| format!("HMIP request {} cancelled", request_id))??;
+        Ok((seq.value(), response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn response(request_id: u32, status: u16) -> proto::Response {
+        proto::Response {
+            request_id,
+            status,
+            body: Bytes::from_static(b"ok"),
+        }
+    }
+
+    #[test]
+    fn resolve_wakes_matching_request() {
+        let mut pending = PendingRequests::default();
+        let (tx, mut rx) = oneshot::channel();
+        assert!(pending.register(7, 0x0102, tx));
+
+        assert_eq!(pending.resolve(response(8, 0)), None);
+        assert_eq!(pending.resolve(response(7, 0)), Some(0x0102));
+
+        let resp = rx.try_recv().unwrap().unwrap();
+        assert_eq!(resp.request_id, 7);
+        assert_eq!(resp.body.as_ref(), b"ok");
+        assert!(pending.waiting.is_empty());
+    }
+
+    #[test]
+    fn duplicate_request_id_is_rejected() {
+        let mut pending = PendingRequests::default();
+        let (tx1, _rx1) = oneshot::channel();
+        let (tx2, mut rx2) = oneshot::channel();
+
+        assert!(pending.register(1, 0, tx1));
+        assert!(!pending.register(1, 0, tx2));
+
+        let err = rx2.try_recv().unwrap().unwrap_err();
+        assert!(err.contains("Duplicate"));
+        assert_eq!(pending.waiting.len(), 1);
+    }
+
+    #[test]
+    fn abandoned_requests_are_pruned_on_register() {
+        let mut pending = PendingRequests::default();
+        let (tx1, rx1) = oneshot::channel();
+        assert!(pending.register(1, 0, tx1));
+        drop(rx1);
+
+        let (tx2, _rx2) = oneshot::channel();
+        assert!(pending.register(2, 0, tx2));
+        assert_eq!(pending.waiting.len(), 1);
+    }
+
+    #[test]
+    fn fail_all_reports_reason() {
+        let mut pending = PendingRequests::default();
+        let (tx, mut rx) = oneshot::channel();
+        assert!(pending.register(3, 0, tx));
+
+        pending.fail_all("Connection lost");
+        assert_eq!(rx.try_recv().unwrap().unwrap_err(), "Connection lost");
+        assert!(pending.waiting.is_empty());
+    }
+}
@@ -0,0 +1,518 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// HMIP body 声明式 schema 注册表
+///
+/// 作用：按 method / event_id / msg_type 把 Request/Response/Event/Raw 的 body
+/// 解码为 JSON 对象，随 `hmip-event` 的 message summary 一起推送，前端无需手写字节解析。
+///
+/// 配置文件（JSON）示例：
+/// ```json
+/// {
+///   "methods": [
+///     { "method": 258, "name": "read_status",
+///       "request": [{ "name": "slot", "type": "u8" }],
+///       "response": [
+///         { "name": "state", "type": "enum", "repr": "u8", "values": { "0": "idle", "1": "run" } },
+///         { "name": "temps", "type": "array", "items": { "type": "f32" }, "prefix": "u8" }
+///       ] }
+///   ],
+///   "events": [{ "event_id": 1, "name": "door", "body": [{ "name": "open", "type": "bool" }] }],
+///   "msg_types": [{ "msg_type": 64, "name": "vendor", "body": [{ "name": "text", "type": "string" }] }]
+/// }
+/// ```
+///
+/// 约定：
+/// - 所有数值按小端解码（与 HMIP 其余字段一致）
+/// - string/bytes/array 默认以 u16 长度（array 为元素个数）前缀；指定 `size` 时为定长
+/// - 定长 string 会去掉尾部的 0 填充；bytes 输出为 base64
+/// - body 末尾多余字节视为解码失败（通常意味着 schema 与固件版本不一致）
+pub const DEFAULT_SCHEMA_FILE: &str = "hmip_schema.json";
+
+// 防止零宽元素 + 超大个数前缀导致长时间空转
+const MAX_ARRAY_ITEMS: usize = 65536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntRepr {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LenPrefix {
+    U8,
+    #[default]
+    U16,
+    U32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Bool,
+    String {
+        #[serde(default)]
+        prefix: LenPrefix,
+        size: Option<usize>,
+    },
+    Bytes {
+        #[serde(default)]
+        prefix: LenPrefix,
+        size: Option<usize>,
+    },
+    Array {
+        items: Box<FieldType>,
+        #[serde(default)]
+        prefix: LenPrefix,
+        size: Option<usize>,
+    },
+    Struct {
+        fields: Vec<FieldSpec>,
+    },
+    Enum {
+        repr: IntRepr,
+        /// key 为十进制数值字符串
+        values: HashMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    #[serde(flatten)]
+    pub ty: FieldType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MethodSchema {
+    pub method: u16,
+    pub name: String,
+    #[serde(default)]
+    pub request: Option<Vec<FieldSpec>>,
+    #[serde(default)]
+    pub response: Option<Vec<FieldSpec>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventSchema {
+    pub event_id: u16,
+    pub name: String,
+    pub body: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MsgTypeSchema {
+    pub msg_type: u8,
+    pub name: String,
+    pub body: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SchemaFile {
+    pub methods: Vec<MethodSchema>,
+    pub events: Vec<EventSchema>,
+    pub msg_types: Vec<MsgTypeSchema>,
+}
+
+/// 需要解码的 body 类别（Response 的 method 由 pending 表按 request_id 反查）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    Request(u16),
+    Response(u16),
+    Event(u16),
+    Raw(u8),
+}
+
+/// 解码结果（随 summary 推送；失败时 fields 为空并给出原因）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedBody {
+    pub schema: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 已加载的 schema（command 查询/加载时返回）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaInfo {
+    pub path: Option<String>,
+    pub methods: usize,
+    pub events: usize,
+    pub msg_types: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct SchemaRegistry {
+    path: Option<String>,
+    methods: HashMap<u16, MethodSchema>,
+    events: HashMap<u16, EventSchema>,
+    msg_types: HashMap<u8, MsgTypeSchema>,
+}
+
+impl SchemaRegistry {
+    pub fn from_file(file: SchemaFile) -> Result<Self, String> {
+        let mut registry = Self::default();
+        for m in file.methods {
+            if let Some(old) = registry.methods.insert(m.method, m) {
+                return Err(format!("Duplicate schema for method {}", old.method));
+            }
+        }
+        for e in file.events {
+            if let Some(old) = registry.events.insert(e.event_id, e) {
+                return Err(format!("Duplicate schema for event_id {}", old.event_id));
+            }
+        }
+        for t in file.msg_types {
+            if let Some(old) = registry.msg_types.insert(t.msg_type, t) {
+                return Err(format!("Duplicate schema for msg_type {}", old.msg_type));
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read schema file {}: {}", path.display(), e))?;
+        let file: SchemaFile = serde_json::from_str(&raw)
+            .map_err(|e| format!("Invalid schema file {}: {}", path.display(), e))?;
+        let mut registry = Self::from_file(file)?;
+        registry.path = Some(path.display().to_string());
+        Ok(registry)
+    }
+
+    pub fn info(&self) -> SchemaInfo {
+        SchemaInfo {
+            path: self.path.clone(),
+            methods: self.methods.len(),
+            events: self.events.len(),
+            msg_types: self.msg_types.len(),
+        }
+    }
+
+    /// 查找并解码；未注册的 body 返回 None（前端仍可使用 base64 预览）
+    ///
+    /// body 超过 `max_len` 时不解码（解码结果随事件推送，不能绕过事件的预览上限），只给出原因
+    pub fn decode(&self, kind: BodyKind, body: &[u8], max_len: usize) -> Option<DecodedBody> {
+        let (name, fields) = match kind {
+            BodyKind::Request(method) => {
+                let m = self.methods.get(&method)?;
+                (&m.name, m.request.as_ref()?)
+            }
+            BodyKind::Response(method) => {
+                let m = self.methods.get(&method)?;
+                (&m.name, m.response.as_ref()?)
+            }
+            BodyKind::Event(event_id) => {
+                let e = self.events.get(&event_id)?;
+                (&e.name, &e.body)
+            }
+            BodyKind::Raw(msg_type) => {
+                let t = self.msg_types.get(&msg_type)?;
+                (&t.name, &t.body)
+            }
+        };
+
+        if body.len() > max_len {
+            return Some(DecodedBody {
+                schema: name.clone(),
+                fields: None,
+                error: Some(format!(
+                    "Body too large to decode inline ({} > {} bytes)",
+                    body.len(),
+                    max_len
+                )),
+            });
+        }
+
+        let mut reader = Reader { buf: body, pos: 0 };
+        let result = decode_struct(&mut reader, fields).and_then(|value| {
+            if reader.remaining() != 0 {
+                return Err(format!(
+                    "{} trailing bytes after last field",
+                    reader.remaining()
+                ));
+            }
+            Ok(value)
+        });
+
+        Some(match result {
+            Ok(value) => DecodedBody {
+                schema: name.clone(),
+                fields: Some(value),
+                error: None,
+            },
+            Err(err) => DecodedBody {
+                schema: name.clone(),
+                fields: None,
+                error: Some(err),
+            },
+        })
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.remaining() < n {
+            return Err(format!(
+                "Unexpected end of body at offset {} (need {} bytes, have {})",
+                self.pos,
+                n,
+                self.remaining()
+            ));
+        }
+        let out = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(out)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn len_prefix(&mut self, prefix: LenPrefix) -> Result<usize, String> {
+        Ok(match prefix {
+            LenPrefix::U8 => self.array::<1>()?[0] as usize,
+            LenPrefix::U16 => u16::from_le_bytes(self.array()?) as usize,
+            LenPrefix::U32 => u32::from_le_bytes(self.array()?) as usize,
+        })
+    }
+
+    fn int(&mut self, repr: IntRepr) -> Result<i128, String> {
+        Ok(match repr {
+            IntRepr::U8 => self.array::<1>()?[0] as i128,
+            IntRepr::U16 => u16::from_le_bytes(self.array()?) as i128,
+            IntRepr::U32 => u32::from_le_bytes(self.array()?) as i128,
+            IntRepr::U64 => u64::from_le_bytes(self.array()?) as i128,
+            IntRepr::I8 => i8::from_le_bytes(self.array()?) as i128,
+            IntRepr::I16 => i16::from_le_bytes(self.array()?) as i128,
+            IntRepr::I32 => i32::from_le_bytes(self.array()?) as i128,
+            IntRepr::I64 => i64::from_le_bytes(self.array()?) as i128,
+        })
+    }
+}
+
+fn int_value(v: i128) -> Value {
+    if v < 0 {
+        Value::Number(Number::from(v as i64))
+    } else {
+        Value::Number(Number::from(v as u64))
+    }
+}
+
+fn float_value(v: f64) -> Value {
+    // NaN/Inf 无法用 JSON 表示
+    Number::from_f64(v).map(Value::Number).unwrap_or(Value::Null)
+}
+
+fn decode_struct(reader: &mut Reader<'_>, fields: &[FieldSpec]) -> Result<Value, String> {
+    let mut obj = Map::with_capacity(fields.len());
+    for field in fields {
+        let value = decode_field(reader, &field.ty).map_err(|e| format!("{}: {}", field.name, e))?;
+        obj.insert(field.name.clone(), value);
+    }
+    Ok(Value::Object(obj))
+}
+
+fn decode_field(reader: &mut Reader<'_>, ty: &FieldType) -> Result<Value, String> {
+    Ok(match ty {
+        FieldType::U8 => int_value(reader.int(IntRepr::U8)?),
+        FieldType::U16 => int_value(reader.int(IntRepr::U16)?),
+        FieldType::U32 => int_value(reader.int(IntRepr::U32)?),
+        FieldType::U64 => int_value(reader.int(IntRepr::U64)?),
+        FieldType::I8 => int_value(reader.int(IntRepr::I8)?),
+        FieldType::I16 => int_value(reader.int(IntRepr::I16)?),
+        FieldType::I32 => int_value(reader.int(IntRepr::I32)?),
+        FieldType::I64 => int_value(reader.int(IntRepr::I64)?),
+        FieldType::F32 => float_value(f32::from_le_bytes(reader.array()?) as f64),
+        FieldType::F64 => float_value(f64::from_le_bytes(reader.array()?)),
+        FieldType::Bool => Value::Bool(reader.array::<1>()?[0] != 0),
+        FieldType::String { prefix, size } => {
+            let len = match size {
+                Some(size) => *size,
+                None => reader.len_prefix(*prefix)?,
+            };
+            let mut bytes = reader.take(len)?;
+            if size.is_some() {
+                while let [rest @ .., 0] = bytes {
+                    bytes = rest;
+                }
+            }
+            Value::String(String::from_utf8_lossy(bytes).into_owned())
+        }
+        FieldType::Bytes { prefix, size } => {
+            let len = match size {
+                Some(size) => *size,
+                None => reader.len_prefix(*prefix)?,
+            };
+            Value::String(general_purpose::STANDARD.encode(reader.take(len)?))
+        }
+        FieldType::Array {
+            items,
+            prefix,
+            size,
+        } => {
+            let count = match size {
+                Some(size) => *size,
+                None => reader.len_prefix(*prefix)?,
+            };
+            if count > MAX_ARRAY_ITEMS {
+                return Err(format!(
+                    "Array too long (count={}, max={})",
+                    count, MAX_ARRAY_ITEMS
+                ));
+            }
+            let mut out = Vec::with_capacity(count.min(reader.remaining()));
+            for index in 0..count {
+                out.push(decode_field(reader, items).map_err(|e| format!("[{}]: {}", index, e))?);
+            }
+            Value::Array(out)
+        }
+        FieldType::Struct { fields } => decode_struct(reader, fields)?,
+        FieldType::Enum { repr, values } => {
+            let raw = reader.int(*repr)?;
+            match values.get(&raw.to_string()) {
+                Some(name) => Value::String(name.clone()),
+                None => int_value(raw),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: usize = 2048;
+    use serde_json::json;
+
+    fn registry() -> SchemaRegistry {
+        let file: SchemaFile = serde_json::from_value(json!({
+            "methods": [{
+                "method": 258,
+                "name": "read_status",
+                "request": [{ "name": "slot", "type": "u8" }],
+                "response": [
+                    { "name": "state", "type": "enum", "repr": "u8", "values": { "0": "idle", "1": "run" } },
+                    { "name": "temps", "type": "array", "items": { "type": "f32" }, "prefix": "u8" },
+                    { "name": "label", "type": "string", "size": 6 },
+                    { "name": "offset", "type": "i16" }
+                ]
+            }],
+            "events": [{
+                "event_id": 7,
+                "name": "door",
+                "body": [
+                    { "name": "open", "type": "bool" },
+                    { "name": "who", "type": "struct", "fields": [
+                        { "name": "id", "type": "u32" },
+                        { "name": "name", "type": "string", "prefix": "u8" }
+                    ]}
+                ]
+            }]
+        }))
+        .unwrap();
+        SchemaRegistry::from_file(file).unwrap()
+    }
+
+    #[test]
+    fn decodes_response_with_enum_array_and_fixed_string() {
+        let mut body = vec![1u8, 2];
+        body.extend_from_slice(&1.5f32.to_le_bytes());
+        body.extend_from_slice(&(-2.0f32).to_le_bytes());
+        body.extend_from_slice(b"PM1\0\0\0");
+        body.extend_from_slice(&(-3i16).to_le_bytes());
+
+        let decoded = registry().decode(BodyKind::Response(258), &body, MAX).unwrap();
+        assert_eq!(decoded.schema, "read_status");
+        assert_eq!(
+            decoded.fields.unwrap(),
+            json!({ "state": "run", "temps": [1.5, -2.0], "label": "PM1", "offset": -3 })
+        );
+    }
+
+    #[test]
+    fn decodes_nested_struct_in_event() {
+        let mut body = vec![1u8];
+        body.extend_from_slice(&42u32.to_le_bytes());
+        body.push(3);
+        body.extend_from_slice(b"bob");
+
+        let decoded = registry().decode(BodyKind::Event(7), &body, MAX).unwrap();
+        assert_eq!(
+            decoded.fields.unwrap(),
+            json!({ "open": true, "who": { "id": 42, "name": "bob" } })
+        );
+    }
+
+    #[test]
+    fn reports_truncated_and_trailing_bodies() {
+        let reg = registry();
+
+        let short = reg.decode(BodyKind::Response(258), &[0, 1, 0], MAX).unwrap();
+        assert!(short.fields.is_none());
+        assert!(short.error.unwrap().starts_with("temps: [0]"));
+
+        let trailing = reg.decode(BodyKind::Request(258), &[1, 2], MAX).unwrap();
+        assert!(trailing.error.unwrap().contains("trailing"));
+
+        assert!(reg.decode(BodyKind::Request(999), &[], MAX).is_none());
+        assert!(reg.decode(BodyKind::Raw(0x40), &[], MAX).is_none());
+    }
+
+    #[test]
+    fn oversized_body_is_not_decoded() {
+        let mut body = vec![1u8];
+        body.extend_from_slice(&42u32.to_le_bytes());
+        body.push(3);
+        body.extend_from_slice(b"bob");
+
+        let decoded = registry().decode(BodyKind::Event(7), &body, 4).unwrap();
+        assert_eq!(decoded.schema, "door");
+        assert!(decoded.fields.is_none());
+        assert!(decoded.error.unwrap().contains("too large"));
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let file: SchemaFile = serde_json::from_value(json!({
+            "events": [
+                { "event_id": 1, "name": "a", "body": [] },
+                { "event_id": 1, "name": "b", "body": [] }
+            ]
+        }))
+        .unwrap();
+        assert!(SchemaRegistry::from_file(file).is_err());
+    }
+}
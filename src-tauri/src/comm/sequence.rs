@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// 接收侧 seq 连续性检查（按 channel）
+///
+/// 说明：
+/// - 以“期望下一个 seq = 上一个最大 seq + 1”判断：大于期望为丢帧（gap），
+///   小于期望时在最近 64 个 seq 内已见过为重复，否则为乱序/迟到
+/// - seq 比较按 u32 回绕处理（差值落在前半区间视为“更新”）
+/// - 迟到帧会冲抵此前 gap 统计的 missing，missing 即“目前仍未收到”的帧数
+/// - seq 回退超出 64 的历史窗口，或回到 0/1，视为对端重启（链路未断开时也会发生）：
+///   该 channel 从当前 seq 重新跟踪，missing 清零
+/// - 跟踪状态随连接重建清空（对端重启后 seq 会从头开始），统计计数跨重连累计
+const HISTORY_BITS: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqAnomaly {
+    Gap { expected: u32, missing: u32 },
+    Duplicate,
+    OutOfOrder { expected: u32 },
+    /// 对端 seq 重新开始（previous 为此前的最大 seq）
+    Reset { previous: u32 },
+}
+
+struct ChannelTrack {
+    highest: u32,
+    // bit i 表示 highest - i 已收到
+    seen: u64,
+}
+
+/// 单个连接上的 seq 跟踪器
+#[derive(Default)]
+pub struct SeqTracker {
+    channels: HashMap<u8, ChannelTrack>,
+}
+
+impl SeqTracker {
+    pub fn observe(&mut self, channel: u8, seq: u32) -> Option<SeqAnomaly> {
+        let Some(track) = self.channels.get_mut(&channel) else {
+            self.channels.insert(channel, ChannelTrack { highest: seq, seen: 1 });
+            return None;
+        };
+
+        let expected = track.highest.wrapping_add(1);
+        let ahead = seq.wrapping_sub(track.highest);
+        if ahead != 0 && ahead < 1 << 31 {
+            track.seen = if ahead >= HISTORY_BITS { 1 } else { (track.seen << ahead) | 1 };
+            track.highest = seq;
+            return match ahead {
+                1 => None,
+                _ => Some(SeqAnomaly::Gap {
+                    expected,
+                    missing: ahead - 1,
+                }),
+            };
+        }
+
+        let behind = track.highest.wrapping_sub(seq);
+        if behind >= HISTORY_BITS || (seq <= 1 && behind > 0) {
+            let previous = track.highest;
+            *track = ChannelTrack { highest: seq, seen: 1 };
+            return Some(SeqAnomaly::Reset { previous });
+        }
+        let bit = 1u64 << behind;
+        if track.seen & bit != 0 {
+            return Some(SeqAnomaly::Duplicate);
+        }
+        track.seen |= bit;
+        Some(SeqAnomaly::OutOfOrder { expected })
+    }
+}
+
+/// 单个 channel 的累计统计（用于 `get_hmip_seq_stats`）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChannelSeqStats {
+    pub channel: u8,
+    pub received: u64,
+    pub last_seq: u32,
+    pub gaps: u64,
+    /// 仍未收到的帧数（gap 累加，迟到帧冲抵）
+    pub missing: u64,
+    pub duplicates: u64,
+    pub out_of_order: u64,
+    /// 检测到对端 seq 重新开始的次数
+    pub resets: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SeqStats {
+    channels: BTreeMap<u8, ChannelSeqStats>,
+}
+
+impl SeqStats {
+    pub fn record(&mut self, channel: u8, seq: u32, anomaly: Option<SeqAnomaly>) {
+        let stats = self.channels.entry(channel).or_insert_with(|| ChannelSeqStats {
+            channel,
+            ..Default::default()
+        });
+        stats.received += 1;
+        match anomaly {
+            None => stats.last_seq = seq,
+            Some(SeqAnomaly::Gap { missing, .. }) => {
+                stats.last_seq = seq;
+                stats.gaps += 1;
+                stats.missing += u64::from(missing);
+            }
+            Some(SeqAnomaly::Duplicate) => stats.duplicates += 1,
+            Some(SeqAnomaly::OutOfOrder { .. }) => {
+                stats.out_of_order += 1;
+                stats.missing = stats.missing.saturating_sub(1);
+            }
+            // 重启前缺失的帧不会再到达
+            Some(SeqAnomaly::Reset { .. }) => {
+                stats.last_seq = seq;
+                stats.resets += 1;
+                stats.missing = 0;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<ChannelSeqStats> {
+        self.channels.values().cloned().collect()
+    }
+}
+
+/// 发送侧 seq 的分配粒度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeqScope {
+    /// 整个连接共用一个计数器（各 channel 交错递增）
+    #[default]
+    Connection,
+    /// 每个 channel 独立计数（对端按 channel 校验连续性时使用）
+    Channel,
+}
+
+/// 重连后发送侧 seq 的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeqResetPolicy {
+    /// 每次建立连接从 1 重新开始（对端多按新连接重置跟踪）
+    #[default]
+    Reset,
+    /// 跨重连延续上次的计数
+    Persist,
+}
+
+/// 发送侧 seq 配置（挂在 HmipLinkConfig.seq 上）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeqConfig {
+    pub scope: SeqScope,
+    pub on_reconnect: SeqResetPolicy,
+}
+
+const TX_SEQ_START: u32 = 1;
+
+struct TxCounter {
+    next: u32,
+    allocated: u64,
+    wraps: u64,
+}
+
+impl Default for TxCounter {
+    fn default() -> Self {
+        Self {
+            next: TX_SEQ_START,
+            allocated: 0,
+            wraps: 0,
+        }
+    }
+}
+
+/// 单个发送计数器的快照
+#[derive(Debug, Clone, Serialize)]
+pub struct TxSeqState {
+    /// SeqScope::Connection 时为 None
+    pub channel: Option<u8>,
+    /// 下一个将要分配的 seq
+    pub next_seq: u32,
+    /// 本计数器自创建（或上次重置）以来分配的 seq 数
+    pub allocated: u64,
+    /// u32 回绕次数
+    pub wraps: u64,
+}
+
+/// 发送侧 seq 概况（`get_hmip_tx_seq` 返回值）
+#[derive(Debug, Clone, Serialize)]
+pub struct TxSeqSnapshot {
+    pub config: SeqConfig,
+    /// 尚未分配过的计数器不出现（其下一个 seq 为 1）
+    pub counters: Vec<TxSeqState>,
+}
+
+/// 连接级发送 seq 分配器
+///
+/// 说明：
+/// - 每个 CommActorHandle 持有一个，serial/tcp/各监听对端互不影响
+/// - 回绕时从 0 继续（接收侧按 u32 回绕判断连续），并记录日志与回绕次数
+/// - 调用方显式指定 seq 的帧不经过分配器，也不推进计数
+/// - Reset 策略下每次重连递增代次（epoch）：入队时分配的 seq 若早于当前代次，发出前重新分配
+pub struct SeqGenerator {
+    config: SeqConfig,
+    counters: Mutex<BTreeMap<Option<u8>, TxCounter>>,
+    epoch: AtomicU64,
+}
+
+/// 排队帧的 seq
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxSeq {
+    /// 调用方显式指定，原样发出
+    Explicit(u32),
+    /// 由分配器分配（记录分配时的代次）
+    Assigned { seq: u32, epoch: u64 },
+}
+
+impl TxSeq {
+    pub fn value(self) -> u32 {
+        match self {
+            Self::Explicit(seq) | Self::Assigned { seq, .. } => seq,
+        }
+    }
+}
+
+impl SeqGenerator {
+    pub fn new(config: SeqConfig) -> Self {
+        Self {
+            config,
+            counters: Mutex::new(BTreeMap::new()),
+            epoch: AtomicU64::new(0),
+        }
+    }
+
+    fn key(&self, channel: u8) -> Option<u8> {
+        match self.config.scope {
+            SeqScope::Connection => None,
+            SeqScope::Channel => Some(channel),
+        }
+    }
+
+    /// 为 channel 上的下一帧分配 seq
+    pub fn next(&self, channel: u8) -> u32 {
+        self.assign(channel).value()
+    }
+
+    /// 为排队帧分配 seq（与代次一起在计数器锁内读取）
+    pub fn assign(&self, channel: u8) -> TxSeq {
+        let key = self.key(channel);
+        let Ok(mut counters) = self.counters.lock() else {
+            return TxSeq::Explicit(TX_SEQ_START);
+        };
+        let epoch = self.epoch.load(Ordering::Acquire);
+        let counter = counters.entry(key).or_default();
+        let seq = counter.next;
+        counter.next = seq.wrapping_add(1);
+        counter.allocated += 1;
+        if counter.next == 0 {
+            counter.wraps += 1;
+            log::info!("HMIP tx seq wrapped (channel {:?}, wraps {})", key, counter.wraps);
+        }
+        TxSeq::Assigned { seq, epoch }
+    }
+
+    /// 排队帧发出（或重连重排）前调用：分配于上一代次的 seq 重新分配，返回最终 seq
+    pub fn refresh(&self, channel: u8, seq: &mut TxSeq) -> u32 {
+        if let TxSeq::Assigned { epoch, .. } = *seq {
+            if epoch != self.epoch.load(Ordering::Acquire) {
+                *seq = self.assign(channel);
+            }
+        }
+        seq.value()
+    }
+
+    /// 连接（重新）建立时调用：Reset 策略下清空全部计数器并进入新代次，返回是否发生了重置
+    pub fn on_connected(&self) -> bool {
+        if self.config.on_reconnect != SeqResetPolicy::Reset {
+            return false;
+        }
+        if let Ok(mut counters) = self.counters.lock() {
+            counters.clear();
+            self.epoch.fetch_add(1, Ordering::AcqRel);
+        }
+        true
+    }
+
+    pub fn snapshot(&self) -> TxSeqSnapshot {
+        let counters = self
+            .counters
+            .lock()
+            .map(|counters| {
+                counters
+                    .iter()
+                    .map(|(channel, counter)| TxSeqState {
+                        channel: *channel,
+                        next_seq: counter.next,
+                        allocated: counter.allocated,
+                        wraps: counter.wraps,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        TxSeqSnapshot {
+            config: self.config,
+            counters,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_gap_duplicate_and_late_frames() {
+        let mut tracker = SeqTracker::default();
+        assert_eq!(tracker.observe(0, 10), None);
+        assert_eq!(tracker.observe(0, 11), None);
+        assert_eq!(
+            tracker.observe(0, 14),
+            Some(SeqAnomaly::Gap {
+                expected: 12,
+                missing: 2
+            })
+        );
+        assert_eq!(tracker.observe(0, 12), Some(SeqAnomaly::OutOfOrder { expected: 15 }));
+        assert_eq!(tracker.observe(0, 12), Some(SeqAnomaly::Duplicate));
+        assert_eq!(tracker.observe(0, 14), Some(SeqAnomaly::Duplicate));
+        assert_eq!(tracker.observe(0, 15), None);
+
+        // 其他 channel 独立跟踪
+        assert_eq!(tracker.observe(1, 100), None);
+    }
+
+    #[test]
+    fn seq_wraparound_is_in_order() {
+        let mut tracker = SeqTracker::default();
+        assert_eq!(tracker.observe(0, u32::MAX), None);
+        assert_eq!(tracker.observe(0, 0), None);
+        assert_eq!(
+            tracker.observe(0, 2),
+            Some(SeqAnomaly::Gap {
+                expected: 1,
+                missing: 1
+            })
+        );
+    }
+
+    #[test]
+    fn peer_restart_resyncs_the_channel() {
+        let mut tracker = SeqTracker::default();
+        let mut stats = SeqStats::default();
+        for seq in [500, 501, 505] {
+            let anomaly = tracker.observe(0, seq);
+            stats.record(0, seq, anomaly);
+        }
+
+        // 对端重启：seq 从 1 重新开始，之后恢复正常统计
+        let anomaly = tracker.observe(0, 1);
+        assert_eq!(anomaly, Some(SeqAnomaly::Reset { previous: 505 }));
+        stats.record(0, 1, anomaly);
+        assert_eq!(tracker.observe(0, 2), None);
+        assert_eq!(tracker.observe(0, 3), None);
+
+        // 回退超出历史窗口同样重新跟踪
+        assert_eq!(tracker.observe(0, 1000), Some(SeqAnomaly::Gap { expected: 4, missing: 996 }));
+        assert_eq!(tracker.observe(0, 900), Some(SeqAnomaly::Reset { previous: 1000 }));
+        assert_eq!(tracker.observe(0, 901), None);
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot[0].resets, snapshot[0].missing, snapshot[0].last_seq), (1, 0, 1));
+    }
+
+    #[test]
+    fn stats_offset_missing_with_late_frames() {
+        let mut tracker = SeqTracker::default();
+        let mut stats = SeqStats::default();
+        for seq in [1, 4, 2, 2, 5] {
+            stats.record(0, seq, tracker.observe(0, seq));
+        }
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        let ch = &snapshot[0];
+        assert_eq!(ch.received, 5);
+        assert_eq!(ch.last_seq, 5);
+        assert_eq!(ch.gaps, 1);
+        assert_eq!(ch.missing, 1);
+        assert_eq!(ch.duplicates, 1);
+        assert_eq!(ch.out_of_order, 1);
+    }
+
+    #[test]
+    fn tx_seq_scope_and_reconnect_policy() {
+        let per_channel = SeqGenerator::new(SeqConfig {
+            scope: SeqScope::Channel,
+            on_reconnect: SeqResetPolicy::Reset,
+        });
+        assert_eq!((per_channel.next(0), per_channel.next(1), per_channel.next(0)), (1, 1, 2));
+        per_channel.on_connected();
+        assert_eq!(per_channel.next(0), 1);
+
+        let shared = SeqGenerator::new(SeqConfig {
+            scope: SeqScope::Connection,
+            on_reconnect: SeqResetPolicy::Persist,
+        });
+        assert_eq!((shared.next(0), shared.next(1)), (1, 2));
+        shared.on_connected();
+        assert_eq!(shared.next(3), 3);
+
+        let snapshot = shared.snapshot().counters;
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!((snapshot[0].channel, snapshot[0].next_seq, snapshot[0].allocated), (None, 4, 3));
+    }
+
+    #[test]
+    fn tx_seq_assigned_before_reset_is_reassigned() {
+        let generator = SeqGenerator::new(SeqConfig::default());
+        let mut queued = generator.assign(0);
+        let mut explicit = TxSeq::Explicit(42);
+        assert_eq!(generator.refresh(0, &mut queued), 1);
+
+        // 重连重置后：新一代从 1 开始，排队中的旧帧重新分配，显式 seq 不变
+        assert!(generator.on_connected());
+        assert_eq!(generator.next(0), 1);
+        assert_eq!(generator.refresh(0, &mut queued), 2);
+        assert_eq!(generator.refresh(0, &mut queued), 2);
+        assert_eq!(generator.refresh(0, &mut explicit), 42);
+    }
+
+    #[test]
+    fn tx_seq_wrap_is_counted() {
+        let generator = SeqGenerator::new(SeqConfig::default());
+        if let Ok(mut counters) = generator.counters.lock() {
+            counters.entry(None).or_default().next = u32::MAX;
+        }
+        assert_eq!(generator.next(0), u32::MAX);
+        assert_eq!(generator.next(0), 0);
+        assert_eq!(generator.snapshot().counters[0].wraps, 1);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::comm::session::HmipLinkConfig;
+use crate::comm::transport::{Transport, TransportError};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio_serial::{
@@ -12,6 +14,9 @@ pub struct SerialConfig {
     pub data_bits: u8,
     pub stop_bits: u8,
     pub parity: String,
+    /// HMIP 会话层选项（握手等），缺省时不启用
+    #[serde(default)]
+    pub hmip: HmipLinkConfig,
 }
 
 impl Default for SerialConfig {
@@ -22,6 +27,7 @@ impl Default for SerialConfig {
             data_bits: 8,
             stop_bits: 1,
             parity: "none".to_string(),
+            hmip: HmipLinkConfig::default(),
         }
     }
 }
@@ -65,6 +71,26 @@ pub fn open_stream(config: &SerialConfig) -> Result<SerialStream, String> {
         .map_err(|e| format!("Failed to open serial port: {}", e))
 }
 
+impl Transport for SerialConfig {
+    type Stream = SerialStream;
+
+    fn kind(&self) -> &'static str {
+        "serial"
+    }
+
+    fn describe(&self) -> String {
+        format!("{} @ {}", self.port, self.baud_rate)
+    }
+
+    fn link(&self) -> &HmipLinkConfig {
+        &self.hmip
+    }
+
+    async fn open(&self) -> Result<SerialStream, TransportError> {
+        Ok(open_stream(self)?)
+    }
+}
+
 /// List available serial ports
 pub fn list_ports() -> Result<Vec<String>, String> {
     let ports = tokio_serial::available_ports()
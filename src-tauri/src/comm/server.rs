@@ -0,0 +1,322 @@
+use crate::comm::actor::{self, CommActorHandle, CommEvent};
+use crate::comm::auth::FrameAuth;
+use crate::comm::session::{HandshakeRole, HmipLinkConfig, SessionInfo};
+use crate::comm::transport::{ReconnectPolicy, Transport, TransportError};
+use crate::comm::CommShared;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// 监听模式的传输名；各对端的事件使用 `tcp_server:<peer_addr>` 作为 transport
+pub const SERVER_TRANSPORT: &str = "tcp_server";
+
+// accept 出错（如文件描述符耗尽）时的退避，避免空转刷屏
+const ACCEPT_ERROR_BACKOFF_MS: u64 = 200;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TcpServerConfig {
+    pub bind_host: String,
+    pub port: u16,
+    /// 同时在线的对端上限，超出的连接直接关闭
+    pub max_clients: usize,
+    /// HMIP 会话层选项，对每个接入的对端生效
+    #[serde(default)]
+    pub hmip: HmipLinkConfig,
+}
+
+impl Default for TcpServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_host: "0.0.0.0".to_string(),
+            port: 5020,
+            max_clients: 8,
+            hmip: HmipLinkConfig::default(),
+        }
+    }
+}
+
+pub fn peer_transport(addr: &str) -> String {
+    format!("{}:{}", SERVER_TRANSPORT, addr)
+}
+
+/// 已接入的对端：流由 accept 得到，断开后不重连（等待设备重新接入）
+struct PeerTransport {
+    name: String,
+    addr: String,
+    link: HmipLinkConfig,
+}
+
+impl Transport for PeerTransport {
+    type Stream = TcpStream;
+
+    fn kind(&self) -> &'static str {
+        SERVER_TRANSPORT
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn describe(&self) -> String {
+        self.addr.clone()
+    }
+
+    fn link(&self) -> &HmipLinkConfig {
+        &self.link
+    }
+
+    async fn open(&self) -> Result<TcpStream, TransportError> {
+        Err(format!("Peer {} cannot be reopened", self.addr).into())
+    }
+
+    fn reconnect_policy(&self) -> ReconnectPolicy {
+        ReconnectPolicy::Never
+    }
+
+    /// 设备主动接入：由设备发 HELLO，本端应答 HELLO_ACK
+    fn handshake_role(&self) -> HandshakeRole {
+        HandshakeRole::Server
+    }
+}
+
+/// `list_tcp_peers` 返回的对端信息
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerInfo {
+    pub addr: String,
+    /// 该对端事件中的 transport 字段
+    pub transport: String,
+    pub connected_at_ms: u64,
+    pub session: Option<SessionInfo>,
+}
+
+struct Peer {
+    // 同一地址断开后重连会复用 key，用 id 区分新旧连接
+    id: u64,
+    connected_at_ms: u64,
+    handle: CommActorHandle,
+}
+
+type PeerMap = Arc<Mutex<HashMap<String, Peer>>>;
+
+pub struct TcpServerHandle {
+    local_addr: SocketAddr,
+    peers: PeerMap,
+    shutdown_tx: oneshot::Sender<()>,
+    join: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl TcpServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        let Ok(peers) = self.peers.lock() else {
+            return Vec::new();
+        };
+        let mut list: Vec<PeerInfo> = peers
+            .iter()
+            .map(|(addr, peer)| PeerInfo {
+                addr: addr.clone(),
+                transport: peer_transport(addr),
+                connected_at_ms: peer.connected_at_ms,
+                session: peer.handle.session(),
+            })
+            .collect();
+        list.sort_by_key(|peer| peer.connected_at_ms);
+        list
+    }
+
+    /// 在持锁期间访问指定对端的 actor（仅用于取出发送队列等同步操作，不要在闭包内 await）
+    pub fn with_peer<R>(&self, addr: &str, f: impl FnOnce(&CommActorHandle) -> R) -> Result<R, String> {
+        let peers = self
+            .peers
+            .lock()
+            .map_err(|_| "TCP server peer table is poisoned".to_string())?;
+        peers
+            .get(addr)
+            .map(|peer| f(&peer.handle))
+            .ok_or_else(|| format!("TCP peer {} not connected", addr))
+    }
+
+    /// 断开指定对端
+    pub async fn kick(&self, addr: &str) -> Result<(), String> {
+        let peer = self
+            .peers
+            .lock()
+            .map_err(|_| "TCP server peer table is poisoned".to_string())?
+            .remove(addr)
+            .ok_or_else(|| format!("TCP peer {} not connected", addr))?;
+        peer.handle.shutdown().await;
+        Ok(())
+    }
+
+    /// 停止监听并断开全部对端
+    pub async fn shutdown(self) {
+        let _ = self.shutdown_tx.send(());
+        if let Err(err) = self.join.await {
+            log::warn!("TCP server task ended with error: {}", err);
+        }
+        let peers: Vec<Peer> = match self.peers.lock() {
+            Ok(mut peers) => peers.drain().map(|(_, peer)| peer).collect(),
+            Err(_) => Vec::new(),
+        };
+        for peer in peers {
+            peer.handle.shutdown().await;
+        }
+    }
+}
+
+/// 绑定监听地址并开始接受连接（绑定失败直接返回错误）
+pub async fn start(
+    app: AppHandle,
+    config: TcpServerConfig,
+    auth: Option<FrameAuth>,
+    shared: CommShared,
+) -> Result<TcpServerHandle, String> {
+    if config.max_clients == 0 {
+        return Err("max_clients must be at least 1".to_string());
+    }
+    let bind_addr = format!("{}:{}", config.bind_host, config.port);
+    let listener = TcpListener::bind(&bind_addr)
+        .await
+        .map_err(|e| format!("Failed to bind {}: {}", bind_addr, e))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read listener address: {}", e))?;
+
+    let peers: PeerMap = Arc::default();
+    let accept_peers = peers.clone();
+    let auth = auth.map(Arc::new);
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+
+    let join = tauri::async_runtime::spawn(async move {
+        let acceptor = Acceptor {
+            app,
+            config,
+            auth,
+            shared,
+            peers: accept_peers,
+            next_id: AtomicU64::new(1),
+        };
+        let app = &acceptor.app;
+        let _ = actor::emit_event(
+            app,
+            &CommEvent::ServerListening {
+                transport: SERVER_TRANSPORT.to_string(),
+                local_addr: local_addr.to_string(),
+                max_clients: acceptor.config.max_clients,
+                timestamp_ms: actor::now_ms(),
+            },
+        );
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown_rx => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => acceptor.accept(stream, addr),
+                    Err(err) => {
+                        let _ = actor::emit_event(
+                            app,
+                            &CommEvent::Error {
+                                transport: SERVER_TRANSPORT.to_string(),
+                                message: format!("Accept failed: {}", err),
+                                reason: None,
+                                timestamp_ms: actor::now_ms(),
+                            },
+                        );
+                        tokio::time::sleep(Duration::from_millis(ACCEPT_ERROR_BACKOFF_MS)).await;
+                    }
+                },
+            }
+        }
+
+        let _ = actor::emit_event(
+            app,
+            &CommEvent::Disconnected {
+                transport: SERVER_TRANSPORT.to_string(),
+                timestamp_ms: actor::now_ms(),
+            },
+        );
+    });
+
+    Ok(TcpServerHandle {
+        local_addr,
+        peers,
+        shutdown_tx,
+        join,
+    })
+}
+
+/// accept 循环持有的上下文
+struct Acceptor {
+    app: AppHandle,
+    config: TcpServerConfig,
+    auth: Option<Arc<FrameAuth>>,
+    shared: CommShared,
+    peers: PeerMap,
+    next_id: AtomicU64,
+}
+
+impl Acceptor {
+    fn accept(&self, stream: TcpStream, addr: SocketAddr) {
+        let addr = addr.to_string();
+        // 持锁完成“检查上限 → 启动 actor → 登记”：对端即刻断开时 on_exit 会等待登记完成后再移除
+        let Ok(mut table) = self.peers.lock() else {
+            return;
+        };
+        if table.len() >= self.config.max_clients {
+            drop(table);
+            drop(stream);
+            let _ = actor::emit_event(
+                &self.app,
+                &CommEvent::PeerRejected {
+                    transport: SERVER_TRANSPORT.to_string(),
+                    peer: addr,
+                    reason: format!("Client limit reached ({})", self.config.max_clients),
+                    timestamp_ms: actor::now_ms(),
+                },
+            );
+            return;
+        }
+
+        let _ = stream.set_nodelay(true);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let exit_peers = self.peers.clone();
+        let exit_addr = addr.clone();
+        let peer = PeerTransport {
+            name: peer_transport(&addr),
+            addr: addr.clone(),
+            link: self.config.hmip.clone(),
+        };
+        let handle = actor::spawn_actor(
+            self.app.clone(),
+            peer,
+            stream,
+            self.auth.clone(),
+            self.shared.clone(),
+            None,
+            Some(Box::new(move || {
+                if let Ok(mut table) = exit_peers.lock() {
+                    if table.get(&exit_addr).is_some_and(|peer| peer.id == id) {
+                        table.remove(&exit_addr);
+                    }
+                }
+            })),
+        );
+        table.insert(
+            addr,
+            Peer {
+                id,
+                connected_at_ms: actor::now_ms(),
+                handle,
+            },
+        );
+    }
+}
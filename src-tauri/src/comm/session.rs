@@ -0,0 +1,422 @@
+use crate::comm::{
+    auth::{self, AuthConfig, FrameAuth},
+    fragment::FragmentConfig,
+    outbox::OutboxConfig,
+    proto,
+    queue::TxQueueConfig,
+    sequence::SeqConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// 心跳间隔下限：避免误配置为 0 导致写队列被心跳占满
+const MIN_HEARTBEAT_INTERVAL_MS: u64 = 100;
+
+/// HMIP 会话层配置（挂在 SerialConfig/TcpConfig 上，默认全部关闭）
+///
+/// 说明：
+/// - 字段缺省时保持旧行为：连接建立后直接收发，不做任何会话协商
+/// - 前端可只传需要启用的子项，其余走 serde default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HmipLinkConfig {
+    pub handshake: Option<HandshakeConfig>,
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// 发送端分片参数；None 时不拆分（接收端始终按默认限制重组分片帧）
+    pub fragmentation: Option<FragmentConfig>,
+    /// 帧认证/加密（预共享密钥）；None 时收发明文帧
+    pub auth: Option<AuthConfig>,
+    /// 类型化发送（`send_hmip_message`）的 CRC 策略
+    pub crc: CrcPolicy,
+    /// 发送侧 seq 分配（按连接/按 channel，重连是否重置）
+    pub seq: SeqConfig,
+    /// 发送队列（类、权重、容量与 channel 映射）
+    pub tx_queues: TxQueueConfig,
+    /// 持久化发件箱（断线期间暂存消息，重连后按序补发）；None 时不启用
+    pub outbox: Option<OutboxConfig>,
+}
+
+/// 是否为帧置 FLAG_CRC32
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrcPolicy {
+    #[default]
+    Always,
+    Never,
+    /// 认证帧已由标签保证完整性时省略 CRC，明文帧仍带 CRC
+    Auto,
+}
+
+impl CrcPolicy {
+    pub fn flags(self, authenticated: bool) -> u8 {
+        match self {
+            Self::Always => proto::FLAG_CRC32,
+            Self::Never => 0,
+            Self::Auto if authenticated => 0,
+            Self::Auto => proto::FLAG_CRC32,
+        }
+    }
+}
+
+impl HmipLinkConfig {
+    /// 解析认证密钥；密钥非法时返回错误（connect 前调用）
+    ///
+    /// 认证依赖握手交换会话随机数（防止旧连接的帧被重放），因此启用认证时必须配置握手
+    pub fn frame_auth(&self) -> Result<Option<FrameAuth>, String> {
+        if self.auth.is_some() && self.handshake.is_none() {
+            return Err("hmip.auth requires hmip.handshake".to_string());
+        }
+        self.auth.as_ref().map(FrameAuth::from_config).transpose()
+    }
+
+    /// 校验发送队列配置（connect 前调用）
+    pub fn validate_tx_queues(&self) -> Result<(), String> {
+        self.tx_queues.validate()
+    }
+}
+
+/// 握手角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandshakeRole {
+    /// 连接建立后发送 HELLO，等待 HELLO_ACK
+    Client,
+    /// 等待对端 HELLO，协商后回复 HELLO_ACK
+    Server,
+}
+
+/// HELLO/HELLO_ACK 握手参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HandshakeConfig {
+    /// 握手角色；None 时按传输决定（监听模式的对端为 server，其余为 client）
+    pub role: Option<HandshakeRole>,
+    /// 本端名称（写入 HELLO / HELLO_ACK 的 name，超过 255 字节会被截断）
+    pub name: String,
+    /// 本端支持的能力位
+    pub capabilities: u32,
+    /// 对端必须具备的能力位；缺失则视为握手失败并进入重连
+    pub required_capabilities: u32,
+    pub timeout_ms: u64,
+    /// 声明支持 v2 帧头；对端同样声明时握手后切换到 v2（HELLO 本身始终用 v1 发送）
+    pub header_v2: bool,
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> Self {
+        Self {
+            role: None,
+            name: "hmi".to_string(),
+            capabilities: 0,
+            required_capabilities: 0,
+            timeout_ms: 3000,
+            header_v2: false,
+        }
+    }
+}
+
+impl HandshakeConfig {
+    /// 是否以 server 角色握手（role 应在建立连接时按传输补齐）
+    pub fn is_server(&self) -> bool {
+        self.role == Some(HandshakeRole::Server)
+    }
+
+    /// 实际写入 HELLO 的能力位（含协议层保留位）
+    pub fn local_capabilities(&self) -> u32 {
+        if self.header_v2 {
+            self.capabilities | proto::capability::HEADER_V2
+        } else {
+            self.capabilities
+        }
+    }
+}
+
+/// HEARTBEAT 保活与链路看门狗参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeartbeatConfig {
+    /// 本端发送心跳的间隔（同时作为对端心跳的期望间隔）
+    pub interval_ms: u64,
+    /// 连续错过多少个对端心跳后判定链路失效
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 1000,
+            max_missed: 3,
+        }
+    }
+}
+
+/// 单个连接上的心跳计时（会话就绪后启动，断线即丢弃）
+pub struct Keepalive {
+    interval: Duration,
+    peer_timeout: Duration,
+    next_send: Instant,
+    peer_deadline: Instant,
+}
+
+impl Keepalive {
+    pub fn start(cfg: &HeartbeatConfig, now: Instant) -> Self {
+        let interval = Duration::from_millis(cfg.interval_ms.max(MIN_HEARTBEAT_INTERVAL_MS));
+        let peer_timeout = interval * cfg.max_missed.max(1);
+        Self {
+            interval,
+            peer_timeout,
+            next_send: now,
+            peer_deadline: now + peer_timeout,
+        }
+    }
+
+    pub fn next_send(&self) -> Instant {
+        self.next_send
+    }
+
+    pub fn peer_deadline(&self) -> Instant {
+        self.peer_deadline
+    }
+
+    pub fn peer_timeout(&self) -> Duration {
+        self.peer_timeout
+    }
+
+    pub fn on_sent(&mut self, now: Instant) {
+        self.next_send = now + self.interval;
+    }
+
+    pub fn on_peer_heartbeat(&mut self, now: Instant) {
+        self.peer_deadline = now + self.peer_timeout;
+    }
+}
+
+/// 握手完成后的会话信息（连接断开即清空）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionInfo {
+    pub peer_name: String,
+    pub peer_capabilities: u32,
+    /// 协商结果：本端与对端能力位的交集
+    pub capabilities: u32,
+    /// 协商出的帧头版本（后续发送使用）
+    pub wire_version: u8,
+    pub established_at_ms: u64,
+}
+
+pub fn encode_hello_frame(cfg: &HandshakeConfig, auth: Option<&FrameAuth>, seq: u32) -> Vec<u8> {
+    let payload = proto::encode_hello(&proto::Hello {
+        role: proto::Role::Client,
+        capabilities: cfg.local_capabilities(),
+        name: cfg.name.clone(),
+        nonce: auth.and_then(FrameAuth::local_nonce),
+    });
+    auth::encode_frame(
+        auth,
+        proto::VERSION_1,
+        proto::EncodeFrameParams {
+            msg_type: proto::msg_type::HELLO,
+            flags: proto::FLAG_CRC32,
+            channel: 0,
+            seq,
+            payload: &payload,
+        },
+    )
+}
+
+/// 服务端角色应答 HELLO（与 HELLO 一样始终用 v1 发送）
+pub fn encode_hello_ack_frame(cfg: &HandshakeConfig, auth: Option<&FrameAuth>, seq: u32) -> Vec<u8> {
+    let payload = proto::encode_hello_ack(&proto::HelloAck {
+        capabilities: cfg.local_capabilities(),
+        name: cfg.name.clone(),
+        nonce: auth.and_then(FrameAuth::local_nonce),
+    });
+    auth::encode_frame(
+        auth,
+        proto::VERSION_1,
+        proto::EncodeFrameParams {
+            msg_type: proto::msg_type::HELLO_ACK,
+            flags: proto::FLAG_CRC32,
+            channel: 0,
+            seq,
+            payload: &payload,
+        },
+    )
+}
+
+pub fn encode_heartbeat_frame(
+    auth: Option<&FrameAuth>,
+    version: u8,
+    seq: u32,
+    timestamp_ms: u64,
+) -> Vec<u8> {
+    let payload = proto::encode_heartbeat(&proto::Heartbeat { timestamp_ms });
+    auth::encode_frame(
+        auth,
+        version,
+        proto::EncodeFrameParams {
+            msg_type: proto::msg_type::HEARTBEAT,
+            flags: proto::FLAG_CRC32,
+            channel: 0,
+            seq,
+            payload: &payload,
+        },
+    )
+}
+
+/// 根据对端 HELLO_ACK 计算会话能力；对端缺少必需能力时返回错误原因。
+/// 按对端声明的名称与能力位协商（client 取自 HELLO_ACK，server 取自 HELLO）
+pub fn negotiate(
+    cfg: &HandshakeConfig,
+    peer_name: &str,
+    peer_capabilities: u32,
+    now_ms: u64,
+) -> Result<SessionInfo, String> {
+    let missing = cfg.required_capabilities & !peer_capabilities;
+    if missing != 0 {
+        return Err(format!(
+            "peer '{}' is missing required capabilities {:#010x}",
+            peer_name, missing
+        ));
+    }
+
+    let capabilities = cfg.local_capabilities() & peer_capabilities;
+    let wire_version = if capabilities & proto::capability::HEADER_V2 != 0 {
+        proto::VERSION_2
+    } else {
+        proto::VERSION_1
+    };
+
+    Ok(SessionInfo {
+        peer_name: peer_name.to_string(),
+        peer_capabilities,
+        capabilities,
+        wire_version,
+        established_at_ms: now_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_intersects_capabilities() {
+        let cfg = HandshakeConfig {
+            capabilities: 0b1011,
+            ..Default::default()
+        };
+
+        let info = negotiate(&cfg, "eqp-fw", 0b0110, 42).unwrap();
+        assert_eq!(info.peer_name, "eqp-fw");
+        assert_eq!(info.peer_capabilities, 0b0110);
+        assert_eq!(info.capabilities, 0b0010);
+        assert_eq!(info.wire_version, proto::VERSION_1);
+        assert_eq!(info.established_at_ms, 42);
+    }
+
+    #[test]
+    fn negotiate_selects_header_v2_only_when_both_sides_support_it() {
+        let cfg = HandshakeConfig {
+            header_v2: true,
+            ..Default::default()
+        };
+
+        let v1_peer = negotiate(&cfg, "eqp-fw", 0, 0).unwrap();
+        assert_eq!(v1_peer.wire_version, proto::VERSION_1);
+
+        let v2_peer = negotiate(&cfg, "eqp-fw", proto::capability::HEADER_V2, 0).unwrap();
+        assert_eq!(v2_peer.wire_version, proto::VERSION_2);
+
+        let local_v1 = HandshakeConfig::default();
+        let info = negotiate(&local_v1, "eqp-fw", proto::capability::HEADER_V2, 0).unwrap();
+        assert_eq!(info.wire_version, proto::VERSION_1);
+    }
+
+    #[test]
+    fn negotiate_rejects_missing_required_capabilities() {
+        let cfg = HandshakeConfig {
+            capabilities: 0b0011,
+            required_capabilities: 0b0101,
+            ..Default::default()
+        };
+
+        let err = negotiate(&cfg, "eqp-fw", 0b0001, 0).unwrap_err();
+        assert!(err.contains("0x00000004"));
+    }
+
+    #[test]
+    fn server_role_answers_with_local_capabilities() {
+        let cfg: HandshakeConfig =
+            serde_json::from_str(r#"{ "role": "server", "name": "hmi-srv", "header_v2": true }"#).unwrap();
+        assert!(cfg.is_server());
+        assert!(!HandshakeConfig::default().is_server());
+
+        let mut dec = proto::FrameDecoder::new(proto::DecoderConfig::default());
+        dec.push(&encode_hello_ack_frame(&cfg, None, 7)).unwrap();
+        let frame = dec.next_frame().unwrap().unwrap();
+        assert_eq!((frame.header.version, frame.header.seq), (proto::VERSION_1, 7));
+        let Ok(proto::Message::HelloAck(ack)) = proto::decode_message(&frame) else {
+            panic!("expected HELLO_ACK");
+        };
+        assert_eq!(ack.name, "hmi-srv");
+        assert_eq!(ack.capabilities, proto::capability::HEADER_V2);
+    }
+
+    #[test]
+    fn keepalive_tracks_peer_deadline() {
+        let cfg = HeartbeatConfig {
+            interval_ms: 500,
+            max_missed: 3,
+        };
+        let t0 = Instant::now();
+        let mut keepalive = Keepalive::start(&cfg, t0);
+
+        // 第一次心跳立即发送
+        assert_eq!(keepalive.next_send(), t0);
+        assert_eq!(keepalive.peer_deadline(), t0 + Duration::from_millis(1500));
+
+        keepalive.on_sent(t0);
+        assert_eq!(keepalive.next_send(), t0 + Duration::from_millis(500));
+
+        let t1 = t0 + Duration::from_millis(800);
+        keepalive.on_peer_heartbeat(t1);
+        assert_eq!(keepalive.peer_deadline(), t1 + Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn keepalive_clamps_degenerate_config() {
+        let cfg = HeartbeatConfig {
+            interval_ms: 0,
+            max_missed: 0,
+        };
+        let keepalive = Keepalive::start(&cfg, Instant::now());
+        assert_eq!(
+            keepalive.peer_timeout(),
+            Duration::from_millis(MIN_HEARTBEAT_INTERVAL_MS)
+        );
+    }
+
+    #[test]
+    fn hello_frame_decodes_as_client_hello() {
+        let cfg = HandshakeConfig {
+            name: "hmi-01".to_string(),
+            capabilities: 0x10,
+            ..Default::default()
+        };
+
+        let mut dec = proto::FrameDecoder::new(proto::DecoderConfig::default());
+        dec.push(&encode_hello_frame(&cfg, None, 3)).unwrap();
+        let frame = dec.next_frame().unwrap().unwrap();
+        assert_eq!(frame.header.seq, 3);
+
+        match proto::decode_message(&frame).unwrap() {
+            proto::Message::Hello(v) => {
+                assert_eq!(v.role, proto::Role::Client);
+                assert_eq!(v.capabilities, 0x10);
+                assert_eq!(v.name, "hmi-01");
+            }
+            _ => panic!("unexpected msg"),
+        }
+    }
+}
@@ -1,12 +1,26 @@
+use crate::comm::session::HmipLinkConfig;
+use crate::comm::tls::{self, TlsConfig};
+use crate::comm::transport::{Transport, TransportError};
 use serde::{Deserialize, Serialize};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TcpConfig {
     pub host: String,
     pub port: u16,
     pub timeout_ms: u64,
+    /// TLS 选项，缺省为明文 TCP
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// HMIP 会话层选项（握手等），缺省时不启用
+    #[serde(default)]
+    pub hmip: HmipLinkConfig,
 }
 
 impl Default for TcpConfig {
@@ -15,11 +29,63 @@ impl Default for TcpConfig {
             host: "127.0.0.1".to_string(),
             port: 502,
             timeout_ms: 5000,
+            tls: None,
+            hmip: HmipLinkConfig::default(),
         }
     }
 }
 
-pub async fn open_stream(config: &TcpConfig) -> Result<TcpStream, String> {
+/// TCP 客户端连接：明文或 TLS
+pub enum TcpLink {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for TcpLink {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TcpLink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+pub async fn open_stream(config: &TcpConfig) -> Result<TcpLink, TransportError> {
+    let tls = match &config.tls {
+        Some(tls_config) => Some(tls::TlsSetup::new(tls_config, &config.host)?),
+        None => None,
+    };
     let addr = format!("{}:{}", config.host, config.port);
     // 这里是两层 Result, 第一层是 timeout 的错误，第二层是 TcpStream::connect 的错误
     // 所以第一个 map_err 是处理 timeout 的错误，第二个 map_err 是处理连接失败的错误
@@ -33,6 +99,36 @@ pub async fn open_stream(config: &TcpConfig) -> Result<TcpStream, String> {
 
     // 工业现场常见：希望尽量减少交互延迟，TCP_NODELAY 可以减少小包延迟
     let _ = stream.set_nodelay(true);
-    Ok(stream)
+
+    match tls {
+        Some(tls) => {
+            let timeout = Duration::from_millis(config.timeout_ms);
+            let stream = tls.connect(stream, timeout).await?;
+            Ok(TcpLink::Tls(Box::new(stream)))
+        }
+        None => Ok(TcpLink::Plain(stream)),
+    }
 }
 
+impl Transport for TcpConfig {
+    type Stream = TcpLink;
+
+    fn kind(&self) -> &'static str {
+        "tcp"
+    }
+
+    fn describe(&self) -> String {
+        match &self.tls {
+            Some(_) => format!("{}:{} (TLS)", self.host, self.port),
+            None => format!("{}:{}", self.host, self.port),
+        }
+    }
+
+    fn link(&self) -> &HmipLinkConfig {
+        &self.hmip
+    }
+
+    async fn open(&self) -> Result<TcpLink, TransportError> {
+        open_stream(self).await
+    }
+}
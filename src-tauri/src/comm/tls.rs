@@ -0,0 +1,515 @@
+use crate::comm::transport::TransportError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{
+    self, AlertDescription, CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore,
+    SignatureScheme,
+};
+use tokio_rustls::TlsConnector;
+
+/// `CommEvent::Error` 的 reason：TLS 配置无效（证书/私钥文件无法读取、参数组合错误）
+pub const REASON_CONFIG: &str = "tls_config";
+/// 握手超时
+pub const REASON_HANDSHAKE_TIMEOUT: &str = "tls_handshake_timeout";
+/// 握手期间连接被关闭等非 TLS 层错误
+pub const REASON_HANDSHAKE_FAILED: &str = "tls_handshake_failed";
+/// 服务端证书不受配置的 CA 信任
+pub const REASON_CERTIFICATE_UNTRUSTED: &str = "tls_certificate_untrusted";
+/// 服务端证书已过期或尚未生效
+pub const REASON_CERTIFICATE_EXPIRED: &str = "tls_certificate_expired";
+/// 服务端证书与 server_name 不匹配
+pub const REASON_SERVER_NAME_MISMATCH: &str = "tls_server_name_mismatch";
+/// 服务端证书指纹与 pinned_sha256 不一致
+pub const REASON_FINGERPRINT_MISMATCH: &str = "tls_fingerprint_mismatch";
+/// 服务端拒绝了客户端证书（或要求客户端证书而本端未配置）
+pub const REASON_CLIENT_CERTIFICATE_REJECTED: &str = "tls_client_certificate_rejected";
+/// 其它 TLS 协议错误
+pub const REASON_PROTOCOL: &str = "tls_protocol_error";
+
+const FINGERPRINT_LEN: usize = 32;
+
+/// TCP 客户端的 TLS 选项（本地 PEM 文件路径）
+///
+/// 说明：
+/// - 缺省按 `ca_path` 校验服务端证书链，并校验证书名称（`server_name`，缺省为 host）；
+///   未配置 `ca_path` 时使用内置的公共 Web PKI 根证书（webpki-roots）
+/// - 配置 `pinned_sha256` 时改为指纹固定：只接受叶子证书 DER 的 SHA-256 与之相同的服务端，
+///   不再做 CA 与名称校验（适用于自签名证书的设备）
+/// - 同时配置 `cert_path` 与 `key_path` 时启用双向 TLS
+/// - 每次（重新）连接时读取文件，证书轮换后无需重启
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TlsConfig {
+    /// 信任的 CA 证书（PEM，可含多个）；缺省使用公共 Web PKI 根证书
+    pub ca_path: Option<String>,
+    /// 客户端证书链（PEM）
+    pub cert_path: Option<String>,
+    /// 客户端私钥（PEM：PKCS#8 / PKCS#1 / SEC1）
+    pub key_path: Option<String>,
+    /// 校验证书时使用的服务端名称，缺省为 host
+    pub server_name: Option<String>,
+    /// 服务端证书指纹（hex，可用 `:` 分隔）
+    pub pinned_sha256: Option<String>,
+}
+
+/// 按配置准备一次 TLS 连接（读取证书文件），在建立 TCP 连接之前调用，配置错误不必等网络
+pub struct TlsSetup {
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+}
+
+impl TlsSetup {
+    pub fn new(config: &TlsConfig, host: &str) -> Result<Self, TransportError> {
+        let client = client_config(config)
+            .map_err(|message| TransportError::new(message, Some(REASON_CONFIG)))?;
+        let name = config.server_name.as_deref().unwrap_or(host);
+        let server_name = ServerName::try_from(name.to_string()).map_err(|_| {
+            TransportError::new(
+                format!("Invalid TLS server name: {}", name),
+                Some(REASON_CONFIG),
+            )
+        })?;
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(client)),
+            server_name,
+        })
+    }
+
+    /// 在已建立的 TCP 连接上完成 TLS 握手
+    pub async fn connect(
+        self,
+        stream: TcpStream,
+        timeout: Duration,
+    ) -> Result<TlsStream<TcpStream>, TransportError> {
+        let handshake = self.connector.connect(self.server_name, stream);
+        match tokio::time::timeout(timeout, handshake).await {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(err)) => Err(TransportError::new(
+                format!("TLS handshake failed: {}", err),
+                Some(error_reason(&err).unwrap_or(REASON_HANDSHAKE_FAILED)),
+            )),
+            Err(_) => Err(TransportError::new(
+                "TLS handshake timeout",
+                Some(REASON_HANDSHAKE_TIMEOUT),
+            )),
+        }
+    }
+}
+
+/// 从 IO 错误中识别 TLS 失败原因；非 TLS 错误返回 None
+///
+/// TLS 1.3 下服务端在客户端握手完成后才校验客户端证书，拒绝时的 alert 出现在首次读取，
+/// 因此读写错误也经由这里分类。
+pub fn error_reason(err: &io::Error) -> Option<&'static str> {
+    let err = err.get_ref()?.downcast_ref::<rustls::Error>()?;
+    let reason = match err {
+        rustls::Error::InvalidCertificate(err) => match err {
+            CertificateError::Expired
+            | CertificateError::ExpiredContext { .. }
+            | CertificateError::NotValidYet
+            | CertificateError::NotValidYetContext { .. } => REASON_CERTIFICATE_EXPIRED,
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. } => {
+                REASON_SERVER_NAME_MISMATCH
+            }
+            // 仅由指纹固定模式的校验器产生
+            CertificateError::ApplicationVerificationFailure => REASON_FINGERPRINT_MISMATCH,
+            _ => REASON_CERTIFICATE_UNTRUSTED,
+        },
+        rustls::Error::AlertReceived(
+            AlertDescription::CertificateRequired
+            | AlertDescription::BadCertificate
+            | AlertDescription::UnsupportedCertificate
+            | AlertDescription::CertificateRevoked
+            | AlertDescription::CertificateExpired
+            | AlertDescription::CertificateUnknown
+            | AlertDescription::UnknownCA
+            | AlertDescription::AccessDenied,
+        ) => REASON_CLIENT_CERTIFICATE_REJECTED,
+        _ => REASON_PROTOCOL,
+    };
+    Some(reason)
+}
+
+fn client_config(config: &TlsConfig) -> Result<ClientConfig, String> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS setup failed: {}", e))?;
+
+    let builder = match &config.pinned_sha256 {
+        Some(pin) => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+                fingerprint: parse_fingerprint(pin)?,
+                provider,
+            })),
+        None => {
+            let mut roots = RootCertStore::empty();
+            match config.ca_path.as_deref() {
+                Some(ca_path) => {
+                    for cert in load_certs(ca_path)? {
+                        roots
+                            .add(cert)
+                            .map_err(|e| format!("Invalid CA certificate in {}: {}", ca_path, e))?;
+                    }
+                }
+                None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+            }
+            builder.with_root_certificates(roots)
+        }
+    };
+
+    match (&config.cert_path, &config.key_path) {
+        (Some(cert_path), Some(key_path)) => builder
+            .with_client_auth_cert(load_certs(cert_path)?, load_key(key_path)?)
+            .map_err(|e| format!("Invalid client certificate/key: {}", e)),
+        (None, None) => Ok(builder.with_no_client_auth()),
+        _ => Err("TLS cert_path and key_path must be set together".to_string()),
+    }
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read certificates from {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    PrivateKeyDer::from_pem_file(path)
+        .map_err(|e| format!("Failed to read private key from {}: {}", path, e))
+}
+
+fn parse_fingerprint(text: &str) -> Result<[u8; FINGERPRINT_LEN], String> {
+    let hex: String = text.trim().chars().filter(|c| *c != ':').collect();
+    if hex.len() != FINGERPRINT_LEN * 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "pinned_sha256 must be {} hex characters (':' separators allowed)",
+            FINGERPRINT_LEN * 2
+        ));
+    }
+    let mut fingerprint = [0u8; FINGERPRINT_LEN];
+    for (i, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| "pinned_sha256 contains non-hex characters".to_string())?;
+    }
+    Ok(fingerprint)
+}
+
+/// 指纹固定：叶子证书指纹一致即信任（握手签名仍按正常流程校验）
+#[derive(Debug)]
+struct PinnedVerifier {
+    fingerprint: [u8; FINGERPRINT_LEN],
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(end_entity.as_ref()).as_slice() == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comm::tcp::{self, TcpConfig, TcpLink};
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use std::path::PathBuf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::server::WebPkiClientVerifier;
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    /// 测试用 CA：签发的证书与私钥以 PEM 写入临时目录
+    struct Pki {
+        dir: PathBuf,
+        ca: rcgen::Certificate,
+        ca_key: KeyPair,
+    }
+
+    struct Identity {
+        cert: CertificateDer<'static>,
+        key: PrivateKeyDer<'static>,
+    }
+
+    impl Pki {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("hmip_tls_{}_{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let ca_key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = params.self_signed(&ca_key).unwrap();
+            std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+            Self { dir, ca, ca_key }
+        }
+
+        /// 签发 `localhost` 证书，写入 `<name>.pem` / `<name>.key`
+        fn issue(&self, name: &str, expired: bool) -> Identity {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+            if expired {
+                params.not_before = rcgen::date_time_ymd(2000, 1, 1);
+                params.not_after = rcgen::date_time_ymd(2001, 1, 1);
+            }
+            let cert = params.signed_by(&key, &self.ca, &self.ca_key).unwrap();
+            std::fs::write(self.path(&format!("{}.pem", name)), cert.pem()).unwrap();
+            std::fs::write(self.path(&format!("{}.key", name)), key.serialize_pem()).unwrap();
+            Identity {
+                cert: cert.der().clone(),
+                key: PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+            }
+        }
+
+        fn path(&self, file: &str) -> String {
+            self.dir.join(file).to_string_lossy().into_owned()
+        }
+
+        fn client_config(&self) -> TlsConfig {
+            TlsConfig {
+                ca_path: Some(self.path("ca.pem")),
+                cert_path: None,
+                key_path: None,
+                server_name: Some("localhost".to_string()),
+                pinned_sha256: None,
+            }
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// 本地 TLS 回显服务端（接受一个连接）；require_client_cert 时按同一 CA 校验客户端证书
+    async fn spawn_server(pki: &Pki, identity: &Identity, require_client_cert: bool) -> u16 {
+        let provider = Arc::new(crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = if require_client_cert {
+            let mut roots = RootCertStore::empty();
+            roots.add(pki.ca.der().clone()).unwrap();
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+        let config = builder
+            .with_single_cert(vec![identity.cert.clone()], identity.key.clone_key())
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            if let Ok(mut stream) = acceptor.accept(stream).await {
+                let mut buf = [0u8; 64];
+                while let Ok(n) = stream.read(&mut buf).await {
+                    if n == 0 || stream.write_all(&buf[..n]).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        port
+    }
+
+    fn tcp_config(port: u16, tls: TlsConfig) -> TcpConfig {
+        TcpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            timeout_ms: 2000,
+            tls: Some(tls),
+            ..TcpConfig::default()
+        }
+    }
+
+    /// 连接并回显一次；返回失败原因（成功为 None）
+    async fn round_trip(config: &TcpConfig) -> Result<(), Option<&'static str>> {
+        let mut link = tcp::open_stream(config).await.map_err(|err| err.reason)?;
+        assert!(matches!(link, TcpLink::Tls(_)));
+        let mut buf = [0u8; 4];
+        let result = async {
+            link.write_all(b"hmip").await?;
+            link.read_exact(&mut buf).await
+        }
+        .await;
+        result.map_err(|err| error_reason(&err))?;
+        assert_eq!(&buf, b"hmip");
+        Ok(())
+    }
+
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn mutual_tls_round_trip_and_missing_client_certificate() {
+        let pki = Pki::new("mtls");
+        let server = pki.issue("server", false);
+        pki.issue("client", false);
+        run(async {
+            let mut tls = pki.client_config();
+            tls.cert_path = Some(pki.path("client.pem"));
+            tls.key_path = Some(pki.path("client.key"));
+            let port = spawn_server(&pki, &server, true).await;
+            assert_eq!(round_trip(&tcp_config(port, tls)).await, Ok(()));
+
+            // TLS 1.3 下服务端的拒绝在首次读取时才到达
+            let port = spawn_server(&pki, &server, true).await;
+            assert_eq!(
+                round_trip(&tcp_config(port, pki.client_config())).await,
+                Err(Some(REASON_CLIENT_CERTIFICATE_REJECTED))
+            );
+        });
+    }
+
+    #[test]
+    fn server_verification_failures_have_distinct_reasons() {
+        let pki = Pki::new("verify");
+        let other = Pki::new("verify_other");
+        let server = pki.issue("server", false);
+        let expired = pki.issue("expired", true);
+        run(async {
+            let port = spawn_server(&pki, &server, false).await;
+            assert_eq!(
+                round_trip(&tcp_config(port, pki.client_config())).await,
+                Ok(())
+            );
+
+            let mut tls = pki.client_config();
+            tls.server_name = Some("plc.example".to_string());
+            let port = spawn_server(&pki, &server, false).await;
+            assert_eq!(
+                round_trip(&tcp_config(port, tls)).await,
+                Err(Some(REASON_SERVER_NAME_MISMATCH))
+            );
+
+            let port = spawn_server(&pki, &expired, false).await;
+            assert_eq!(
+                round_trip(&tcp_config(port, pki.client_config())).await,
+                Err(Some(REASON_CERTIFICATE_EXPIRED))
+            );
+
+            let port = spawn_server(&pki, &server, false).await;
+            assert_eq!(
+                round_trip(&tcp_config(port, other.client_config())).await,
+                Err(Some(REASON_CERTIFICATE_UNTRUSTED))
+            );
+
+            // 未配置 ca_path 时按公共根证书校验，私有 CA 签发的证书不被信任
+            let port = spawn_server(&pki, &server, false).await;
+            let tls = TlsConfig {
+                ca_path: None,
+                ..pki.client_config()
+            };
+            assert_eq!(
+                round_trip(&tcp_config(port, tls)).await,
+                Err(Some(REASON_CERTIFICATE_UNTRUSTED))
+            );
+
+            let mut tls = pki.client_config();
+            tls.ca_path = Some(pki.path("missing.pem"));
+            assert_eq!(
+                round_trip(&tcp_config(port, tls)).await,
+                Err(Some(REASON_CONFIG))
+            );
+        });
+    }
+
+    #[test]
+    fn pinned_fingerprint_accepts_only_matching_certificate() {
+        let pki = Pki::new("pin");
+        let server = pki.issue("server", false);
+        let fingerprint: Vec<String> = Sha256::digest(server.cert.as_ref())
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        let pinned = |pin: String| TlsConfig {
+            ca_path: None,
+            server_name: Some("any.name".to_string()),
+            pinned_sha256: Some(pin),
+            ..pki.client_config()
+        };
+        run(async {
+            let port = spawn_server(&pki, &server, false).await;
+            let config = tcp_config(port, pinned(fingerprint.join(":")));
+            assert_eq!(round_trip(&config).await, Ok(()));
+
+            let port = spawn_server(&pki, &server, false).await;
+            let config = tcp_config(port, pinned("00".repeat(FINGERPRINT_LEN)));
+            assert_eq!(
+                round_trip(&config).await,
+                Err(Some(REASON_FINGERPRINT_MISMATCH))
+            );
+        });
+        assert!(parse_fingerprint("abcd").is_err());
+        assert!(parse_fingerprint(&"zz".repeat(FINGERPRINT_LEN)).is_err());
+    }
+}
@@ -0,0 +1,748 @@
+use crate::comm::{actor, methods, proto, rpc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// HMIP 文件传输（基于 Request/Response，由 HMI 发起，设备实现下列 method）
+///
+/// body 均为小端；string 为 u16 长度前缀 + UTF-8；sha256 为 32 字节原始摘要：
+///
+/// | method | 名称 | Request body | Response body（status=OK） |
+/// | --- | --- | --- | --- |
+/// | 0xFE01 | FILE_STAT | path | size u64 + sha256 |
+/// | 0xFE02 | FILE_READ | path + offset u64 + len u32 | offset u64 + crc32 u32 + data |
+/// | 0xFE10 | FILE_WRITE_BEGIN | path + size u64 + sha256 | offset u64（已接收字节数，续传起点） |
+/// | 0xFE11 | FILE_WRITE | path + offset u64 + crc32 u32 + data | 空 |
+/// | 0xFE12 | FILE_WRITE_END | path | 空（设备校验大小与 sha256，不一致时回非 OK） |
+///
+/// 约定：
+/// - 每个分块带 CRC32（`proto::crc32_bytes`），整文件用 SHA-256 校验
+/// - 单次调用失败（超时/断线/BUSY/分块校验失败）按退避重试同一分块，重连后自动接着传
+/// - 下载先写入 `<name>.part`，旁边的 `<name>.part.meta` 记录远端路径/大小/摘要；
+///   再次下载同一文件且 meta 一致时从 `.part` 末尾续传，校验通过后改名为目标文件
+/// - 上传的续传起点由设备在 FILE_WRITE_BEGIN 中给出；FILE_WRITE 须按 offset 幂等写入
+///   （断线期间排队的请求可能在重连后重复送达）
+pub mod method {
+    pub const FILE_STAT: u16 = 0xFE01;
+    pub const FILE_READ: u16 = 0xFE02;
+    pub const FILE_WRITE_BEGIN: u16 = 0xFE10;
+    pub const FILE_WRITE: u16 = 0xFE11;
+    pub const FILE_WRITE_END: u16 = 0xFE12;
+}
+
+pub const DEFAULT_CHUNK_SIZE: u32 = 1024;
+const MAX_CHUNK_SIZE: u32 = 60 * 1024;
+const MAX_PATH_LEN: usize = 1024;
+const DEFAULT_MAX_RETRIES: u32 = 10;
+const RETRY_MIN_DELAY_MS: u64 = 200;
+const RETRY_MAX_DELAY_MS: u64 = 5000;
+const PROGRESS_INTERVAL_MS: u64 = 200;
+
+const TRANSFER_EVENT_NAME: &str = "hmip-transfer";
+const PART_SUFFIX: &str = ".part";
+const META_SUFFIX: &str = ".part.meta";
+const CANCELLED: &str = "Transfer cancelled";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TransferOptions {
+    /// 每块字节数（默认 1024，上限 60 KiB；超过连接分片上限时由分片层拆分）
+    pub chunk_size: Option<u32>,
+    pub channel: u8,
+    /// 单次调用超时
+    pub timeout_ms: Option<u64>,
+    /// 同一分块连续失败的重试次数
+    pub max_retries: Option<u32>,
+    /// 发送队列名（例如与控制命令共用串口时放入低权重的 bulk 队列）
+    pub queue: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+    Download,
+    Upload,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferInfo {
+    pub id: u32,
+    pub transport: String,
+    pub direction: TransferDirection,
+    pub remote_path: String,
+    pub local_path: String,
+    /// 未取得文件大小前为 0
+    pub total: u64,
+    pub transferred: u64,
+    pub started_at_ms: u64,
+}
+
+/// `hmip-transfer` 事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransferEvent {
+    Progress {
+        transfer: TransferInfo,
+        timestamp_ms: u64,
+    },
+    /// 单次调用失败，等待后重试（通常是断线重连中）
+    Retrying {
+        id: u32,
+        attempt: u32,
+        error: String,
+        timestamp_ms: u64,
+    },
+    Finished {
+        transfer: TransferInfo,
+        /// 取消时为 false 且 error 为 None
+        completed: bool,
+        error: Option<String>,
+        timestamp_ms: u64,
+    },
+}
+
+struct Entry {
+    info: TransferInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+/// 进行中的传输表
+#[derive(Clone, Default)]
+pub struct Transfers {
+    entries: Arc<Mutex<HashMap<u32, Entry>>>,
+    next_id: Arc<AtomicU32>,
+}
+
+impl Transfers {
+    pub fn list(&self) -> Vec<TransferInfo> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+        let mut list: Vec<TransferInfo> = entries.values().map(|e| e.info.clone()).collect();
+        list.sort_by_key(|info| info.id);
+        list
+    }
+
+    /// 请求取消（当前分块结束后生效；下载的 .part 保留以便续传）
+    pub fn cancel(&self, id: u32) -> Result<(), String> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| "Transfer table is poisoned".to_string())?;
+        let entry = entries
+            .get(&id)
+            .ok_or_else(|| format!("Transfer {} not found", id))?;
+        entry.cancel.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn register(&self, mut info: TransferInfo) -> Result<(u32, Arc<AtomicBool>), String> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| "Transfer table is poisoned".to_string())?;
+        if entries
+            .values()
+            .any(|e| e.info.transport == info.transport && e.info.remote_path == info.remote_path)
+        {
+            return Err(format!(
+                "A transfer of {} on {} is already running",
+                info.remote_path, info.transport
+            ));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        info.id = id;
+        let cancel = Arc::new(AtomicBool::new(false));
+        entries.insert(
+            id,
+            Entry {
+                info,
+                cancel: cancel.clone(),
+            },
+        );
+        Ok((id, cancel))
+    }
+
+    fn update(&self, id: u32, transferred: u64, total: u64) -> Option<TransferInfo> {
+        let mut entries = self.entries.lock().ok()?;
+        let entry = entries.get_mut(&id)?;
+        entry.info.transferred = transferred;
+        entry.info.total = total;
+        Some(entry.info.clone())
+    }
+
+    fn remove(&self, id: u32) -> Option<TransferInfo> {
+        self.entries.lock().ok()?.remove(&id).map(|e| e.info)
+    }
+}
+
+/// 下载设备文件到 local_path（通常位于 Log 目录），返回传输 ID
+pub fn start_download(
+    app: AppHandle,
+    transfers: &Transfers,
+    client: rpc::RpcClient,
+    transport: String,
+    remote_path: String,
+    local_path: PathBuf,
+    options: TransferOptions,
+) -> Result<u32, String> {
+    check_path(&remote_path)?;
+    let info = new_info(
+        TransferDirection::Download,
+        transport,
+        &remote_path,
+        &local_path,
+    );
+    let mut job = Job::register(app, transfers, client, info, options)?;
+    let id = job.id;
+    tauri::async_runtime::spawn(async move {
+        let result = job.download(&remote_path, &local_path).await;
+        job.finish(result);
+    });
+    Ok(id)
+}
+
+/// 上传本地文件到设备 remote_path，返回传输 ID
+pub fn start_upload(
+    app: AppHandle,
+    transfers: &Transfers,
+    client: rpc::RpcClient,
+    transport: String,
+    local_path: PathBuf,
+    remote_path: String,
+    options: TransferOptions,
+) -> Result<u32, String> {
+    check_path(&remote_path)?;
+    if !local_path.is_file() {
+        return Err(format!("Local file {} not found", local_path.display()));
+    }
+    let info = new_info(
+        TransferDirection::Upload,
+        transport,
+        &remote_path,
+        &local_path,
+    );
+    let mut job = Job::register(app, transfers, client, info, options)?;
+    let id = job.id;
+    tauri::async_runtime::spawn(async move {
+        let result = job.upload(&local_path, &remote_path).await;
+        job.finish(result);
+    });
+    Ok(id)
+}
+
+/// 下载文件的默认本地文件名：取远端路径最后一段，拒绝目录穿越与 .part 结尾
+///
+/// 名称必须是单个普通路径段；`:` 一律拒绝（Windows 上 `C:evil` 这类盘符相对路径会落到 Log 目录之外）
+pub fn local_file_name(remote_path: &str, requested: Option<&str>) -> Result<String, String> {
+    let name = match requested.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => name,
+        None => remote_path.rsplit(['/', '\\']).next().unwrap_or_default(),
+    };
+    let mut components = Path::new(name).components();
+    let single_normal = matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    );
+    if !single_normal
+        || name.contains(['/', '\\', ':'])
+        || name.ends_with(PART_SUFFIX)
+        || name.ends_with(META_SUFFIX)
+    {
+        return Err(format!("Invalid local file name: {:?}", name));
+    }
+    Ok(name.to_string())
+}
+
+fn new_info(
+    direction: TransferDirection,
+    transport: String,
+    remote_path: &str,
+    local_path: &Path,
+) -> TransferInfo {
+    TransferInfo {
+        id: 0,
+        transport,
+        direction,
+        remote_path: remote_path.to_string(),
+        local_path: local_path.display().to_string(),
+        total: 0,
+        transferred: 0,
+        started_at_ms: actor::now_ms(),
+    }
+}
+
+struct Job {
+    app: AppHandle,
+    transfers: Transfers,
+    client: rpc::RpcClient,
+    id: u32,
+    cancel: Arc<AtomicBool>,
+    chunk_size: u32,
+    channel: u8,
+    queue: Option<String>,
+    timeout: Duration,
+    max_retries: u32,
+    total: u64,
+    last_progress_ms: u64,
+}
+
+impl Job {
+    fn register(
+        app: AppHandle,
+        transfers: &Transfers,
+        client: rpc::RpcClient,
+        info: TransferInfo,
+        options: TransferOptions,
+    ) -> Result<Self, String> {
+        let chunk_size = options.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(format!(
+                "chunk_size must be between 1 and {}",
+                MAX_CHUNK_SIZE
+            ));
+        }
+        let (id, cancel) = transfers.register(info)?;
+        Ok(Self {
+            app,
+            transfers: transfers.clone(),
+            client,
+            id,
+            cancel,
+            chunk_size,
+            channel: options.channel,
+            queue: options.queue,
+            timeout: Duration::from_millis(
+                options
+                    .timeout_ms
+                    .unwrap_or(rpc::DEFAULT_REQUEST_TIMEOUT_MS),
+            ),
+            max_retries: options.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            total: 0,
+            last_progress_ms: 0,
+        })
+    }
+
+    async fn download(&mut self, remote_path: &str, local_path: &Path) -> Result<(), String> {
+        let stat = self
+            .call(
+                method::FILE_STAT,
+                encode_path(remote_path),
+                decode_stat_response,
+            )
+            .await?;
+        self.total = stat.size;
+
+        let part = with_suffix(local_path, PART_SUFFIX);
+        let meta = with_suffix(local_path, META_SUFFIX);
+        let expected_meta = format!("{}\n{}\n{}\n", remote_path, stat.size, hex(&stat.sha256));
+
+        // 断点续传：meta 描述的是同一远端文件时沿用 .part，否则从头开始
+        let mut offset = 0;
+        if std::fs::read_to_string(&meta).ok().as_deref() == Some(expected_meta.as_str()) {
+            offset = std::fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+            if offset > stat.size {
+                offset = 0;
+            }
+        } else {
+            std::fs::write(&meta, &expected_meta)
+                .map_err(|e| format!("Failed to write {}: {}", meta.display(), e))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&part)
+            .map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+        file.set_len(offset)
+            .and_then(|_| file.seek(SeekFrom::Start(offset)))
+            .map_err(|e| format!("Failed to prepare {}: {}", part.display(), e))?;
+        self.progress(offset, true);
+
+        while offset < stat.size {
+            let len = (stat.size - offset).min(u64::from(self.chunk_size)) as u32;
+            let data = self
+                .call(
+                    method::FILE_READ,
+                    encode_read_request(remote_path, offset, len),
+                    |body| decode_read_response(body, offset, len).map(|data| data.to_vec()),
+                )
+                .await?;
+            if data.is_empty() {
+                return Err(format!("Remote file ended early at offset {}", offset));
+            }
+            file.write_all(&data)
+                .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+            offset += data.len() as u64;
+            self.progress(offset, offset >= stat.size);
+        }
+        file.sync_all()
+            .map_err(|e| format!("Failed to flush {}: {}", part.display(), e))?;
+        drop(file);
+
+        let (_, sha256) = hash_file_blocking(part.clone()).await?;
+        if sha256 != stat.sha256 {
+            // 摘要不一致说明 .part 内容已不可信，删除后下次从头下载
+            let _ = std::fs::remove_file(&part);
+            let _ = std::fs::remove_file(&meta);
+            return Err("SHA-256 mismatch after download".to_string());
+        }
+        std::fs::rename(&part, local_path)
+            .map_err(|e| format!("Failed to move {} into place: {}", part.display(), e))?;
+        let _ = std::fs::remove_file(&meta);
+        Ok(())
+    }
+
+    async fn upload(&mut self, local_path: &Path, remote_path: &str) -> Result<(), String> {
+        let (size, sha256) = hash_file_blocking(local_path.to_path_buf()).await?;
+        self.total = size;
+
+        let mut offset = self
+            .call(
+                method::FILE_WRITE_BEGIN,
+                encode_write_begin(remote_path, size, &sha256),
+                decode_offset_response,
+            )
+            .await?;
+        if offset > size {
+            return Err(format!(
+                "Device reported resume offset {} beyond file size {}",
+                offset, size
+            ));
+        }
+        let mut file = File::open(local_path)
+            .and_then(|mut file| file.seek(SeekFrom::Start(offset)).map(|_| file))
+            .map_err(|e| format!("Failed to open {}: {}", local_path.display(), e))?;
+        self.progress(offset, true);
+
+        let mut buf = vec![0u8; self.chunk_size as usize];
+        while offset < size {
+            let len = (size - offset).min(u64::from(self.chunk_size)) as usize;
+            file.read_exact(&mut buf[..len])
+                .map_err(|e| format!("Failed to read {}: {}", local_path.display(), e))?;
+            self.call(
+                method::FILE_WRITE,
+                encode_write(remote_path, offset, &buf[..len]),
+                |_| Ok(()),
+            )
+            .await?;
+            offset += len as u64;
+            self.progress(offset, offset >= size);
+        }
+
+        self.call(method::FILE_WRITE_END, encode_path(remote_path), |_| Ok(()))
+            .await
+    }
+
+    /// 调用设备 method；超时/断线/BUSY/解析失败按退避重试，其他非 OK status 直接失败
+    async fn call<T>(
+        &self,
+        method: u16,
+        body: Vec<u8>,
+        parse: impl Fn(&[u8]) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut attempt = 0u32;
+        loop {
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err(CANCELLED.to_string());
+            }
+            let mut call = rpc::RpcCall::new(method, body.clone());
+            call.channel = self.channel;
+            call.queue = self.queue.clone();
+            call.timeout = self.timeout;
+
+            let error = match self.client.call(call).await {
+                Ok((_, resp)) if resp.status == proto::status::OK => match parse(&resp.body) {
+                    Ok(value) => return Ok(value),
+                    Err(err) => err,
+                },
+                Ok((_, resp)) if resp.status == proto::status::BUSY => "Device is busy".to_string(),
+                Ok((_, resp)) => {
+                    return Err(format!(
+                        "Method 0x{:04X} failed with status {}",
+                        method, resp.status
+                    ))
+                }
+                Err(err) => err,
+            };
+
+            attempt += 1;
+            if attempt > self.max_retries {
+                return Err(format!("{} (gave up after {} attempts)", error, attempt));
+            }
+            self.emit(TransferEvent::Retrying {
+                id: self.id,
+                attempt,
+                error,
+                timestamp_ms: actor::now_ms(),
+            });
+            let delay = (RETRY_MIN_DELAY_MS << attempt.min(5)).min(RETRY_MAX_DELAY_MS);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+    }
+
+    fn progress(&mut self, transferred: u64, force: bool) {
+        let Some(info) = self.transfers.update(self.id, transferred, self.total) else {
+            return;
+        };
+        let now = actor::now_ms();
+        if !force && now.saturating_sub(self.last_progress_ms) < PROGRESS_INTERVAL_MS {
+            return;
+        }
+        self.last_progress_ms = now;
+        self.emit(TransferEvent::Progress {
+            transfer: info,
+            timestamp_ms: now,
+        });
+    }
+
+    fn finish(self, result: Result<(), String>) {
+        let Some(info) = self.transfers.remove(self.id) else {
+            return;
+        };
+        let (completed, error) = match result {
+            Ok(()) => (true, None),
+            Err(err) if err == CANCELLED => (false, None),
+            Err(err) => {
+                log::warn!(
+                    "HMIP transfer {} ({}) failed: {}",
+                    info.id,
+                    info.remote_path,
+                    err
+                );
+                (false, Some(err))
+            }
+        };
+        self.emit(TransferEvent::Finished {
+            transfer: info,
+            completed,
+            error,
+            timestamp_ms: actor::now_ms(),
+        });
+    }
+
+    fn emit(&self, event: TransferEvent) {
+        if let Err(err) = self.app.emit(TRANSFER_EVENT_NAME, &event) {
+            log::warn!(
+                "Failed to emit transfer event (window may be closed): {}",
+                err
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+    pub size: u64,
+    pub sha256: [u8; 32],
+}
+
+fn check_path(path: &str) -> Result<(), String> {
+    if path.is_empty() || path.len() > MAX_PATH_LEN {
+        return Err(format!("Remote path must be 1..={} bytes", MAX_PATH_LEN));
+    }
+    Ok(())
+}
+
+pub fn encode_path(path: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(2 + path.len());
+    methods::put_string(&mut out, path);
+    out
+}
+
+pub fn encode_read_request(path: &str, offset: u64, len: u32) -> Vec<u8> {
+    let mut out = encode_path(path);
+    out.extend_from_slice(&offset.to_le_bytes());
+    out.extend_from_slice(&len.to_le_bytes());
+    out
+}
+
+pub fn encode_write_begin(path: &str, size: u64, sha256: &[u8; 32]) -> Vec<u8> {
+    let mut out = encode_path(path);
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(sha256);
+    out
+}
+
+pub fn encode_write(path: &str, offset: u64, data: &[u8]) -> Vec<u8> {
+    let mut out = encode_path(path);
+    out.extend_from_slice(&offset.to_le_bytes());
+    out.extend_from_slice(&proto::crc32_bytes(data).to_le_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+pub fn decode_stat_response(body: &[u8]) -> Result<FileStat, String> {
+    if body.len() != 40 {
+        return Err(format!(
+            "FILE_STAT response must be 40 bytes, got {}",
+            body.len()
+        ));
+    }
+    let mut sha256 = [0u8; 32];
+    sha256.copy_from_slice(&body[8..40]);
+    Ok(FileStat {
+        size: u64::from_le_bytes(body[..8].try_into().unwrap_or_default()),
+        sha256,
+    })
+}
+
+/// 解析 FILE_READ 应答并校验 offset 与分块 CRC32
+pub fn decode_read_response(body: &[u8], expected_offset: u64, max_len: u32) -> Result<&[u8], String> {
+    if body.len() < 12 {
+        return Err("FILE_READ response is truncated".to_string());
+    }
+    let offset = u64::from_le_bytes(body[..8].try_into().unwrap_or_default());
+    if offset != expected_offset {
+        return Err(format!(
+            "FILE_READ offset mismatch (expected {}, got {})",
+            expected_offset, offset
+        ));
+    }
+    let crc = u32::from_le_bytes(body[8..12].try_into().unwrap_or_default());
+    let data = &body[12..];
+    // 超出请求长度的应答不可信（可能越过文件末尾写入 .part），按失败处理并重试
+    if data.len() > max_len as usize {
+        return Err(format!(
+            "FILE_READ returned {} bytes, requested {}",
+            data.len(),
+            max_len
+        ));
+    }
+    if proto::crc32_bytes(data) != crc {
+        return Err(format!("Chunk CRC32 mismatch at offset {}", offset));
+    }
+    Ok(data)
+}
+
+fn decode_offset_response(body: &[u8]) -> Result<u64, String> {
+    let bytes: [u8; 8] = body.try_into().map_err(|_| {
+        format!(
+            "FILE_WRITE_BEGIN response must be 8 bytes, got {}",
+            body.len()
+        )
+    })?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_file(path: &Path) -> Result<(u64, [u8; 32]), String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = file
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((size, hasher.finalize().into()))
+}
+
+async fn hash_file_blocking(path: PathBuf) -> Result<(u64, [u8; 32]), String> {
+    tauri::async_runtime::spawn_blocking(move || hash_file(&path))
+        .await
+        .map_err(|e| format!("Hash task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_response_checks_offset_and_crc() {
+        let data = b"chunk-data";
+        let mut body = 4096u64.to_le_bytes().to_vec();
+        body.extend_from_slice(&proto::crc32_bytes(data).to_le_bytes());
+        body.extend_from_slice(data);
+
+        assert_eq!(decode_read_response(&body, 4096, 64).unwrap(), data);
+        assert!(decode_read_response(&body, 0, 64)
+            .unwrap_err()
+            .contains("offset"));
+        assert!(decode_read_response(&body, 4096, 4)
+            .unwrap_err()
+            .contains("requested 4"));
+
+        let last = body.len() - 1;
+        body[last] ^= 0xFF;
+        assert!(decode_read_response(&body, 4096, 64)
+            .unwrap_err()
+            .contains("CRC32"));
+    }
+
+    #[test]
+    fn request_bodies_use_length_prefixed_path() {
+        let write = encode_write("a/b", 7, b"xy");
+        assert_eq!(&write[..5], &[3, 0, b'a', b'/', b'b']);
+        assert_eq!(u64::from_le_bytes(write[5..13].try_into().unwrap()), 7);
+        assert_eq!(
+            u32::from_le_bytes(write[13..17].try_into().unwrap()),
+            proto::crc32_bytes(b"xy")
+        );
+        assert_eq!(&write[17..], b"xy");
+
+        let mut stat = 5u64.to_le_bytes().to_vec();
+        stat.extend_from_slice(&[0xAB; 32]);
+        assert_eq!(
+            decode_stat_response(&stat).unwrap(),
+            FileStat {
+                size: 5,
+                sha256: [0xAB; 32]
+            }
+        );
+        assert!(decode_offset_response(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn local_name_rejects_traversal() {
+        assert_eq!(
+            local_file_name("/var/log/run.log", None).unwrap(),
+            "run.log"
+        );
+        assert_eq!(local_file_name("C:\\rcp\\a.rcp", None).unwrap(), "a.rcp");
+        assert_eq!(local_file_name("x", Some("copy.txt")).unwrap(), "copy.txt");
+        assert!(local_file_name("/var/log/", None).is_err());
+        assert!(local_file_name("x", Some("../evil")).is_err());
+        assert!(local_file_name("x", Some("a.part")).is_err());
+        assert!(local_file_name("x", Some("C:evil")).is_err());
+        assert!(local_file_name("D:\\rcp\\", None).is_err());
+        assert!(local_file_name("x", Some(".")).is_err());
+        assert!(local_file_name("x", Some("..")).is_err());
+    }
+
+    #[test]
+    fn hash_file_matches_sha256() {
+        let path = std::env::temp_dir().join(format!("hmip_transfer_hash_{}", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        let (size, digest) = hash_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(size, 3);
+        assert_eq!(
+            hex(&digest),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
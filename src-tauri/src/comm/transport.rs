@@ -0,0 +1,248 @@
+use crate::comm::session::{HandshakeRole, HmipLinkConfig};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+const RECONNECT_MIN_DELAY_MS: u64 = 200;
+const RECONNECT_MAX_DELAY_MS: u64 = 5000;
+
+/// 一种可承载 HMIP 的传输（串口、TCP 客户端、监听模式的对端……）
+///
+/// 说明：
+/// - 所有传输都经 `actor::spawn_actor` 跑同一套 `run_io_loop` 与重连流程，
+///   新增传输只需实现本 trait（打开流、描述、重连策略）
+/// - 实现者通常就是该传输的连接配置本身（如 `SerialConfig`、`TcpConfig`）
+pub trait Transport: Send + Sync + 'static {
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// 传输类型（如 `serial`、`tcp`、`tcp_server`）
+    fn kind(&self) -> &'static str;
+
+    /// 事件中的 transport 字段（如 `serial`、`tcp`、`tcp_server:<addr>`），缺省为 kind
+    fn name(&self) -> &str {
+        self.kind()
+    }
+
+    /// 面向日志的连接目标描述（如 `COM3 @ 115200`、`127.0.0.1:502`）
+    fn describe(&self) -> String;
+
+    /// 该连接的 HMIP 会话层配置
+    fn link(&self) -> &HmipLinkConfig;
+
+    /// 建立（或断线后重新建立）连接
+    fn open(&self) -> impl Future<Output = Result<Self::Stream, TransportError>> + Send;
+
+    /// 断线后的重连策略
+    fn reconnect_policy(&self) -> ReconnectPolicy {
+        ReconnectPolicy::default()
+    }
+
+    /// 面向消息的传输（UDP、WebSocket）返回消息元数据记录，缺省为字节流
+    fn message_meta(&self, _stream: &Self::Stream) -> Option<MessageMeta> {
+        None
+    }
+
+    /// `hmip.handshake.role` 未配置时的握手角色（主动连接为 client）
+    fn handshake_role(&self) -> HandshakeRole {
+        HandshakeRole::Client
+    }
+}
+
+/// 连接失败（建立失败或读写出错）
+///
+/// `reason` 为可区分的失败原因（如 TLS 的 `tls_certificate_expired`），随 `CommEvent::Error` 上报；
+/// 普通 IO 错误为 None。
+#[derive(Debug, Clone)]
+pub struct TransportError {
+    pub message: String,
+    pub reason: Option<&'static str>,
+}
+
+impl TransportError {
+    pub fn new(message: impl Into<String>, reason: Option<&'static str>) -> Self {
+        Self {
+            message: message.into(),
+            reason,
+        }
+    }
+}
+
+impl From<String> for TransportError {
+    fn from(message: String) -> Self {
+        Self::new(message, None)
+    }
+}
+
+impl From<TransportError> for String {
+    fn from(err: TransportError) -> Self {
+        err.message
+    }
+}
+
+/// 消息元数据：流在每次 read 时记下本次数据所属消息的来源与类型，actor 读取后写入 Rx 事件
+///
+/// 说明：
+/// - 数据报语义（UDP）：每次 read 恰好一个数据报，帧不跨数据报
+/// - 非数据报（WebSocket）：二进制消息按字节流送入 HMIP 解码，帧可跨消息；
+///   文本消息整条放在元数据中（不受读缓冲大小限制），只以 Rx 事件上报
+#[derive(Debug, Clone, Default)]
+pub struct MessageMeta {
+    datagram: bool,
+    last: Arc<Mutex<LastMessage>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LastMessage {
+    source: Option<SocketAddr>,
+    text: Option<String>,
+    /// 下一次写的目标（覆盖 source，actor 写完后清除）
+    destination: Option<SocketAddr>,
+}
+
+impl MessageMeta {
+    pub fn datagram() -> Self {
+        Self {
+            datagram: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_datagram(&self) -> bool {
+        self.datagram
+    }
+
+    pub fn set_source(&self, addr: SocketAddr) {
+        if let Ok(mut last) = self.last.lock() {
+            last.source = Some(addr);
+        }
+    }
+
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.last.lock().ok().and_then(|last| last.source)
+    }
+
+    pub fn set_destination(&self, addr: Option<SocketAddr>) {
+        if let Ok(mut last) = self.last.lock() {
+            last.destination = addr;
+        }
+    }
+
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.last.lock().ok().and_then(|last| last.destination)
+    }
+
+    pub fn set_text(&self, text: Option<String>) {
+        if let Ok(mut last) = self.last.lock() {
+            last.text = text;
+        }
+    }
+
+    /// 取出本次 read 对应的文本消息全文（二进制数据为 None）
+    pub fn take_text(&self) -> Option<String> {
+        self.last.lock().ok().and_then(|mut last| last.text.take())
+    }
+}
+
+/// 以连接 ID 作为事件 transport 字段的传输（连接表中的具名连接）
+pub struct Named<T> {
+    id: String,
+    inner: T,
+}
+
+impl<T: Transport> Named<T> {
+    pub fn new(id: impl Into<String>, inner: T) -> Self {
+        Self {
+            id: id.into(),
+            inner,
+        }
+    }
+}
+
+impl<T: Transport> Transport for Named<T> {
+    type Stream = T::Stream;
+
+    fn kind(&self) -> &'static str {
+        self.inner.kind()
+    }
+
+    fn name(&self) -> &str {
+        &self.id
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    fn link(&self) -> &HmipLinkConfig {
+        self.inner.link()
+    }
+
+    fn open(&self) -> impl Future<Output = Result<Self::Stream, TransportError>> + Send {
+        self.inner.open()
+    }
+
+    fn reconnect_policy(&self) -> ReconnectPolicy {
+        self.inner.reconnect_policy()
+    }
+
+    fn message_meta(&self, stream: &Self::Stream) -> Option<MessageMeta> {
+        self.inner.message_meta(stream)
+    }
+
+    fn handshake_role(&self) -> HandshakeRole {
+        self.inner.handshake_role()
+    }
+}
+
+/// 断线（或重连失败）后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconnectPolicy {
+    /// 不重连，actor 直接结束（监听模式的对端由设备重新接入）
+    Never,
+    /// 指数退避重连：第 n 次等待 `min_delay_ms * 2^min(n, 6)`，不超过 `max_delay_ms`
+    Backoff {
+        min_delay_ms: u64,
+        max_delay_ms: u64,
+    },
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::Backoff {
+            min_delay_ms: RECONNECT_MIN_DELAY_MS,
+            max_delay_ms: RECONNECT_MAX_DELAY_MS,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 attempt 次重连前的等待时长；None 表示不再重连
+    pub fn delay_ms(self, attempt: u32) -> Option<u64> {
+        match self {
+            Self::Never => None,
+            Self::Backoff {
+                min_delay_ms,
+                max_delay_ms,
+            } => {
+                let exp = attempt.min(6);
+                Some(min_delay_ms.saturating_mul(1u64 << exp).min(max_delay_ms))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.delay_ms(1), Some(400));
+        assert_eq!(policy.delay_ms(3), Some(1600));
+        assert_eq!(policy.delay_ms(5), Some(5000));
+        assert_eq!(policy.delay_ms(u32::MAX), Some(5000));
+        assert_eq!(ReconnectPolicy::Never.delay_ms(1), None);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::comm::session::HmipLinkConfig;
+use crate::comm::transport::{MessageMeta, Transport, TransportError};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UdpSocket;
+
+/// UDP 传输
+///
+/// 说明：
+/// - 绑定 `bind_host:bind_port`（端口 0 由系统分配）
+/// - 配置 `remote` 时 connect 到该对端，只与它收发；否则接收任意来源，发送目标为最近一个数据报的来源
+///   （尚未收到数据时发往组播组，都没有则丢弃并记日志）；对设备 Request 的应答发回该请求的来源
+/// - 任意来源模式下各来源的接收状态（seq、分片重组）分开维护；认证会话是点对点的，须配置 `remote`
+/// - 每个数据报可包含一帧或多帧 HMIP，帧不跨数据报；Rx 事件的 `source` 为数据报来源地址
+/// - 发送失败（对端未监听、数据报过大等）只丢弃该数据报，不触发重连
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UdpConfig {
+    pub bind_host: String,
+    pub bind_port: u16,
+    pub remote: Option<UdpRemote>,
+    /// 加入组播组（接收发往该组的数据报）
+    pub multicast: Option<UdpMulticastConfig>,
+    /// HMIP 会话层选项（握手等），缺省时不启用
+    #[serde(default)]
+    pub hmip: HmipLinkConfig,
+}
+
+impl Default for UdpConfig {
+    fn default() -> Self {
+        Self {
+            bind_host: "0.0.0.0".to_string(),
+            bind_port: 0,
+            remote: None,
+            multicast: None,
+            hmip: HmipLinkConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UdpRemote {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UdpMulticastConfig {
+    /// 组地址（IPv4 224.0.0.0/4 或 IPv6 ff00::/8）
+    pub group: String,
+    /// IPv4 为本地接口地址，IPv6 为接口索引；缺省由系统选择
+    pub interface: Option<String>,
+    /// 是否接收本机发往该组的数据报
+    #[serde(default)]
+    pub loopback: bool,
+    /// 发往组播组的数据报 TTL / hop limit
+    pub ttl: Option<u32>,
+}
+
+impl UdpMulticastConfig {
+    fn group_addr(&self) -> Result<IpAddr, String> {
+        let group: IpAddr = self
+            .group
+            .parse()
+            .map_err(|_| format!("Invalid multicast group: {}", self.group))?;
+        if !group.is_multicast() {
+            return Err(format!("Not a multicast address: {}", self.group));
+        }
+        Ok(group)
+    }
+
+    fn join(&self, socket: &UdpSocket) -> Result<IpAddr, String> {
+        let group = self.group_addr()?;
+        let interface = self.interface.as_deref().unwrap_or_default();
+        let result = match group {
+            IpAddr::V4(group) => {
+                let interface = if interface.is_empty() {
+                    Ipv4Addr::UNSPECIFIED
+                } else {
+                    interface
+                        .parse()
+                        .map_err(|_| format!("Invalid multicast interface: {}", interface))?
+                };
+                socket
+                    .join_multicast_v4(group, interface)
+                    .and_then(|_| socket.set_multicast_loop_v4(self.loopback))
+                    .and_then(|_| match self.ttl {
+                        Some(ttl) => socket.set_multicast_ttl_v4(ttl),
+                        None => Ok(()),
+                    })
+            }
+            IpAddr::V6(group) => {
+                let index = if interface.is_empty() {
+                    0
+                } else {
+                    interface
+                        .parse()
+                        .map_err(|_| format!("Invalid multicast interface index: {}", interface))?
+                };
+                socket
+                    .join_multicast_v6(&group, index)
+                    .and_then(|_| socket.set_multicast_loop_v6(self.loopback))
+            }
+        };
+        result.map_err(|e| format!("Failed to join multicast group {}: {}", self.group, e))?;
+        Ok(group)
+    }
+}
+
+/// 把 UDP socket 适配为 actor 使用的读写流：一次 read 取一个数据报，一次 write 发一个数据报
+pub struct UdpStream {
+    socket: UdpSocket,
+    connected: bool,
+    /// 未 connect 且尚未收到数据时的发送目标（组播组）
+    fallback_target: Option<SocketAddr>,
+    meta: MessageMeta,
+}
+
+impl UdpStream {
+    fn target(&self) -> Option<SocketAddr> {
+        self.meta
+            .destination()
+            .or_else(|| self.meta.source())
+            .or(self.fallback_target)
+    }
+}
+
+impl AsyncRead for UdpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            match this.socket.poll_recv_from(cx, buf) {
+                Poll::Ready(Ok(addr)) => {
+                    this.meta.set_source(addr);
+                    return Poll::Ready(Ok(()));
+                }
+                // 之前发出的数据报被对端拒收（ICMP port unreachable），不代表本端断开
+                Poll::Ready(Err(err))
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset
+                    ) =>
+                {
+                    continue;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for UdpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = if this.connected {
+            this.socket.poll_send(cx, buf)
+        } else {
+            match this.target() {
+                Some(addr) => this.socket.poll_send_to(cx, buf, addr),
+                None => {
+                    log::warn!("UDP has no destination yet; dropping {} bytes", buf.len());
+                    return Poll::Ready(Ok(buf.len()));
+                }
+            }
+        };
+        match result {
+            Poll::Ready(Err(err)) => {
+                log::warn!("UDP send of {} bytes failed: {}", buf.len(), err);
+                Poll::Ready(Ok(buf.len()))
+            }
+            other => other,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub async fn open_stream(config: &UdpConfig) -> Result<UdpStream, String> {
+    if config.remote.is_none() && config.hmip.auth.is_some() {
+        return Err("hmip.auth requires a UDP remote".to_string());
+    }
+    let bind = format!("{}:{}", config.bind_host, config.bind_port);
+    let socket = UdpSocket::bind(&bind)
+        .await
+        .map_err(|e| format!("Failed to bind {}: {}", bind, e))?;
+
+    let mut fallback_target = None;
+    if let Some(multicast) = &config.multicast {
+        let group = multicast.join(&socket)?;
+        let port = socket
+            .local_addr()
+            .map(|addr| addr.port())
+            .unwrap_or(config.bind_port);
+        fallback_target = Some(SocketAddr::new(group, port));
+    }
+
+    let connected = match &config.remote {
+        Some(remote) => {
+            let addr = format!("{}:{}", remote.host, remote.port);
+            socket
+                .connect(&addr)
+                .await
+                .map_err(|e| format!("Failed to connect {}: {}", addr, e))?;
+            true
+        }
+        None => false,
+    };
+
+    Ok(UdpStream {
+        socket,
+        connected,
+        fallback_target,
+        meta: MessageMeta::datagram(),
+    })
+}
+
+impl Transport for UdpConfig {
+    type Stream = UdpStream;
+
+    fn kind(&self) -> &'static str {
+        "udp"
+    }
+
+    fn describe(&self) -> String {
+        let mut text = format!("{}:{}", self.bind_host, self.bind_port);
+        if let Some(remote) = &self.remote {
+            text.push_str(&format!(" -> {}:{}", remote.host, remote.port));
+        }
+        if let Some(multicast) = &self.multicast {
+            text.push_str(&format!(" (group {})", multicast.group));
+        }
+        text
+    }
+
+    fn link(&self) -> &HmipLinkConfig {
+        &self.hmip
+    }
+
+    async fn open(&self) -> Result<UdpStream, TransportError> {
+        Ok(open_stream(self).await?)
+    }
+
+    fn message_meta(&self, stream: &UdpStream) -> Option<MessageMeta> {
+        Some(stream.meta.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_parses_with_optional_sections() {
+        let config: UdpConfig = serde_json::from_str(
+            r#"{"bind_host":"0.0.0.0","bind_port":5000,
+                "multicast":{"group":"239.1.2.3","ttl":4}}"#,
+        )
+        .unwrap();
+        assert!(config.remote.is_none());
+        let multicast = config.multicast.as_ref().unwrap();
+        assert!(!multicast.loopback);
+        assert_eq!(
+            multicast.group_addr().unwrap(),
+            "239.1.2.3".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(config.describe(), "0.0.0.0:5000 (group 239.1.2.3)");
+    }
+
+    #[test]
+    fn rejects_unicast_group() {
+        let multicast = UdpMulticastConfig {
+            group: "10.0.0.1".to_string(),
+            interface: None,
+            loopback: false,
+            ttl: None,
+        };
+        assert!(multicast
+            .group_addr()
+            .unwrap_err()
+            .contains("Not a multicast"));
+    }
+
+    #[test]
+    fn explicit_destination_overrides_latest_source() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            use tokio::io::AsyncWriteExt;
+
+            let config = UdpConfig {
+                bind_host: "127.0.0.1".to_string(),
+                ..Default::default()
+            };
+            let mut stream = open_stream(&config).await.unwrap();
+            let requester = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let latest = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            stream.meta.set_source(latest.local_addr().unwrap());
+
+            stream.meta.set_destination(Some(requester.local_addr().unwrap()));
+            stream.write_all(b"reply").await.unwrap();
+            stream.meta.set_destination(None);
+            stream.write_all(b"other").await.unwrap();
+
+            let mut buf = [0u8; 16];
+            let n = requester.recv(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"reply");
+            let n = latest.recv(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"other");
+        });
+    }
+
+    #[test]
+    fn auth_requires_a_remote() {
+        let config = UdpConfig {
+            bind_host: "127.0.0.1".to_string(),
+            hmip: HmipLinkConfig {
+                auth: Some(Default::default()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(open_stream(&config))
+            .err()
+            .unwrap();
+        assert!(err.contains("requires a UDP remote"));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::comm::session::HmipLinkConfig;
+use serde::{Deserialize, Serialize};
+
+/// Unix 域套接字传输（连接本机守护进程，如运动控制协处理器）
+///
+/// 说明：
+/// - `path` 为守护进程监听的 socket 文件路径（如 `/run/motion/hmip.sock`）
+/// - 与 TCP 客户端相同走 `run_io_loop`：流式 HMIP 解码，断线后按退避重连（守护进程重启后自动恢复）
+/// - 仅 Unix 平台可用；其它平台 connect 时报错
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnixConfig {
+    pub path: String,
+    pub timeout_ms: u64,
+    /// HMIP 会话层选项（握手等），缺省时不启用
+    #[serde(default)]
+    pub hmip: HmipLinkConfig,
+}
+
+impl Default for UnixConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            timeout_ms: 5000,
+            hmip: HmipLinkConfig::default(),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::UnixConfig;
+    use crate::comm::session::HmipLinkConfig;
+    use crate::comm::transport::{Transport, TransportError};
+    use std::time::Duration;
+    use tokio::net::UnixStream;
+
+    async fn open_stream(config: &UnixConfig) -> Result<UnixStream, String> {
+        if config.path.is_empty() {
+            return Err("Unix socket path is empty".to_string());
+        }
+        tokio::time::timeout(
+            Duration::from_millis(config.timeout_ms),
+            UnixStream::connect(&config.path),
+        )
+        .await
+        .map_err(|_| "Connection timeout".to_string())?
+        .map_err(|e| format!("Failed to connect {}: {}", config.path, e))
+    }
+
+    impl Transport for UnixConfig {
+        type Stream = UnixStream;
+
+        fn kind(&self) -> &'static str {
+            "unix"
+        }
+
+        fn describe(&self) -> String {
+            self.path.clone()
+        }
+
+        fn link(&self) -> &HmipLinkConfig {
+            &self.hmip
+        }
+
+        async fn open(&self) -> Result<UnixStream, TransportError> {
+            Ok(open_stream(self).await?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_parses_with_default_link() {
+        let config: UnixConfig =
+            serde_json::from_str(r#"{"path":"/run/motion/hmip.sock","timeout_ms":1000}"#).unwrap();
+        assert_eq!(config.path, "/run/motion/hmip.sock");
+        assert!(config.hmip.handshake.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn describe_is_the_socket_path() {
+        use crate::comm::transport::Transport;
+        let config = UnixConfig {
+            path: "/tmp/hmi.sock".to_string(),
+            ..UnixConfig::default()
+        };
+        assert_eq!(config.kind(), "unix");
+        assert_eq!(config.describe(), "/tmp/hmi.sock");
+    }
+}
@@ -0,0 +1,417 @@
+use crate::comm::session::HmipLinkConfig;
+use crate::comm::tcp::{self, TcpConfig, TcpLink};
+use crate::comm::tls::TlsConfig;
+use crate::comm::transport::{MessageMeta, Transport, TransportError};
+use bytes::Bytes;
+use futures_util::{Sink, Stream};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Sleep};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::WebSocketStream;
+
+/// WebSocket 客户端传输（设备网关的 ws:// / wss:// 端点）
+///
+/// 说明：
+/// - 二进制消息按字节流送入 HMIP 解码（帧可跨消息）；文本消息只以 Rx 事件上报（`text` 为消息全文）
+/// - 发送的数据（HMIP 帧、send_data）每次写入作为一条二进制消息
+/// - wss:// 使用与 TCP 相同的 `tls` 选项（CA / 双向 TLS / 指纹固定）；未配置时按公共 Web PKI 根证书校验
+/// - 断线后与 TCP 客户端一样按退避重连；配置 `ping` 时按间隔发送 Ping，超时未收到任何消息判定断线
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WsConfig {
+    pub url: String,
+    /// 建立 TCP 连接、TLS 握手与 WebSocket 握手各自的超时
+    pub timeout_ms: u64,
+    /// wss:// 的 TLS 选项，缺省按公共 Web PKI 根证书与 host 校验
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Ping/Pong 保活，缺省不启用
+    #[serde(default)]
+    pub ping: Option<WsPingConfig>,
+    /// HMIP 会话层选项（握手等），缺省时不启用
+    #[serde(default)]
+    pub hmip: HmipLinkConfig,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            url: "ws://127.0.0.1:8080".to_string(),
+            timeout_ms: 5000,
+            tls: None,
+            ping: None,
+            hmip: HmipLinkConfig::default(),
+        }
+    }
+}
+
+/// Ping/Pong 保活参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsPingConfig {
+    /// 发送 Ping 的间隔
+    pub interval_ms: u64,
+    /// 发出 Ping 后等待对端响应（Pong 或任意消息）的时长
+    pub timeout_ms: u64,
+}
+
+impl Default for WsPingConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 10_000,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+/// 单个连接上的 Ping 计时：收到任意消息即视为对端存活
+struct PingTimer {
+    interval: Duration,
+    peer_timeout: Duration,
+    next_ping: Pin<Box<Sleep>>,
+    peer_deadline: Pin<Box<Sleep>>,
+    ping_due: bool,
+}
+
+impl PingTimer {
+    fn start(cfg: &WsPingConfig) -> Self {
+        let interval = Duration::from_millis(cfg.interval_ms.max(1));
+        let peer_timeout = interval + Duration::from_millis(cfg.timeout_ms);
+        let now = Instant::now();
+        Self {
+            interval,
+            peer_timeout,
+            next_ping: Box::pin(tokio::time::sleep_until(now + interval)),
+            peer_deadline: Box::pin(tokio::time::sleep_until(now + peer_timeout)),
+            ping_due: false,
+        }
+    }
+
+    fn on_peer_activity(&mut self) {
+        self.peer_deadline
+            .as_mut()
+            .reset(Instant::now() + self.peer_timeout);
+    }
+}
+
+/// 把 WebSocket 消息流适配为 actor 使用的读写流
+pub struct WsStream {
+    inner: WebSocketStream<TcpLink>,
+    meta: MessageMeta,
+    /// 当前消息尚未读出的部分（消息大于读缓冲时分多次读出）
+    pending: Bytes,
+    flush_pending: bool,
+    ping: Option<PingTimer>,
+}
+
+impl WsStream {
+    /// 推进 Ping 发送与未完成的 flush；对端超时返回错误
+    fn poll_maintain(&mut self, cx: &mut Context<'_>) -> io::Result<()> {
+        if let Some(ping) = self.ping.as_mut() {
+            if ping.peer_deadline.as_mut().poll(cx).is_ready() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "No pong within {}ms",
+                        ping.peer_timeout.saturating_sub(ping.interval).as_millis()
+                    ),
+                ));
+            }
+            if ping.next_ping.as_mut().poll(cx).is_ready() {
+                ping.ping_due = true;
+                let next = Instant::now() + ping.interval;
+                ping.next_ping.as_mut().reset(next);
+                // 重新登记下一次 Ping 的唤醒
+                let _ = ping.next_ping.as_mut().poll(cx);
+            }
+            if ping.ping_due {
+                if let Poll::Ready(ready) = Pin::new(&mut self.inner).poll_ready(cx) {
+                    ready.map_err(to_io_error)?;
+                    Pin::new(&mut self.inner)
+                        .start_send(Message::Ping(Bytes::new()))
+                        .map_err(to_io_error)?;
+                    ping.ping_due = false;
+                    self.flush_pending = true;
+                }
+            }
+        }
+        if self.flush_pending {
+            if let Poll::Ready(result) = Pin::new(&mut self.inner).poll_flush(cx) {
+                result.map_err(to_io_error)?;
+                self.flush_pending = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for WsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.poll_maintain(cx)?;
+        loop {
+            if !this.pending.is_empty() {
+                let n = this.pending.len().min(buf.remaining());
+                buf.put_slice(&this.pending.split_to(n));
+                return Poll::Ready(Ok(()));
+            }
+            let message = match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(to_io_error(err))),
+                // 连接结束：读出 0 字节，由 actor 按 Remote closed 处理
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Some(ping) = this.ping.as_mut() {
+                ping.on_peer_activity();
+            }
+            match message {
+                Message::Binary(data) => {
+                    this.meta.set_text(None);
+                    this.pending = data;
+                }
+                // 文本消息整条经元数据交给 actor，不经读缓冲拆分（避免拆成多个 Rx 事件、切断 UTF-8 字符）；
+                // 读出的字节只是占位，actor 以元数据中的全文为准
+                Message::Text(text) if !text.is_empty() => {
+                    let n = text.len().min(buf.remaining());
+                    buf.put_slice(&text.as_bytes()[..n]);
+                    this.meta.set_text(Some(text.as_str().to_string()));
+                    return Poll::Ready(Ok(()));
+                }
+                Message::Close(_) => return Poll::Ready(Ok(())),
+                // Ping 由 tungstenite 自动回复 Pong；空文本消息没有可上报的内容
+                Message::Text(_) | Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+            }
+        }
+    }
+}
+
+impl AsyncWrite for WsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.poll_maintain(cx)?;
+        match Pin::new(&mut this.inner).poll_ready(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(to_io_error(err))),
+            Poll::Pending => return Poll::Pending,
+        }
+        Pin::new(&mut this.inner)
+            .start_send(Message::Binary(Bytes::copy_from_slice(buf)))
+            .map_err(to_io_error)?;
+        // 消息缓冲在 tungstenite 中，尽快发出；未完成的 flush 在后续读写时继续推进
+        this.flush_pending = true;
+        this.poll_maintain(cx)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_flush(cx);
+        if result.is_ready() {
+            this.flush_pending = false;
+        }
+        result.map_err(to_io_error)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_close(cx)
+            .map_err(to_io_error)
+    }
+}
+
+fn to_io_error(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+pub async fn open_stream(config: &WsConfig) -> Result<WsStream, TransportError> {
+    let request = config
+        .url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| format!("Invalid WebSocket URL {}: {}", config.url, e))?;
+    let uri = request.uri();
+    let secure = match uri.scheme_str() {
+        Some("ws") => false,
+        Some("wss") => true,
+        _ => {
+            return Err(format!(
+                "WebSocket URL must start with ws:// or wss://: {}",
+                config.url
+            )
+            .into())
+        }
+    };
+    let host = uri
+        .host()
+        .ok_or_else(|| format!("WebSocket URL has no host: {}", config.url))?;
+
+    let link = tcp::open_stream(&TcpConfig {
+        // IPv6 字面量去掉方括号，TLS 按 IP 地址校验
+        host: host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        port: uri.port_u16().unwrap_or(if secure { 443 } else { 80 }),
+        timeout_ms: config.timeout_ms,
+        tls: match (secure, &config.tls) {
+            (true, Some(tls)) => Some(tls.clone()),
+            (true, None) => Some(TlsConfig::default()),
+            (false, _) => None,
+        },
+        hmip: HmipLinkConfig::default(),
+    })
+    .await?;
+
+    let (inner, _response) = tokio::time::timeout(
+        Duration::from_millis(config.timeout_ms),
+        tokio_tungstenite::client_async(request, link),
+    )
+    .await
+    .map_err(|_| "WebSocket handshake timeout".to_string())?
+    .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
+
+    Ok(WsStream {
+        inner,
+        meta: MessageMeta::default(),
+        pending: Bytes::new(),
+        flush_pending: false,
+        ping: config.ping.as_ref().map(PingTimer::start),
+    })
+}
+
+impl Transport for WsConfig {
+    type Stream = WsStream;
+
+    fn kind(&self) -> &'static str {
+        "ws"
+    }
+
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn link(&self) -> &HmipLinkConfig {
+        &self.hmip
+    }
+
+    async fn open(&self) -> Result<WsStream, TransportError> {
+        open_stream(self).await
+    }
+
+    fn message_meta(&self, stream: &WsStream) -> Option<MessageMeta> {
+        Some(stream.meta.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn run<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    const TEXT: &str = "腔室压力正常，温度 25℃";
+
+    fn ws_config(port: u16) -> WsConfig {
+        WsConfig {
+            url: format!("ws://127.0.0.1:{}/hmip", port),
+            timeout_ms: 2000,
+            ..WsConfig::default()
+        }
+    }
+
+    #[test]
+    fn binary_and_text_messages_are_marked() {
+        run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                ws.send(Message::binary(vec![1u8, 2, 3])).await.unwrap();
+                ws.send(Message::text("status ok")).await.unwrap();
+                ws.send(Message::text(TEXT)).await.unwrap();
+                // 客户端的写入作为一条二进制消息到达
+                match ws.next().await {
+                    Some(Ok(Message::Binary(data))) => data.to_vec(),
+                    other => panic!("unexpected message: {:?}", other),
+                }
+            });
+
+            let mut stream = open_stream(&ws_config(port)).await.ok().unwrap();
+            let mut buf = [0u8; 16];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], &[1, 2, 3]);
+            assert_eq!(stream.meta.take_text(), None);
+            assert!(stream.read(&mut buf).await.unwrap() > 0);
+            assert_eq!(stream.meta.take_text().as_deref(), Some("status ok"));
+
+            // 超过读缓冲的文本（多字节字符跨缓冲边界）仍是一条完整消息
+            assert_eq!(stream.read(&mut buf).await.unwrap(), buf.len());
+            assert_eq!(stream.meta.take_text().as_deref(), Some(TEXT));
+
+            stream.write_all(&[9, 8, 7]).await.unwrap();
+            assert_eq!(server.await.unwrap(), vec![9, 8, 7]);
+        });
+    }
+
+    #[test]
+    fn silent_peer_fails_ping_keepalive() {
+        run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                // 握手后不再读取，Ping 得不到 Pong
+                let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            });
+
+            let mut config = ws_config(port);
+            config.ping = Some(WsPingConfig {
+                interval_ms: 50,
+                timeout_ms: 50,
+            });
+            let mut stream = open_stream(&config).await.ok().unwrap();
+            let mut buf = [0u8; 16];
+            let err = stream.read(&mut buf).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        });
+    }
+
+    #[test]
+    fn rejects_unsupported_urls() {
+        run(async {
+            let mut config = ws_config(1);
+            config.url = "http://127.0.0.1:1/".to_string();
+            assert!(open_stream(&config)
+                .await
+                .err()
+                .unwrap()
+                .message
+                .contains("ws:// or wss://"));
+        });
+    }
+}
@@ -1,14 +1,15 @@
-use crate::comm::{actor::CommPriority, proto, serial, tcp, CommState};
+use crate::comm::{
+    actor, actor::CommPriority, alarm, auth, capture, fragment, methods, outbox, proto, queue,
+    registry::{self, ConnectionId},
+    rpc, schema, sequence, serial, server, session, tcp, transfer, transport, CommState,
+};
 use crate::sensor::SensorSimulator;
 use crate::system;
 use base64::{engine::general_purpose, Engine as _};
-use serde::Deserialize;
-use std::sync::atomic::{AtomicU32, Ordering};
-use tokio::sync::mpsc::error::TrySendError;
-use tauri::{AppHandle, Manager, State};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
 use std::path::PathBuf;
-
-static HMIP_NEXT_SEQ: AtomicU32 = AtomicU32::new(1);
+use std::time::Duration;
 
 #[tauri::command]
 pub fn get_system_overview() -> Result<system::SystemOverview, String> {
@@ -109,6 +110,136 @@ pub async fn get_serial_ports() -> Result<Vec<String>, String> {
     serial::list_ports()
 }
 
+/// 建立（或替换）指定 ID 的连接：先同步打开一次，失败直接返回错误，成功后交给 actor 维护重连
+async fn open_connection<T: transport::Transport>(
+    app: AppHandle,
+    state: &CommState,
+    id: ConnectionId,
+    conn: T,
+) -> Result<(), String> {
+    let auth = conn.link().frame_auth()?;
+    conn.link().validate_tx_queues()?;
+    let stream = match conn.open().await {
+        Ok(stream) => stream,
+        Err(err) => {
+            // 带 reason 的失败（如 TLS 握手）同时以 comm-event 上报，与重连失败的事件一致
+            if err.reason.is_some() {
+                let _ = actor::emit_event(
+                    &app,
+                    &actor::CommEvent::Error {
+                        transport: id.as_str().to_string(),
+                        message: err.message.clone(),
+                        reason: err.reason,
+                        timestamp_ms: actor::now_ms(),
+                    },
+                );
+            }
+            return Err(err.message);
+        }
+    };
+    let slot = state.connections.slot_or_insert(&id);
+    let outbox = open_outbox(&app, &slot, &id, conn.link()).await?;
+
+    // 先停掉旧 actor 再启动新的：两者共用发件箱，同时运行会把队首消息各发一次
+    let mut current = slot.lock().await;
+    if let Some(old) = current.take() {
+        old.shutdown().await;
+    }
+    *current = Some(actor::spawn_actor(
+        app,
+        transport::Named::new(id.as_str(), conn),
+        stream,
+        auth.map(std::sync::Arc::new),
+        state.shared.clone(),
+        outbox,
+        None,
+    ));
+    Ok(())
+}
+
+async fn close_connection(state: &CommState, id: &ConnectionId) {
+    if let Some(old) = state.connections.remove(id).await {
+        old.shutdown().await;
+    }
+}
+
+async fn send_raw_to(
+    state: &CommState,
+    id: &ConnectionId,
+    data: Vec<u8>,
+    priority: Option<CommPriority>,
+    queue: Option<&str>,
+    ttl_ms: Option<u64>,
+) -> Result<(), String> {
+    let tx_queues = {
+        let slot = state.slot(id);
+        let lock = slot.lock().await;
+        let handle = lock
+            .as_ref()
+            .ok_or_else(|| id.not_connected_message())?;
+        handle.tx_queues.clone()
+    };
+
+    push_raw(&id.label(), &tx_queues, data, priority, queue, ttl_ms)
+}
+
+/// 建立具名连接（同一 ID 已连接时替换旧连接）
+///
+/// 说明：
+/// - `id` 由前端指定，之后作为其它命令的 `transport` 参数，也是该连接事件中的 `transport` 字段
+/// - `config.kind` 为 `serial` / `tcp` / `udp` / `unix` / `ws`，其余字段为对应传输的配置
+///   （`SerialConfig` / `TcpConfig` / `UdpConfig` / `UnixConfig` / `WsConfig`）
+#[tauri::command]
+pub async fn connect(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    id: ConnectionId,
+    config: registry::ConnectionConfig,
+) -> Result<(), String> {
+    id.validate()?;
+    config.check_id(&id)?;
+    match config {
+        registry::ConnectionConfig::Serial(config) => open_connection(app, &state, id, config).await,
+        registry::ConnectionConfig::Tcp(config) => open_connection(app, &state, id, config).await,
+        registry::ConnectionConfig::Udp(config) => open_connection(app, &state, id, config).await,
+        registry::ConnectionConfig::Ws(config) => open_connection(app, &state, id, config).await,
+        #[cfg(unix)]
+        registry::ConnectionConfig::Unix(config) => open_connection(app, &state, id, config).await,
+        #[cfg(not(unix))]
+        registry::ConnectionConfig::Unix(_) => {
+            Err("Unix domain sockets are not supported on this platform".to_string())
+        }
+    }
+}
+
+/// 断开并注销具名连接（ID 不存在时忽略）
+#[tauri::command]
+pub async fn disconnect(state: State<'_, CommState>, id: ConnectionId) -> Result<(), String> {
+    close_connection(&state, &id).await;
+    Ok(())
+}
+
+/// 列出已登记的连接（含正在重连的）
+#[tauri::command]
+pub async fn list_connections(
+    state: State<'_, CommState>,
+) -> Result<Vec<registry::ConnectionInfo>, String> {
+    Ok(state.connections.list().await)
+}
+
+/// 向指定连接发送原始数据
+#[tauri::command]
+pub async fn send_data(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    data: Vec<u8>,
+    priority: Option<CommPriority>,
+    queue: Option<String>,
+    ttl_ms: Option<u64>,
+) -> Result<(), String> {
+    send_raw_to(&state, &transport, data, priority, queue.as_deref(), ttl_ms).await
+}
+
 /// 连接串口
 #[tauri::command]
 pub async fn connect_serial(
@@ -116,29 +247,13 @@ pub async fn connect_serial(
     state: State<'_, CommState>,
     config: serial::SerialConfig,
 ) -> Result<(), String> {
-    let stream = serial::open_stream(&config)?;
-    let handle = crate::comm::actor::spawn_serial_actor(app, config.clone(), stream);
-
-    let old = {
-        let mut serial_lock = state.serial.lock().await;
-        serial_lock.replace(handle)
-    };
-    if let Some(old) = old {
-        old.shutdown().await;
-    }
-    Ok(())
+    open_connection(app, &state, ConnectionId::serial(), config).await
 }
 
 /// 断开串口
 #[tauri::command]
 pub async fn disconnect_serial(state: State<'_, CommState>) -> Result<(), String> {
-    let old = {
-        let mut serial_lock = state.serial.lock().await;
-        serial_lock.take()
-    };
-    if let Some(old) = old {
-        old.shutdown().await;
-    }
+    close_connection(&state, &ConnectionId::serial()).await;
     Ok(())
 }
 
@@ -148,24 +263,10 @@ pub async fn send_serial_data(
     state: State<'_, CommState>,
     data: Vec<u8>,
     priority: Option<CommPriority>,
+    queue: Option<String>,
+    ttl_ms: Option<u64>,
 ) -> Result<(), String> {
-    let (tx_high, tx_normal) = {
-        let serial_lock = state.serial.lock().await;
-        let handle = serial_lock
-            .as_ref()
-            .ok_or_else(|| "Serial port not connected".to_string())?;
-        (handle.tx_high.clone(), handle.tx_normal.clone())
-    };
-
-    let tx = match priority.unwrap_or_default() {
-        CommPriority::High => tx_high,
-        CommPriority::Normal => tx_normal,
-    };
-
-    tx.try_send(data).map_err(|err| match err {
-        TrySendError::Full(_) => "Serial write queue is full".to_string(),
-        TrySendError::Closed(_) => "Serial connection is closed".to_string(),
-    })
+    send_raw_to(&state, &ConnectionId::serial(), data, priority, queue.as_deref(), ttl_ms).await
 }
 
 /// 连接 TCP 服务
@@ -175,56 +276,149 @@ pub async fn connect_tcp(
     state: State<'_, CommState>,
     config: tcp::TcpConfig,
 ) -> Result<(), String> {
-    let stream = tcp::open_stream(&config).await?;
-    let handle = crate::comm::actor::spawn_tcp_actor(app, config.clone(), stream);
+    open_connection(app, &state, ConnectionId::tcp(), config).await
+}
 
-    let old = {
-        let mut tcp_lock = state.tcp.lock().await;
-        tcp_lock.replace(handle)
-    };
+/// 断开 TCP
+#[tauri::command]
+pub async fn disconnect_tcp(state: State<'_, CommState>) -> Result<(), String> {
+    close_connection(&state, &ConnectionId::tcp()).await;
+    Ok(())
+}
+
+/// 通过 TCP 发送数据
+#[tauri::command]
+pub async fn send_tcp_data(
+    state: State<'_, CommState>,
+    data: Vec<u8>,
+    priority: Option<CommPriority>,
+    queue: Option<String>,
+    ttl_ms: Option<u64>,
+) -> Result<(), String> {
+    send_raw_to(&state, &ConnectionId::tcp(), data, priority, queue.as_deref(), ttl_ms).await
+}
+
+/// 启动 TCP 监听模式（设备主动接入），返回实际监听地址
+///
+/// 每个接入的对端各自运行一个 actor，事件中的 transport 为 `tcp_server:<peer_addr>`；
+/// 已在监听时先停止旧的监听并断开其全部对端。
+#[tauri::command]
+pub async fn start_tcp_server(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    config: server::TcpServerConfig,
+) -> Result<String, String> {
+    let auth = config.hmip.frame_auth()?;
+    config.hmip.validate_tx_queues()?;
+    let old = state.tcp_server.lock().await.take();
     if let Some(old) = old {
         old.shutdown().await;
     }
-    Ok(())
+
+    let handle = server::start(app, config, auth, state.shared.clone()).await?;
+    let local_addr = handle.local_addr().to_string();
+    let old = state.tcp_server.lock().await.replace(handle);
+    if let Some(old) = old {
+        old.shutdown().await;
+    }
+    Ok(local_addr)
 }
 
-/// 断开 TCP
+/// 停止 TCP 监听并断开全部对端
 #[tauri::command]
-pub async fn disconnect_tcp(state: State<'_, CommState>) -> Result<(), String> {
-    let old = {
-        let mut tcp_lock = state.tcp.lock().await;
-        tcp_lock.take()
-    };
+pub async fn stop_tcp_server(state: State<'_, CommState>) -> Result<(), String> {
+    let old = state.tcp_server.lock().await.take();
     if let Some(old) = old {
         old.shutdown().await;
     }
     Ok(())
 }
 
-/// 通过 TCP 发送数据
+/// 列出当前接入的对端
 #[tauri::command]
-pub async fn send_tcp_data(
+pub async fn list_tcp_peers(state: State<'_, CommState>) -> Result<Vec<server::PeerInfo>, String> {
+    let lock = state.tcp_server.lock().await;
+    let server = lock
+        .as_ref()
+        .ok_or_else(|| "TCP server not running".to_string())?;
+    Ok(server.peers())
+}
+
+/// 向指定对端发送原始数据
+#[tauri::command]
+pub async fn send_tcp_peer_data(
     state: State<'_, CommState>,
+    peer: String,
     data: Vec<u8>,
     priority: Option<CommPriority>,
+    queue: Option<String>,
+    ttl_ms: Option<u64>,
 ) -> Result<(), String> {
-    let (tx_high, tx_normal) = {
-        let tcp_lock = state.tcp.lock().await;
-        let handle = tcp_lock
+    let tx_queues = {
+        let lock = state.tcp_server.lock().await;
+        let server = lock
             .as_ref()
-            .ok_or_else(|| "TCP not connected".to_string())?;
-        (handle.tx_high.clone(), handle.tx_normal.clone())
+            .ok_or_else(|| "TCP server not running".to_string())?;
+        server.with_peer(&peer, |handle| handle.tx_queues.clone())?
+    };
+
+    push_raw(
+        &format!("TCP peer {}", peer),
+        &tx_queues,
+        data,
+        priority,
+        queue.as_deref(),
+        ttl_ms,
+    )
+}
+
+/// 原始数据入队（无 channel，按 queue 名或 priority 选择队列）
+fn push_raw(
+    label: &str,
+    tx_queues: &queue::TxQueues,
+    data: Vec<u8>,
+    priority: Option<CommPriority>,
+    queue: Option<&str>,
+    ttl_ms: Option<u64>,
+) -> Result<(), String> {
+    let target = queue::QueueTarget {
+        queue,
+        channel: None,
+        priority: priority.unwrap_or_default(),
+        ttl_ms,
     };
+    tx_queues
+        .try_push(target, queue::Outbound::Frame(data))
+        .map_err(|err| err.message(label))
+}
 
-    let tx = match priority.unwrap_or_default() {
-        CommPriority::High => tx_high,
-        CommPriority::Normal => tx_normal,
+/// 向指定对端发送 HMIP 帧（分片/认证/帧头版本与主动连接一致），返回（首帧）seq
+#[tauri::command]
+pub async fn send_tcp_peer_hmip_frame(
+    state: State<'_, CommState>,
+    peer: String,
+    frame: HmipSendFrame,
+) -> Result<u32, String> {
+    let sink = {
+        let lock = state.tcp_server.lock().await;
+        let server = lock
+            .as_ref()
+            .ok_or_else(|| "TCP server not running".to_string())?;
+        server.with_peer(&peer, |handle| {
+            FrameSink::from_handle(format!("TCP peer {}", peer), handle)
+        })?
     };
+    send_hmip_frame_via(sink, frame).await
+}
 
-    tx.try_send(data).map_err(|err| match err {
-        TrySendError::Full(_) => "TCP write queue is full".to_string(),
-        TrySendError::Closed(_) => "TCP connection is closed".to_string(),
-    })
+/// 断开指定对端（对端可再次接入）
+#[tauri::command]
+pub async fn kick_tcp_peer(state: State<'_, CommState>, peer: String) -> Result<(), String> {
+    let lock = state.tcp_server.lock().await;
+    let server = lock
+        .as_ref()
+        .ok_or_else(|| "TCP server not running".to_string())?;
+    server.kick(&peer).await
 }
 
 // Deserialize 是 serde 生态中的一个特征表示一个类型可以从外部数据格式反序列化回来
@@ -237,84 +431,920 @@ pub struct HmipSendFrame {
     pub seq: Option<u32>,
     pub payload: Vec<u8>,
     pub priority: Option<CommPriority>,
+    /// 发送队列名；None 时按 channel 映射或 priority 选择
+    pub queue: Option<String>,
+    /// 排队超过该时长未发出则丢弃（分片消息的每个分片各自计时）
+    pub ttl_ms: Option<u64>,
 }
 
-fn next_hmip_seq(seq: Option<u32>) -> u32 {
-    seq.unwrap_or_else(|| HMIP_NEXT_SEQ.fetch_add(1, Ordering::Relaxed))
+#[tauri::command]
+pub async fn send_tcp_hmip_frame(
+    state: State<'_, CommState>,
+    frame: HmipSendFrame,
+) -> Result<u32, String> {
+    send_hmip_frame_to(&state, ConnectionId::tcp(), frame).await
 }
 
 #[tauri::command]
-pub async fn send_tcp_hmip_frame(
+pub async fn send_serial_hmip_frame(
     state: State<'_, CommState>,
     frame: HmipSendFrame,
 ) -> Result<u32, String> {
-    let (tx_high, tx_normal) = {
-        let tcp_lock = state.tcp.lock().await;
-        let handle = tcp_lock
+    send_hmip_frame_to(&state, ConnectionId::serial(), frame).await
+}
+
+/// 向指定连接发送 HMIP 帧（`transport` 为连接 ID）
+#[tauri::command]
+pub async fn send_hmip_frame(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    frame: HmipSendFrame,
+) -> Result<u32, String> {
+    send_hmip_frame_to(&state, transport, frame).await
+}
+
+/// 发送 HMIP 帧所需的连接快照（取出后即释放连接表的锁）
+struct FrameSink {
+    /// 错误提示中的连接名称
+    label: String,
+    tx_queues: std::sync::Arc<queue::TxQueues>,
+    fragmentation: Option<fragment::FragmentConfig>,
+    fragment_id: u32,
+    tx_seq: std::sync::Arc<sequence::SeqGenerator>,
+}
+
+impl FrameSink {
+    fn from_handle(label: String, handle: &actor::CommActorHandle) -> Self {
+        Self {
+            label,
+            tx_queues: handle.tx_queues.clone(),
+            fragmentation: handle.fragmentation().cloned(),
+            fragment_id: handle.next_fragment_id(),
+            tx_seq: handle.tx_seq(),
+        }
+    }
+}
+
+async fn send_hmip_frame_to(
+    state: &CommState,
+    transport: ConnectionId,
+    frame: HmipSendFrame,
+) -> Result<u32, String> {
+    let sink = {
+        let slot = state.slot(&transport);
+        let lock = slot.lock().await;
+        let handle = lock
             .as_ref()
-            .ok_or_else(|| "TCP not connected".to_string())?;
-        (handle.tx_high.clone(), handle.tx_normal.clone())
+            .ok_or_else(|| transport.not_connected_message())?;
+        FrameSink::from_handle(transport.label(), handle)
     };
+    send_hmip_frame_via(sink, frame).await
+}
+
+/// 写入指定连接的写队列（出队时按当前会话封帧），返回（首帧）seq
+///
+/// 若连接配置了分片且 payload 超过分片上限，则拆成多帧逐个入队：
+/// 每个分片独立排队，high 优先级的控制帧可以插在分片之间发出。
+/// 自动分配的 seq 在帧发出前因重连被重置时会重新分配，此时实际发出的 seq 与返回值不同。
+async fn send_hmip_frame_via(sink: FrameSink, frame: HmipSendFrame) -> Result<u32, String> {
+    let FrameSink {
+        label,
+        tx_queues,
+        fragmentation,
+        fragment_id,
+        tx_seq,
+    } = sink;
 
-    let seq = next_hmip_seq(frame.seq);
     let flags = frame.flags.unwrap_or(0);
     let channel = frame.channel.unwrap_or(0);
-    let bytes = proto::encode_frame(proto::EncodeFrameParams {
-        msg_type: frame.msg_type,
-        flags,
-        channel,
-        seq,
-        payload: &frame.payload,
-    });
+    let target = queue::QueueTarget {
+        queue: frame.queue.as_deref(),
+        channel: Some(channel),
+        priority: frame.priority.unwrap_or_default(),
+        ttl_ms: frame.ttl_ms,
+    };
 
-    let tx = match frame.priority.unwrap_or_default() {
-        CommPriority::High => tx_high,
-        CommPriority::Normal => tx_normal,
+    let fragmentation =
+        fragmentation.filter(|cfg| frame.payload.len() > cfg.max_fragment_payload);
+    let Some(cfg) = fragmentation else {
+        let seq = match frame.seq {
+            Some(seq) => sequence::TxSeq::Explicit(seq),
+            None => tx_seq.assign(channel),
+        };
+        let hmip = queue::HmipFrame {
+            msg_type: frame.msg_type,
+            flags,
+            channel,
+            seq,
+            version: None,
+            destination: None,
+            payload: frame.payload,
+        };
+        tx_queues
+            .try_push(target, queue::Outbound::Hmip(hmip))
+            .map_err(|err| err.message(&label))?;
+        return Ok(seq.value());
     };
 
-    tx.try_send(bytes).map_err(|err| match err {
-        TrySendError::Full(_) => "TCP write queue is full".to_string(),
-        TrySendError::Closed(_) => "TCP connection is closed".to_string(),
-    })?;
+    let mut requested_seq = frame.seq;
+    let mut first_seq = None;
+    let frames = fragment::encode_fragments(
+        fragment::FragmentParams {
+            msg_type: frame.msg_type,
+            flags,
+            channel,
+            fragment_id,
+            max_fragment_payload: cfg.max_fragment_payload,
+            payload: &frame.payload,
+        },
+        || {
+            // 指定 seq 时仅用于首个分片，其余分片继续自增分配
+            let seq = match requested_seq.take() {
+                Some(seq) => sequence::TxSeq::Explicit(seq),
+                None => tx_seq.assign(channel),
+            };
+            first_seq.get_or_insert(seq.value());
+            seq
+        },
+    )?;
+    let seq = first_seq.unwrap_or_default();
+
+    // 分片数量可能超过队列容量：这里按队列背压逐个等待入队，而不是直接报 queue full
+    for hmip in frames {
+        tx_queues
+            .push(target, queue::Outbound::Hmip(hmip))
+            .await
+            .map_err(|err| err.message(&label))?;
+    }
 
     Ok(seq)
 }
 
+/// 类型化 HMIP 消息（与 `proto::Message` 一一对应，由后端统一编码 payload）
+///
+/// 省略的 timestamp_ms 使用当前时间；省略的 request_id 由连接自动分配。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HmipMessageSpec {
+    Hello {
+        #[serde(default)]
+        role: HmipRole,
+        #[serde(default)]
+        capabilities: u32,
+        #[serde(default)]
+        name: String,
+    },
+    HelloAck {
+        #[serde(default)]
+        capabilities: u32,
+        #[serde(default)]
+        name: String,
+    },
+    Heartbeat {
+        timestamp_ms: Option<u64>,
+    },
+    Request {
+        request_id: Option<u32>,
+        method: u16,
+        #[serde(default)]
+        body: Vec<u8>,
+    },
+    Response {
+        request_id: u32,
+        status: u16,
+        #[serde(default)]
+        body: Vec<u8>,
+    },
+    Event {
+        event_id: u16,
+        timestamp_ms: Option<u64>,
+        #[serde(default)]
+        body: Vec<u8>,
+    },
+    Error {
+        code: u16,
+        #[serde(default)]
+        message: String,
+    },
+    Raw {
+        msg_type: u8,
+        #[serde(default)]
+        payload: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HmipRole {
+    #[default]
+    Client,
+    Server,
+}
+
+impl HmipMessageSpec {
+    fn into_message(self, next_request_id: impl FnOnce() -> u32) -> proto::Message {
+        let now = || actor::now_ms();
+        match self {
+            Self::Hello {
+                role,
+                capabilities,
+                name,
+            } => proto::Message::Hello(proto::Hello {
+                role: match role {
+                    HmipRole::Client => proto::Role::Client,
+                    HmipRole::Server => proto::Role::Server,
+                },
+                capabilities,
+                name,
+                nonce: None,
+            }),
+            Self::HelloAck { capabilities, name } => proto::Message::HelloAck(proto::HelloAck {
+                capabilities,
+                name,
+                nonce: None,
+            }),
+            Self::Heartbeat { timestamp_ms } => proto::Message::Heartbeat(proto::Heartbeat {
+                timestamp_ms: timestamp_ms.unwrap_or_else(now),
+            }),
+            Self::Request {
+                request_id,
+                method,
+                body,
+            } => proto::Message::Request(proto::Request {
+                request_id: request_id.unwrap_or_else(next_request_id),
+                method,
+                body: body.into(),
+            }),
+            Self::Response {
+                request_id,
+                status,
+                body,
+            } => proto::Message::Response(proto::Response {
+                request_id,
+                status,
+                body: body.into(),
+            }),
+            Self::Event {
+                event_id,
+                timestamp_ms,
+                body,
+            } => proto::Message::Event(proto::Event {
+                event_id,
+                timestamp_ms: timestamp_ms.unwrap_or_else(now),
+                body: body.into(),
+            }),
+            Self::Error { code, message } => proto::Message::Error(proto::ProtoError { code, message }),
+            Self::Raw { msg_type, payload } => proto::Message::Raw {
+                msg_type,
+                payload: payload.into(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HmipSendMessage {
+    pub message: HmipMessageSpec,
+    pub channel: Option<u8>,
+    pub seq: Option<u32>,
+    /// 覆盖连接配置中的 CRC 策略
+    pub crc: Option<session::CrcPolicy>,
+    pub priority: Option<CommPriority>,
+    pub queue: Option<String>,
+    pub ttl_ms: Option<u64>,
+}
+
+/// 发送类型化 HMIP 消息：后端编码 payload，按 CRC 策略置位，返回（首帧）seq
+///
+/// 说明：
+/// - 分片、认证与帧头版本与 `send_*_hmip_frame` 一致
+/// - Request 只发送不等待；需要等待 Response 时使用 `send_hmip_request`
 #[tauri::command]
-pub async fn send_serial_hmip_frame(
+pub async fn send_hmip_message(
     state: State<'_, CommState>,
-    frame: HmipSendFrame,
+    transport: ConnectionId,
+    message: HmipSendMessage,
 ) -> Result<u32, String> {
-    let (tx_high, tx_normal) = {
-        let serial_lock = state.serial.lock().await;
-        let handle = serial_lock
+    let (sink, crc_policy, authenticated, msg) = {
+        let slot = state.slot(&transport);
+        let lock = slot.lock().await;
+        let handle = lock
             .as_ref()
-            .ok_or_else(|| "Serial port not connected".to_string())?;
-        (handle.tx_high.clone(), handle.tx_normal.clone())
+            .ok_or_else(|| transport.not_connected_message())?;
+        (
+            FrameSink::from_handle(transport.label(), handle),
+            handle.crc_policy(),
+            handle.auth().is_some(),
+            message.message.into_message(|| handle.next_request_id()),
+        )
     };
 
-    let seq = next_hmip_seq(frame.seq);
-    let flags = frame.flags.unwrap_or(0);
-    let channel = frame.channel.unwrap_or(0);
-    let bytes = proto::encode_frame(proto::EncodeFrameParams {
-        msg_type: frame.msg_type,
-        flags,
-        channel,
+    let (msg_type, payload) = proto::encode_message(&msg);
+    let flags = message.crc.unwrap_or(crc_policy).flags(authenticated);
+    send_hmip_frame_via(
+        sink,
+        HmipSendFrame {
+            msg_type,
+            flags: Some(flags),
+            channel: message.channel,
+            seq: message.seq,
+            payload,
+            priority: message.priority,
+            queue: message.queue,
+            ttl_ms: message.ttl_ms,
+        },
+    )
+    .await
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HmipSendRequest {
+    pub method: u16,
+    pub body: Vec<u8>,
+    pub flags: Option<u8>,
+    pub channel: Option<u8>,
+    pub seq: Option<u32>,
+    /// 发送队列名；None 时按 channel 映射，未映射时进入 normal 队列
+    pub queue: Option<String>,
+    /// 排队超过该时长未发出则丢弃
+    pub ttl_ms: Option<u64>,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HmipRequestResult {
+    pub request_id: u32,
+    pub seq: u32,
+    pub status: u16,
+    pub body_len: usize,
+    pub body_base64: String,
+}
+
+/// 发送 HMIP Request 并等待匹配 request_id 的 Response
+///
+/// 说明：
+/// - request_id 由后端按连接分配，pending 表由 actor 维护
+/// - 超时/断线/关闭均以 Err 返回；Response 同时仍会以 `hmip-event` 推送
+#[tauri::command]
+pub async fn send_hmip_request(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    request: HmipSendRequest,
+) -> Result<HmipRequestResult, String> {
+    let client = {
+        let slot = state.slot(&transport);
+        let lock = slot.lock().await;
+        let handle = lock
+            .as_ref()
+            .ok_or_else(|| transport.not_connected_message())?;
+        handle.rpc_client(&transport.label())
+    };
+
+    let mut call = rpc::RpcCall::new(request.method, request.body);
+    call.channel = request.channel.unwrap_or(0);
+    call.flags = request.flags.unwrap_or(proto::FLAG_CRC32);
+    call.seq = request.seq;
+    call.queue = request.queue;
+    call.ttl_ms = request.ttl_ms;
+    call.timeout = Duration::from_millis(
+        request
+            .timeout_ms
+            .unwrap_or(rpc::DEFAULT_REQUEST_TIMEOUT_MS),
+    );
+    let (seq, response) = client.call(call).await?;
+
+    Ok(HmipRequestResult {
+        request_id: response.request_id,
         seq,
-        payload: &frame.payload,
-    });
+        status: response.status,
+        body_len: response.body.len(),
+        body_base64: general_purpose::STANDARD.encode(&response.body),
+    })
+}
+
+/// 查询握手会话信息（对端名称/能力位）；未连接时报错，未完成握手时返回 None
+#[tauri::command]
+pub async fn get_hmip_session(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<Option<session::SessionInfo>, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    Ok(handle.session())
+}
 
-    let tx = match frame.priority.unwrap_or_default() {
-        CommPriority::High => tx_high,
-        CommPriority::Normal => tx_normal,
+#[derive(Debug, Clone, Serialize)]
+pub struct HmipPayloadChunk {
+    pub id: u64,
+    pub transport: String,
+    pub seq: u32,
+    pub total_len: usize,
+    pub offset: usize,
+    pub data_base64: String,
+}
+
+/// 拉取被事件截断的完整 payload（或其中一段）
+///
+/// 说明：
+/// - `id` 为 `message` 事件中 `payload_handle.id`（由缓存分配，不随 seq 重复）
+/// - 缓存有容量上限，较早的 payload 可能已被淘汰；断开连接后缓存仍可读取
+#[tauri::command]
+pub fn fetch_hmip_payload(
+    state: State<'_, CommState>,
+    id: u64,
+    offset: Option<usize>,
+    length: Option<usize>,
+) -> Result<HmipPayloadChunk, String> {
+    let offset = offset.unwrap_or(0);
+    let store = state
+        .shared
+        .payloads
+        .lock()
+        .map_err(|_| "HMIP payload store is poisoned".to_string())?;
+    let slice = store.get(id, offset, length)?;
+
+    Ok(HmipPayloadChunk {
+        id,
+        transport: slice.transport,
+        seq: slice.seq,
+        total_len: slice.total_len,
+        offset,
+        data_base64: general_purpose::STANDARD.encode(&slice.data),
+    })
+}
+
+/// 加载 HMIP body schema 配置文件（未指定路径时使用 Log 目录下的 hmip_schema.json）
+///
+/// 加载成功后立即对所有连接生效；失败时保留原有 schema。
+#[tauri::command]
+pub fn load_hmip_schema(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    path: Option<String>,
+) -> Result<schema::SchemaInfo, String> {
+    let path = match path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+        _ => PathBuf::from(get_log_dir(app)?).join(schema::DEFAULT_SCHEMA_FILE),
     };
+    let registry = schema::SchemaRegistry::load(&path)?;
+    let info = registry.info();
 
-    tx.try_send(bytes).map_err(|err| match err {
-        TrySendError::Full(_) => "Serial write queue is full".to_string(),
-        TrySendError::Closed(_) => "Serial connection is closed".to_string(),
-    })?;
+    let mut guard = state
+        .shared
+        .schemas
+        .write()
+        .map_err(|_| "HMIP schema registry is poisoned".to_string())?;
+    *guard = registry;
+    Ok(info)
+}
 
-    Ok(seq)
+/// 查询当前生效的 HMIP body schema 概况
+#[tauri::command]
+pub fn get_hmip_schema_info(state: State<'_, CommState>) -> Result<schema::SchemaInfo, String> {
+    state
+        .shared
+        .schemas
+        .read()
+        .map(|registry| registry.info())
+        .map_err(|_| "HMIP schema registry is poisoned".to_string())
+}
+
+/// 加载 HMIP 告警映射规则（未指定路径时使用 Log 目录下的 hmip_alarms.json）
+///
+/// 加载成功后立即生效；失败时保留原有规则，已激活的告警不受影响。
+#[tauri::command]
+pub fn load_hmip_alarm_rules(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    path: Option<String>,
+) -> Result<alarm::AlarmRulesInfo, String> {
+    let path = match path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+        _ => PathBuf::from(get_log_dir(app)?).join(alarm::DEFAULT_ALARM_RULES_FILE),
+    };
+    let rules = alarm::AlarmRules::load(&path)?;
+    let info = rules.info();
+
+    let mut guard = state
+        .shared
+        .alarms
+        .lock()
+        .map_err(|_| "Alarm table is poisoned".to_string())?;
+    guard.set_rules(rules);
+    Ok(info)
+}
+
+/// 查询当前生效的告警映射规则概况
+#[tauri::command]
+pub fn get_hmip_alarm_rules_info(state: State<'_, CommState>) -> Result<alarm::AlarmRulesInfo, String> {
+    state
+        .shared
+        .alarms
+        .lock()
+        .map(|alarms| alarms.rules_info())
+        .map_err(|_| "Alarm table is poisoned".to_string())
+}
+
+/// 查询后端当前激活的告警（前端启动/重新打开时据此同步）
+#[tauri::command]
+pub fn list_alarms(state: State<'_, CommState>) -> Result<Vec<alarm::AlarmRecord>, String> {
+    state
+        .shared
+        .alarms
+        .lock()
+        .map(|alarms| alarms.active())
+        .map_err(|_| "Alarm table is poisoned".to_string())
+}
+
+/// 确认后端告警（写入历史并推送 `alarm-event`）
+#[tauri::command]
+pub fn acknowledge_alarm(app: AppHandle, state: State<'_, CommState>, id: u64) -> Result<(), String> {
+    let event = state
+        .shared
+        .alarms
+        .lock()
+        .map_err(|_| "Alarm table is poisoned".to_string())?
+        .acknowledge(id, actor::now_ms())?;
+    alarm::emit_alarm_events(&app, &[event]);
+    Ok(())
+}
+
+/// 手动清除后端告警（写入历史并推送 `alarm-event`）
+#[tauri::command]
+pub fn clear_alarm(app: AppHandle, state: State<'_, CommState>, id: u64) -> Result<(), String> {
+    let event = state
+        .shared
+        .alarms
+        .lock()
+        .map_err(|_| "Alarm table is poisoned".to_string())?
+        .clear(id, actor::now_ms())?;
+    alarm::emit_alarm_events(&app, &[event]);
+    Ok(())
+}
+
+/// 查询 HMI 侧已注册的 RPC 方法（设备发来其他 method 时回 NOT_IMPLEMENTED）
+#[tauri::command]
+pub fn list_hmi_methods(state: State<'_, CommState>) -> Vec<methods::MethodInfo> {
+    state.shared.methods.methods()
+}
+
+/// 同步当前登录的操作员（供设备通过 READ_OPERATOR 查询；登出时传 null）
+#[tauri::command]
+pub fn set_hmi_operator(state: State<'_, CommState>, operator: Option<methods::OperatorInfo>) {
+    state.shared.methods.set_operator(operator);
+}
+
+async fn transfer_client(
+    state: &CommState,
+    transport: &ConnectionId,
+) -> Result<rpc::RpcClient, String> {
+    let slot = state.slot(transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    Ok(handle.rpc_client(&transport.label()))
+}
+
+/// 从设备下载文件到 Log 目录（断线重连后自动续传），返回传输 ID
+///
+/// 说明：
+/// - local_name 缺省时取远端路径最后一段；只允许文件名，不允许子目录
+/// - 进度/重试/结束通过 `hmip-transfer` 事件推送
+#[tauri::command]
+pub async fn start_hmip_download(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    remote_path: String,
+    local_name: Option<String>,
+    options: Option<transfer::TransferOptions>,
+) -> Result<u32, String> {
+    let name = transfer::local_file_name(&remote_path, local_name.as_deref())?;
+    let local_path = PathBuf::from(get_log_dir(app.clone())?).join(name);
+    let client = transfer_client(&state, &transport).await?;
+    transfer::start_download(
+        app,
+        &state.transfers,
+        client,
+        transport.as_str().to_string(),
+        remote_path,
+        local_path,
+        options.unwrap_or_default(),
+    )
+}
+
+/// 上传本地文件到设备（续传起点由设备给出），返回传输 ID
+#[tauri::command]
+pub async fn start_hmip_upload(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    local_path: String,
+    remote_path: String,
+    options: Option<transfer::TransferOptions>,
+) -> Result<u32, String> {
+    let client = transfer_client(&state, &transport).await?;
+    transfer::start_upload(
+        app,
+        &state.transfers,
+        client,
+        transport.as_str().to_string(),
+        PathBuf::from(local_path),
+        remote_path,
+        options.unwrap_or_default(),
+    )
+}
+
+/// 取消文件传输（当前分块结束后生效；已下载部分保留以便续传）
+#[tauri::command]
+pub fn cancel_hmip_transfer(state: State<'_, CommState>, id: u32) -> Result<(), String> {
+    state.transfers.cancel(id)
+}
+
+/// 列出进行中的文件传输
+#[tauri::command]
+pub fn list_hmip_transfers(state: State<'_, CommState>) -> Vec<transfer::TransferInfo> {
+    state.transfers.list()
+}
+
+/// 查询发送队列统计（深度/丢弃/饥饿计数，跨重连累计）
+#[tauri::command]
+pub async fn get_hmip_tx_queues(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<Vec<queue::TxQueueStats>, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    Ok(handle.tx_queues.stats())
+}
+
+/// 清空发送队列（queue 缺省时清空全部），返回丢弃的帧数；每帧以 `tx_dropped`（flushed）上报
+#[tauri::command]
+pub async fn flush_hmip_tx_queue(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    queue: Option<String>,
+) -> Result<usize, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    handle.tx_queues.flush(queue.as_deref())
+}
+
+/// 打开连接的持久化发件箱（未配置 `hmip.outbox` 时为 None）
+///
+/// 重新连接时沿用旧连接的发件箱实例，避免同一文件被打开两次。
+async fn open_outbox(
+    app: &AppHandle,
+    slot: &registry::ConnectionSlot,
+    transport: &ConnectionId,
+    link: &session::HmipLinkConfig,
+) -> Result<Option<std::sync::Arc<outbox::Outbox>>, String> {
+    let Some(config) = &link.outbox else {
+        return Ok(None);
+    };
+    let existing = slot
+        .lock()
+        .await
+        .as_ref()
+        .and_then(|handle| handle.outbox());
+    if existing.is_some() {
+        return Ok(existing);
+    }
+
+    let path = PathBuf::from(get_log_dir(app.clone())?)
+        .join(format!("hmip_outbox_{}.bin", transport.as_str()));
+    let app = app.clone();
+    let outbox = outbox::Outbox::open(
+        path,
+        config,
+        transport.as_str(),
+        Box::new(move |event| {
+            if let Err(err) = app.emit(outbox::OUTBOX_EVENT_NAME, &event) {
+                log::warn!("Failed to emit outbox event: {}", err);
+            }
+        }),
+    )?;
+    Ok(Some(std::sync::Arc::new(outbox)))
+}
+
+async fn connection_outbox(
+    state: &CommState,
+    transport: &ConnectionId,
+) -> Result<std::sync::Arc<outbox::Outbox>, String> {
+    let slot = state.slot(transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    handle
+        .outbox()
+        .ok_or_else(|| format!("{} outbox is not enabled", transport.label()))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HmipOutboxMessage {
+    pub message: HmipMessageSpec,
+    pub channel: Option<u8>,
+    /// 覆盖连接配置中的 CRC 策略
+    pub crc: Option<session::CrcPolicy>,
+}
+
+/// 把类型化消息写入持久化发件箱，返回发件箱消息 ID
+///
+/// 说明：
+/// - 链路断开（重连中）时同样可以提交；连接就绪后按提交顺序发出，送达状态以 `outbox-event` 推送
+/// - seq 在实际发出时分配；发件箱消息不分片，payload 不能超过分片上限
+#[tauri::command]
+pub async fn enqueue_hmip_outbox(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+    message: HmipOutboxMessage,
+) -> Result<u64, String> {
+    let (outbox, flags, fragmentation, msg) = {
+        let slot = state.slot(&transport);
+        let lock = slot.lock().await;
+        let handle = lock
+            .as_ref()
+            .ok_or_else(|| transport.not_connected_message())?;
+        let outbox = handle
+            .outbox()
+            .ok_or_else(|| format!("{} outbox is not enabled", transport.label()))?;
+        (
+            outbox,
+            message
+                .crc
+                .unwrap_or(handle.crc_policy())
+                .flags(handle.auth().is_some()),
+            handle.fragmentation().cloned(),
+            message.message.into_message(|| handle.next_request_id()),
+        )
+    };
+
+    let (msg_type, payload) = proto::encode_message(&msg);
+    if let Some(cfg) = fragmentation.filter(|cfg| payload.len() > cfg.max_fragment_payload) {
+        return Err(format!(
+            "Outbox payload too large ({} > {} bytes)",
+            payload.len(),
+            cfg.max_fragment_payload
+        ));
+    }
+    // 追加写入并 fsync 后才返回，放到阻塞线程池，避免占住运行 IO 循环的 worker
+    let channel = message.channel.unwrap_or(0);
+    tauri::async_runtime::spawn_blocking(move || outbox.push(msg_type, flags, channel, payload))
+        .await
+        .map_err(|e| format!("Outbox task failed: {}", e))?
+}
+
+/// 查询持久化发件箱状态
+#[tauri::command]
+pub async fn get_hmip_outbox(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<outbox::OutboxInfo, String> {
+    connection_outbox(&state, &transport).await?.info()
+}
+
+/// 丢弃发件箱中全部未送达的消息，返回条数
+#[tauri::command]
+pub async fn clear_hmip_outbox(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<usize, String> {
+    let outbox = connection_outbox(&state, &transport).await?;
+    tauri::async_runtime::spawn_blocking(move || outbox.clear())
+        .await
+        .map_err(|e| format!("Outbox task failed: {}", e))?
+}
+
+/// 查询接收侧各 channel 的 seq 统计（丢帧/重复/乱序计数，跨重连累计）
+#[tauri::command]
+pub async fn get_hmip_seq_stats(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<Vec<sequence::ChannelSeqStats>, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    Ok(handle.seq_stats())
+}
+
+/// 查询发送侧 seq 分配状态（策略 + 各计数器的下一个 seq）
+#[tauri::command]
+pub async fn get_hmip_tx_seq(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<sequence::TxSeqSnapshot, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    Ok(handle.tx_seq().snapshot())
+}
+
+/// 开始抓包：记录连接上的全部收发字节与完整帧，返回 Log 目录下的抓包文件路径
+///
+/// 已在抓包时切换到新文件（旧文件保持完整可读）。
+#[tauri::command]
+pub async fn start_hmip_capture(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<String, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = PathBuf::from(get_log_dir(app)?).join(format!(
+        "hmip_{}_{}.{}",
+        transport.as_str(),
+        ts,
+        capture::FILE_EXTENSION
+    ));
+    handle.start_capture(&path)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 停止抓包，返回写入的记录数
+#[tauri::command]
+pub async fn stop_hmip_capture(
+    state: State<'_, CommState>,
+    transport: ConnectionId,
+) -> Result<u64, String> {
+    let slot = state.slot(&transport);
+    let lock = slot.lock().await;
+    let handle = lock
+        .as_ref()
+        .ok_or_else(|| transport.not_connected_message())?;
+    handle
+        .stop_capture()
+        .await
+        .ok_or_else(|| format!("{} capture is not running", transport.label()))
+}
+
+/// 回放抓包文件：rx 字节重新经过 FrameDecoder 与正常事件路径（hmip-event）
+///
+/// 参数说明：
+/// - speed：1 为按原始节奏（默认），>1 加速，0 为尽快回放
+/// - auth：抓包链路启用了帧认证时需提供同一密钥，否则认证帧会被拒绝
+///
+/// 同一时间只进行一个回放，新的回放会先停止旧的。
+#[tauri::command]
+pub async fn replay_hmip_capture(
+    app: AppHandle,
+    state: State<'_, CommState>,
+    path: String,
+    speed: Option<f64>,
+    auth: Option<auth::AuthConfig>,
+) -> Result<(), String> {
+    let speed = speed.unwrap_or(1.0);
+    if !speed.is_finite() || speed < 0.0 {
+        return Err("speed must be a non-negative number".to_string());
+    }
+    let frame_auth = auth.as_ref().map(auth::FrameAuth::from_config).transpose()?;
+    let reader = capture::CaptureReader::open(std::path::Path::new(&path))?;
+
+    let mut lock = state.replay.lock().await;
+    if let Some(previous) = lock.take() {
+        previous.stop().await;
+    }
+    *lock = Some(actor::spawn_replay(
+        app,
+        path,
+        reader,
+        speed,
+        frame_auth,
+        state.shared.clone(),
+    ));
+    Ok(())
+}
+
+/// 停止正在进行的回放（未在回放时为 no-op）
+#[tauri::command]
+pub async fn stop_hmip_replay(state: State<'_, CommState>) -> Result<(), String> {
+    let handle = state.replay.lock().await.take();
+    if let Some(handle) = handle {
+        handle.stop().await;
+    }
+    Ok(())
 }
 
 /// 启动传感器数据模拟
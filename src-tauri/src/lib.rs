@@ -38,14 +38,53 @@ pub fn run() {
             commands::get_log_dir,
             commands::save_spectrum_screenshot,
             commands::get_serial_ports,
+            commands::connect,
+            commands::disconnect,
+            commands::list_connections,
+            commands::send_data,
+            commands::send_hmip_frame,
             commands::connect_serial,
             commands::disconnect_serial,
             commands::send_serial_data,
             commands::connect_tcp,
             commands::disconnect_tcp,
             commands::send_tcp_data,
+            commands::start_tcp_server,
+            commands::stop_tcp_server,
+            commands::list_tcp_peers,
+            commands::send_tcp_peer_data,
+            commands::send_tcp_peer_hmip_frame,
+            commands::kick_tcp_peer,
             commands::send_tcp_hmip_frame,
             commands::send_serial_hmip_frame,
+            commands::send_hmip_request,
+            commands::send_hmip_message,
+            commands::get_hmip_session,
+            commands::get_hmip_seq_stats,
+            commands::get_hmip_tx_seq,
+            commands::get_hmip_tx_queues,
+            commands::flush_hmip_tx_queue,
+            commands::enqueue_hmip_outbox,
+            commands::get_hmip_outbox,
+            commands::clear_hmip_outbox,
+            commands::load_hmip_schema,
+            commands::get_hmip_schema_info,
+            commands::load_hmip_alarm_rules,
+            commands::get_hmip_alarm_rules_info,
+            commands::list_alarms,
+            commands::list_hmi_methods,
+            commands::set_hmi_operator,
+            commands::start_hmip_download,
+            commands::start_hmip_upload,
+            commands::cancel_hmip_transfer,
+            commands::list_hmip_transfers,
+            commands::acknowledge_alarm,
+            commands::clear_alarm,
+            commands::fetch_hmip_payload,
+            commands::start_hmip_capture,
+            commands::stop_hmip_capture,
+            commands::replay_hmip_capture,
+            commands::stop_hmip_replay,
             commands::start_sensor_simulation,
             commands::stop_sensor_simulation,
             commands::frontend_log_batch,
@@ -53,6 +92,36 @@ pub fn run() {
         .setup(|app| {
             // 初始化通信状态
             app.manage(comm::CommState::default());
+            // 预加载 HMIP body schema（Log/hmip_schema.json 不存在时跳过）
+            if let Ok(log_dir) = commands::get_log_dir(app.handle().clone()) {
+                let path = std::path::Path::new(&log_dir).join(comm::schema::DEFAULT_SCHEMA_FILE);
+                if path.exists() {
+                    match comm::schema::SchemaRegistry::load(&path) {
+                        Ok(registry) => {
+                            if let Ok(mut guard) = app.state::<comm::CommState>().shared.schemas.write() {
+                                *guard = registry;
+                            }
+                        }
+                        Err(err) => log::warn!("Failed to load HMIP schema: {}", err),
+                    }
+                }
+            }
+            // 后端告警：历史写入 Log/alarm_history.txt，预加载 Log/hmip_alarms.json（不存在时跳过）
+            let alarms = app.state::<comm::CommState>().shared.alarms.clone();
+            if let Ok(log_dir) = commands::get_log_dir(app.handle().clone()) {
+                let log_dir = std::path::Path::new(&log_dir);
+                let rules_path = log_dir.join(comm::alarm::DEFAULT_ALARM_RULES_FILE);
+                if let Ok(mut manager) = alarms.lock() {
+                    manager.set_history_path(log_dir.join(comm::alarm::HISTORY_FILE));
+                    if rules_path.exists() {
+                        match comm::alarm::AlarmRules::load(&rules_path) {
+                            Ok(rules) => manager.set_rules(rules),
+                            Err(err) => log::warn!("Failed to load HMIP alarm rules: {}", err),
+                        }
+                    }
+                }
+            }
+            comm::alarm::spawn_expiry(app.handle().clone(), alarms);
             // 初始化传感器模拟器
             app.manage(sensor::SensorSimulator::default());
             Ok(())
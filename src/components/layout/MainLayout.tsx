@@ -31,6 +31,8 @@ import {
     useFrontendLogBridge,
     useCommEventBridge,
     useHmipEventBridge,
+    useAlarmEventBridge,
+    useHmiOperatorSync,
     useHMIScale,
     useNotify,
 } from "@/hooks";
@@ -81,6 +83,12 @@ export function MainLayout() {
     // 安装 HMIP 事件桥接：将后端 hmip-event 分发到前端读模型/告警
     useHmipEventBridge();
 
+    // 安装后端告警桥接：将 HMIP 映射出的设备告警同步到告警列表
+    useAlarmEventBridge();
+
+    // 同步登录操作员到后端（设备可通过 HMIP READ_OPERATOR 查询）
+    useHmiOperatorSync();
+
     // 安装 HMI 缩放系统（rem + 动态根字体）
     useHMIScale();
 
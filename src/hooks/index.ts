@@ -18,3 +18,5 @@ export { useIntervalWhenActive } from "./useIntervalWhenActive";
 export { useTauriEventStream } from "./useTauriEventStream";
 export { useCommEventBridge } from "./useCommEventBridge";
 export { useHmipEventBridge } from "./useHmipEventBridge";
+export { useAlarmEventBridge } from "./useAlarmEventBridge";
+export { useHmiOperatorSync } from "./useHmiOperatorSync";
@@ -0,0 +1,119 @@
+import { act, renderHook, waitFor } from "@testing-library/react";
+import { beforeEach, describe, expect, it, vi } from "vitest";
+import type { HmipAlarmEvent, HmipAlarmRecord } from "@/types";
+import { listen } from "@/platform/events";
+import { invoke } from "@/platform/invoke";
+import { isTauri } from "@/platform/tauri";
+import { useAlarmStore } from "@/stores";
+import { useAlarmEventBridge } from "./useAlarmEventBridge";
+
+vi.mock("@/platform/tauri", () => ({
+    isTauri: vi.fn(),
+}));
+
+vi.mock("@/platform/events", () => ({
+    listen: vi.fn(),
+}));
+
+vi.mock("@/platform/invoke", () => ({
+    invoke: vi.fn(),
+}));
+
+function record(id: number, alarmId: string): HmipAlarmRecord {
+    return {
+        id,
+        alarm_id: alarmId,
+        transport: "tcp",
+        severity: "alarm",
+        text: "真空异常",
+        source: { kind: "error", code: 17 },
+        raised_at_ms: 1,
+        last_seen_ms: 1,
+        occurrences: 1,
+        acknowledged: false,
+    };
+}
+
+describe("useAlarmEventBridge", () => {
+    type ListenHandler = (event: { payload: HmipAlarmEvent }) => void;
+
+    let handler: ListenHandler | null = null;
+    const unlisten = vi.fn();
+
+    beforeEach(() => {
+        handler = null;
+        unlisten.mockClear();
+        localStorage.clear();
+
+        vi.mocked(isTauri).mockReturnValue(true);
+        vi.mocked(listen).mockImplementation(async (_eventName, cb) => {
+            handler = cb as ListenHandler;
+            return unlisten;
+        });
+        vi.mocked(invoke).mockResolvedValue([]);
+
+        useAlarmStore.getState().clearAll();
+    });
+
+    it("挂载时应同步后端已激活的告警", async () => {
+        vi.mocked(invoke).mockResolvedValue([record(3, "VAC")]);
+
+        renderHook(() => useAlarmEventBridge());
+
+        await waitFor(() => {
+            expect(useAlarmStore.getState().alarms.length).toBe(1);
+        });
+        expect(vi.mocked(invoke)).toHaveBeenCalledWith("list_alarms");
+        expect(useAlarmStore.getState().alarms[0]).toMatchObject({
+            severity: "alarm",
+            message: "[VAC] 真空异常(tcp)",
+            backendId: 3,
+        });
+    });
+
+    it("raised/acknowledged/cleared：应按 backendId 镜像到 alarmStore", async () => {
+        renderHook(() => useAlarmEventBridge());
+
+        await waitFor(() => {
+            expect(vi.mocked(invoke)).toHaveBeenCalled();
+        });
+
+        const alarm = record(7, "DOOR");
+        act(() => {
+            handler?.({ payload: { type: "raised", alarm } });
+            handler?.({ payload: { type: "raised", alarm } });
+        });
+        expect(useAlarmStore.getState().alarms.length).toBe(1);
+        expect(useAlarmStore.getState().unacknowledgedAlarmCount).toBe(1);
+
+        act(() => {
+            handler?.({
+                payload: { type: "acknowledged", alarm, timestamp_ms: 2 },
+            });
+        });
+        expect(useAlarmStore.getState().alarms[0].acknowledged).toBe(true);
+
+        act(() => {
+            handler?.({
+                payload: {
+                    type: "cleared",
+                    alarm,
+                    reason: "event",
+                    timestamp_ms: 3,
+                },
+            });
+        });
+        expect(useAlarmStore.getState().alarms.length).toBe(0);
+    });
+
+    it("unmount：应释放事件订阅", async () => {
+        const { unmount } = renderHook(() => useAlarmEventBridge());
+
+        await waitFor(() => {
+            expect(vi.mocked(listen)).toHaveBeenCalledTimes(1);
+        });
+
+        unmount();
+        expect(unlisten).toHaveBeenCalledTimes(1);
+    });
+});
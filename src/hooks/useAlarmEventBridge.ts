@@ -0,0 +1,86 @@
+/**
+ * 后端告警桥接 Hook
+ *
+ * 作用：
+ * - 订阅 Tauri 后端的 `alarm-event` 事件（HMIP ERROR/EVENT 映射出的设备告警）
+ * - 挂载时通过 `list_alarms` 补齐 WebView 未打开期间已激活的告警
+ * - 触发/确认/清除同步到 alarmStore（按 backendId 关联）
+ *
+ * 说明：告警的触发、清除与历史记录以后端为准，这里只做单向镜像。
+ *
+ * @module hooks/useAlarmEventBridge
+ */
+
+import { useEffect } from "react";
+import { listen } from "@/platform/events";
+import { invoke } from "@/platform/invoke";
+import { isTauri } from "@/platform/tauri";
+import { useAlarmStore } from "@/stores";
+import type { HmipAlarmEvent, HmipAlarmRecord } from "@/types";
+
+const ALARM_EVENT_NAME = "alarm-event";
+
+function findByBackendId(backendId: number) {
+    return useAlarmStore
+        .getState()
+        .alarms.find((a) => a.backendId === backendId);
+}
+
+function mirrorRaised(alarm: HmipAlarmRecord) {
+    if (findByBackendId(alarm.id)) return;
+    useAlarmStore.getState().addAlarm({
+        severity: alarm.severity,
+        message: `[${alarm.alarm_id}] ${alarm.text}(${alarm.transport})`,
+        backendId: alarm.id,
+    });
+}
+
+export function useAlarmEventBridge() {
+    useEffect(() => {
+        if (!isTauri()) return;
+
+        let cancelled = false;
+        let unlisten: null | (() => void) = null;
+
+        const setup = async () => {
+            try {
+                unlisten = await listen<HmipAlarmEvent>(
+                    ALARM_EVENT_NAME,
+                    (event) => {
+                        if (cancelled) return;
+
+                        const payload = event.payload;
+                        if (payload.type === "raised") {
+                            mirrorRaised(payload.alarm);
+                            return;
+                        }
+
+                        const existing = findByBackendId(payload.alarm.id);
+                        if (!existing) return;
+                        if (payload.type === "cleared") {
+                            useAlarmStore.getState().clearAlarm(existing.id);
+                        } else {
+                            useAlarmStore
+                                .getState()
+                                .acknowledgeAlarm(existing.id);
+                        }
+                    },
+                );
+
+                // 先订阅再拉取，避免两者之间触发的告警丢失（重复的由 backendId 去重）
+                const active = await invoke<HmipAlarmRecord[]>("list_alarms");
+                if (cancelled) return;
+                active.forEach(mirrorRaised);
+            } catch (err) {
+                console.error("Failed to setup alarm event bridge:", err);
+            }
+        };
+
+        void setup();
+
+        return () => {
+            cancelled = true;
+            unlisten?.();
+        };
+    }, []);
+}
@@ -0,0 +1,45 @@
+import { act, renderHook } from "@testing-library/react";
+import { beforeEach, describe, expect, it, vi } from "vitest";
+import { invoke } from "@/platform/invoke";
+import { isTauri } from "@/platform/tauri";
+import { useAppStore } from "@/stores";
+import { useHmiOperatorSync } from "./useHmiOperatorSync";
+
+vi.mock("@/platform/tauri", () => ({
+    isTauri: vi.fn(),
+}));
+
+vi.mock("@/platform/invoke", () => ({
+    invoke: vi.fn(),
+}));
+
+describe("useHmiOperatorSync", () => {
+    beforeEach(() => {
+        vi.mocked(isTauri).mockReturnValue(true);
+        vi.mocked(invoke).mockReset();
+        vi.mocked(invoke).mockResolvedValue(undefined);
+        useAppStore.getState().logout();
+    });
+
+    it("登录/登出时应同步到后端", () => {
+        renderHook(() => useHmiOperatorSync());
+        expect(vi.mocked(invoke)).toHaveBeenLastCalledWith("set_hmi_operator", {
+            operator: null,
+        });
+
+        act(() => {
+            useAppStore
+                .getState()
+                .login({ id: "op1", name: "张三", role: "operator" });
+        });
+        expect(vi.mocked(invoke)).toHaveBeenLastCalledWith("set_hmi_operator", {
+            operator: { id: "op1", name: "张三", role: "operator" },
+        });
+    });
+
+    it("非 Tauri 环境不调用后端", () => {
+        vi.mocked(isTauri).mockReturnValue(false);
+        renderHook(() => useHmiOperatorSync());
+        expect(vi.mocked(invoke)).not.toHaveBeenCalled();
+    });
+});
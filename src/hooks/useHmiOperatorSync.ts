@@ -0,0 +1,29 @@
+/**
+ * 操作员同步 Hook
+ *
+ * 作用：把 appStore 的登录用户同步到后端（`set_hmi_operator`），
+ * 设备通过 HMIP READ_OPERATOR 请求查询当前操作员时由后端直接应答。
+ *
+ * @module hooks/useHmiOperatorSync
+ */
+
+import { useEffect } from "react";
+import { invoke } from "@/platform/invoke";
+import { isTauri } from "@/platform/tauri";
+import { useAppStore } from "@/stores";
+import type { HmiOperatorInfo } from "@/types";
+
+export function useHmiOperatorSync() {
+    const user = useAppStore((state) => state.user);
+
+    useEffect(() => {
+        if (!isTauri()) return;
+
+        const operator: HmiOperatorInfo | null = user
+            ? { id: user.id, name: user.name, role: user.role }
+            : null;
+        invoke("set_hmi_operator", { operator }).catch((err) => {
+            console.error("Failed to sync HMI operator:", err);
+        });
+    }, [user]);
+}
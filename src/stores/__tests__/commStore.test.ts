@@ -201,6 +201,90 @@ describe("stores/commStore", () => {
         });
     });
 
+    it("sendHmipRequest 应携带 transport 并返回 Response 结果", async () => {
+        const result = {
+            request_id: 1,
+            seq: 5,
+            status: 0,
+            body_len: 2,
+            body_base64: "AQI=",
+        };
+        const invokeMock = vi.fn().mockResolvedValue(result);
+        vi.doMock("@/platform/invoke", () => ({ invoke: invokeMock }));
+
+        const { useCommStore } = await import("../commStore");
+
+        await expect(
+            useCommStore.getState().sendHmipRequest("tcp", {
+                method: 0x0102,
+                body: new Uint8Array([7]),
+                timeoutMs: 1000,
+            }),
+        ).resolves.toEqual(result);
+
+        expect(invokeMock).toHaveBeenCalledWith("send_hmip_request", {
+            transport: "tcp",
+            request: {
+                method: 0x0102,
+                body: [7],
+                timeout_ms: 1000,
+            },
+        });
+    });
+
+    it("sendHmipMessage 应把类型化消息转为后端参数", async () => {
+        const invokeMock = vi.fn().mockResolvedValue(42);
+        vi.doMock("@/platform/invoke", () => ({ invoke: invokeMock }));
+
+        const { useCommStore } = await import("../commStore");
+
+        await expect(
+            useCommStore.getState().sendHmipMessage(
+                "tcp",
+                {
+                    message: { type: "event", event_id: 3, body: new Uint8Array([1, 2]) },
+                    crc: "auto",
+                },
+                { priority: "high" },
+            ),
+        ).resolves.toBe(42);
+
+        expect(invokeMock).toHaveBeenCalledWith("send_hmip_message", {
+            transport: "tcp",
+            message: {
+                message: { type: "event", event_id: 3, body: [1, 2] },
+                crc: "auto",
+                priority: "high",
+            },
+        });
+    });
+
+    it("fetchHmipPayload 应按句柄与范围拉取完整 payload", async () => {
+        const chunk = {
+            id: 3,
+            transport: "tcp",
+            seq: 9,
+            total_len: 4096,
+            offset: 2048,
+            data_base64: "AAE=",
+        };
+        const invokeMock = vi.fn().mockResolvedValue(chunk);
+        vi.doMock("@/platform/invoke", () => ({ invoke: invokeMock }));
+
+        const { useCommStore } = await import("../commStore");
+
+        await expect(
+            useCommStore
+                .getState()
+                .fetchHmipPayload({ id: 3 }, { offset: 2048 }),
+        ).resolves.toEqual(chunk);
+
+        expect(invokeMock).toHaveBeenCalledWith("fetch_hmip_payload", {
+            id: 3,
+            offset: 2048,
+        });
+    });
+
     it("getSerialPorts 成功时应返回端口列表", async () => {
         const invokeMock = vi.fn().mockResolvedValue(["COM1", "COM2"]);
         vi.doMock("@/platform/invoke", () => ({ invoke: invokeMock }));
@@ -25,7 +25,15 @@ import type {
     CommState,
     CommTransport,
     CommTransportStatus,
+    ConnectionConfig,
+    ConnectionId,
+    ConnectionInfo,
+    HmipPayloadChunk,
+    HmipPayloadHandle,
+    HmipRequestResult,
     HmipSendFrame,
+    HmipSendMessage,
+    HmipSendRequest,
     SerialConfig,
     TcpConfig,
 } from "@/types";
@@ -37,6 +45,10 @@ interface CommOperationOptions {
     onError?: ErrorHandler;
     /** 写入优先级（用于后端写队列：报警/控制命令可优先） */
     priority?: "high" | "normal";
+    /** 后端发送队列名（见 `hmip.tx_queues`），优先于 priority */
+    queue?: string;
+    /** 排队超过该时长未发出则由后端丢弃（以 tx_dropped 事件上报） */
+    ttlMs?: number;
 }
 
 interface CommStoreState extends CommState {
@@ -82,6 +94,20 @@ interface CommStoreState extends CommState {
         options?: CommOperationOptions,
     ) => Promise<void>;
 
+    // 具名连接（可同时存在多个串口/TCP 连接），事件只进入 commEventLog
+    connect: (
+        id: ConnectionId,
+        config: ConnectionConfig,
+        options?: CommOperationOptions,
+    ) => Promise<void>;
+    disconnect: (id: ConnectionId, options?: CommOperationOptions) => Promise<void>;
+    listConnections: (options?: CommOperationOptions) => Promise<ConnectionInfo[]>;
+    sendData: (
+        id: ConnectionId,
+        data: number[],
+        options?: CommOperationOptions,
+    ) => Promise<void>;
+
     // HMIP（HMI Binary Protocol v1）发送：前端 payload → Rust 封帧 → Transport 写入
     sendSerialHmipFrame: (
         frame: HmipSendFrame,
@@ -91,6 +117,24 @@ interface CommStoreState extends CommState {
         frame: HmipSendFrame,
         options?: CommOperationOptions,
     ) => Promise<number>;
+    // HMIP 类型化消息：后端编码 payload 并按 CRC 策略封帧，返回 seq
+    sendHmipMessage: (
+        transport: ConnectionId,
+        message: HmipSendMessage,
+        options?: CommOperationOptions,
+    ) => Promise<number>;
+    // HMIP 请求/响应：后端按 request_id 关联 Response 后返回
+    sendHmipRequest: (
+        transport: ConnectionId,
+        request: HmipSendRequest,
+        options?: CommOperationOptions,
+    ) => Promise<HmipRequestResult>;
+    // 拉取被事件截断的完整 payload（可指定字节范围）
+    fetchHmipPayload: (
+        handle: Pick<HmipPayloadHandle, "id">,
+        range?: { offset?: number; length?: number },
+        options?: CommOperationOptions,
+    ) => Promise<HmipPayloadChunk>;
 
     // 获取可用串口列表
     getSerialPorts: (options?: CommOperationOptions) => Promise<string[]>;
@@ -187,6 +231,41 @@ function updateTransportModel(params: {
     return patch;
 }
 
+/** 串口配置转为后端命令参数（字段使用 snake_case） */
+function toSerialConfigArgs(config: SerialConfig): Record<string, unknown> {
+    return {
+        port: config.port,
+        baud_rate: config.baudRate,
+        data_bits: config.dataBits,
+        stop_bits: config.stopBits,
+        parity: config.parity,
+        ...(config.hmip ? { hmip: config.hmip } : {}),
+    };
+}
+
+/** TCP 配置转为后端命令参数（字段使用 snake_case） */
+function toTcpConfigArgs(config: TcpConfig): Record<string, unknown> {
+    return {
+        host: config.host,
+        port: config.port,
+        timeout_ms: config.timeoutMs,
+        ...(config.tls ? { tls: config.tls } : {}),
+        ...(config.hmip ? { hmip: config.hmip } : {}),
+    };
+}
+
+/** 原始数据发送参数（priority/queue/ttlMs 可选） */
+function toSendDataArgs(
+    data: number[],
+    options?: CommOperationOptions,
+): Record<string, unknown> {
+    const args: Record<string, unknown> = { data };
+    if (options?.priority) args.priority = options.priority;
+    if (options?.queue) args.queue = options.queue;
+    if (typeof options?.ttlMs === "number") args.ttlMs = options.ttlMs;
+    return args;
+}
+
 /**
  * 带超时的 Tauri invoke 调用
  *
@@ -249,8 +328,13 @@ export const useCommStore = create<CommStoreState>((set) => ({
                     ? fullLog.slice(fullLog.length - COMM_EVENT_LOG_MAX)
                     : fullLog;
 
+            // 具名连接与监听模式（tcp_server / tcp_server:<addr>）的事件只进日志，不计入主连接读模型
+            if (event.transport !== "serial" && event.transport !== "tcp") {
+                return { commEventLog: nextLog };
+            }
+
             const patch = updateTransportModel({
-                transport: event.transport,
+                transport: event.transport as CommTransport,
                 event,
                 prev: state,
             });
@@ -268,16 +352,7 @@ export const useCommStore = create<CommStoreState>((set) => ({
             const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
             await invokeWithTimeout(
                 "connect_serial",
-                {
-                    config: {
-                        // 与后端命令参数保持一致：字段使用 snake_case
-                        port: config.port,
-                        baud_rate: config.baudRate,
-                        data_bits: config.dataBits,
-                        stop_bits: config.stopBits,
-                        parity: config.parity,
-                    },
-                },
+                { config: toSerialConfigArgs(config) },
                 timeoutMs,
             );
             set({
@@ -315,9 +390,7 @@ export const useCommStore = create<CommStoreState>((set) => ({
     sendSerialData: async (data, options) => {
         try {
             const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
-            const args: Record<string, unknown> = { data };
-            if (options?.priority) args.priority = options.priority;
-            await invokeWithTimeout("send_serial_data", args, timeoutMs);
+            await invokeWithTimeout("send_serial_data", toSendDataArgs(data, options), timeoutMs);
         } catch (error) {
             const message = toErrorMessage(error);
             set({ lastError: message });
@@ -332,13 +405,7 @@ export const useCommStore = create<CommStoreState>((set) => ({
             const timeoutMs = options?.timeoutMs ?? config.timeoutMs;
             await invokeWithTimeout(
                 "connect_tcp",
-                {
-                    config: {
-                        host: config.host,
-                        port: config.port,
-                        timeout_ms: config.timeoutMs,
-                    },
-                },
+                { config: toTcpConfigArgs(config) },
                 timeoutMs,
             );
             set({
@@ -376,9 +443,80 @@ export const useCommStore = create<CommStoreState>((set) => ({
     sendTcpData: async (data, options) => {
         try {
             const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
-            const args: Record<string, unknown> = { data };
-            if (options?.priority) args.priority = options.priority;
-            await invokeWithTimeout("send_tcp_data", args, timeoutMs);
+            await invokeWithTimeout("send_tcp_data", toSendDataArgs(data, options), timeoutMs);
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
+    connect: async (id, config, options) => {
+        try {
+            const timeoutMs =
+                options?.timeoutMs ??
+                (config.kind === "tcp"
+                    ? config.timeoutMs
+                    : config.kind === "unix" || config.kind === "ws"
+                      ? config.timeout_ms
+                      : DEFAULT_COMM_TIMEOUT_MS);
+            // UdpConfig / UnixConfig / WsConfig 本身即为 snake_case，原样传递
+            const args =
+                config.kind === "tcp"
+                    ? toTcpConfigArgs(config)
+                    : config.kind === "serial"
+                      ? toSerialConfigArgs(config)
+                      : config;
+            await invokeWithTimeout(
+                "connect",
+                { id, config: { ...args, kind: config.kind } },
+                timeoutMs,
+            );
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
+    disconnect: async (id, options) => {
+        try {
+            const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
+            await invokeWithTimeout("disconnect", { id }, timeoutMs);
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
+    listConnections: async (options) => {
+        try {
+            const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
+            return await invokeWithTimeout<ConnectionInfo[]>(
+                "list_connections",
+                undefined,
+                timeoutMs,
+            );
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
+    sendData: async (id, data, options) => {
+        try {
+            const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
+            await invokeWithTimeout(
+                "send_data",
+                { transport: id, ...toSendDataArgs(data, options) },
+                timeoutMs,
+            );
         } catch (error) {
             const message = toErrorMessage(error);
             set({ lastError: message });
@@ -403,6 +541,10 @@ export const useCommStore = create<CommStoreState>((set) => ({
 
             const priority = options?.priority ?? frame.priority;
             if (priority) argsFrame.priority = priority;
+            const queue = options?.queue ?? frame.queue;
+            if (queue) argsFrame.queue = queue;
+            const ttlMs = options?.ttlMs ?? frame.ttlMs;
+            if (typeof ttlMs === "number") argsFrame.ttl_ms = ttlMs;
 
             return await invokeWithTimeout<number>(
                 "send_serial_hmip_frame",
@@ -433,6 +575,10 @@ export const useCommStore = create<CommStoreState>((set) => ({
 
             const priority = options?.priority ?? frame.priority;
             if (priority) argsFrame.priority = priority;
+            const queue = options?.queue ?? frame.queue;
+            if (queue) argsFrame.queue = queue;
+            const ttlMs = options?.ttlMs ?? frame.ttlMs;
+            if (typeof ttlMs === "number") argsFrame.ttl_ms = ttlMs;
 
             return await invokeWithTimeout<number>(
                 "send_tcp_hmip_frame",
@@ -447,6 +593,98 @@ export const useCommStore = create<CommStoreState>((set) => ({
         }
     },
 
+    sendHmipMessage: async (transport, msg, options) => {
+        try {
+            const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
+
+            // body/payload 统一转为 number[]，其余字段已与后端 snake_case 一致
+            const spec: Record<string, unknown> = { ...msg.message };
+            if ("body" in msg.message && msg.message.body) {
+                spec.body = toByteArray(msg.message.body);
+            }
+            if ("payload" in msg.message && msg.message.payload) {
+                spec.payload = toByteArray(msg.message.payload);
+            }
+
+            const argsMessage: Record<string, unknown> = { message: spec };
+            if (typeof msg.channel === "number") argsMessage.channel = msg.channel;
+            if (typeof msg.seq === "number") argsMessage.seq = msg.seq;
+            if (msg.crc) argsMessage.crc = msg.crc;
+
+            const priority = options?.priority ?? msg.priority;
+            if (priority) argsMessage.priority = priority;
+            const queue = options?.queue ?? msg.queue;
+            if (queue) argsMessage.queue = queue;
+            const ttlMs = options?.ttlMs ?? msg.ttl_ms;
+            if (typeof ttlMs === "number") argsMessage.ttl_ms = ttlMs;
+
+            return await invokeWithTimeout<number>(
+                "send_hmip_message",
+                { transport, message: argsMessage },
+                timeoutMs,
+            );
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
+    sendHmipRequest: async (transport, request, options) => {
+        try {
+            // UI 侧兜底超时需长于后端等待 Response 的超时，优先让后端返回明确的超时错误
+            const backendTimeoutMs = request.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
+            const timeoutMs =
+                options?.timeoutMs ?? backendTimeoutMs + DEFAULT_COMM_TIMEOUT_MS;
+
+            const argsRequest: Record<string, unknown> = {
+                method: request.method,
+                body: toByteArray(request.body),
+            };
+
+            if (typeof request.flags === "number") argsRequest.flags = request.flags;
+            if (typeof request.channel === "number") argsRequest.channel = request.channel;
+            if (typeof request.seq === "number") argsRequest.seq = request.seq;
+            if (request.queue) argsRequest.queue = request.queue;
+            if (typeof request.ttlMs === "number") argsRequest.ttl_ms = request.ttlMs;
+            if (typeof request.timeoutMs === "number") {
+                argsRequest.timeout_ms = request.timeoutMs;
+            }
+
+            return await invokeWithTimeout<HmipRequestResult>(
+                "send_hmip_request",
+                { transport, request: argsRequest },
+                timeoutMs,
+            );
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
+    fetchHmipPayload: async (handle, range, options) => {
+        try {
+            const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
+            const args: Record<string, unknown> = { id: handle.id };
+            if (typeof range?.offset === "number") args.offset = range.offset;
+            if (typeof range?.length === "number") args.length = range.length;
+
+            return await invokeWithTimeout<HmipPayloadChunk>(
+                "fetch_hmip_payload",
+                args,
+                timeoutMs,
+            );
+        } catch (error) {
+            const message = toErrorMessage(error);
+            set({ lastError: message });
+            options?.onError?.(message, error);
+            throw error;
+        }
+    },
+
     getSerialPorts: async (options) => {
         try {
             const timeoutMs = options?.timeoutMs ?? DEFAULT_COMM_TIMEOUT_MS;
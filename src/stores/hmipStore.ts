@@ -121,6 +121,14 @@ export const useHmipStore = create<HmipStoreState>((set) => ({
                     ? fullLog.slice(fullLog.length - HMIP_EVENT_LOG_MAX)
                     : fullLog;
 
+            // 回放起止事件不属于任何连接；监听模式对端的事件只进日志
+            if (
+                !("transport" in event) ||
+                (event.transport !== "serial" && event.transport !== "tcp")
+            ) {
+                return { hmipEventLog: nextLog };
+            }
+
             const patch = updateTransportModel({
                 transport: event.transport,
                 event,
@@ -1,17 +1,164 @@
 /** Communication Type Definitions */
 
+/** HMIP 握手参数（与 Rust `session::HandshakeConfig` 一致，字段使用 snake_case） */
+export interface HmipHandshakeConfig {
+    /** client 发 HELLO 等 HELLO_ACK；server 等对端 HELLO 并应答。缺省时监听模式对端为 server，其余为 client */
+    role?: "client" | "server";
+    name?: string;
+    capabilities?: number;
+    required_capabilities?: number;
+    timeout_ms?: number;
+    /** 声明支持 v2 帧头（双方都支持时握手后切换） */
+    header_v2?: boolean;
+}
+
+/** HMIP 帧认证/加密（与 Rust `auth::AuthConfig` 一致；key_hex 为 32 字节预共享密钥；需同时配置 handshake） */
+export interface HmipAuthConfig {
+    mode?: "hmac_sha256" | "chacha20_poly1305";
+    key_hex: string;
+    replay_window?: number;
+    require_auth?: boolean;
+}
+
+/** HMIP 心跳/看门狗参数（与 Rust `session::HeartbeatConfig` 一致） */
+export interface HmipHeartbeatConfig {
+    interval_ms?: number;
+    max_missed?: number;
+}
+
+/** HMIP 分片参数（与 Rust `fragment::FragmentConfig` 一致） */
+export interface HmipFragmentConfig {
+    max_fragment_payload?: number;
+    reassembly_timeout_ms?: number;
+    max_streams?: number;
+    max_message_len?: number;
+    max_buffered_bytes?: number;
+}
+
+/** HMIP 会话层选项（与 Rust `session::HmipLinkConfig` 一致），缺省时不启用 */
+export interface HmipLinkConfig {
+    handshake?: HmipHandshakeConfig | null;
+    heartbeat?: HmipHeartbeatConfig | null;
+    fragmentation?: HmipFragmentConfig | null;
+    auth?: HmipAuthConfig | null;
+    /** 类型化发送（send_hmip_message）的 CRC 策略，默认 always */
+    crc?: "always" | "never" | "auto";
+    /** 发送侧 seq 分配，默认按连接计数、重连后重置 */
+    seq?: HmipSeqConfig;
+    /** 发送队列，默认 high（权重 8，容量 64）+ normal（权重 1，容量 256） */
+    tx_queues?: HmipTxQueueConfig;
+    /** 持久化发件箱（断线期间暂存 enqueue_hmip_outbox 提交的消息），缺省不启用 */
+    outbox?: HmipOutboxConfig | null;
+}
+
+/** 发件箱配置（与 Rust `outbox::OutboxConfig` 一致），文件位于 Log/hmip_outbox_<transport>.bin */
+export interface HmipOutboxConfig {
+    /** 文件大小上限，默认 4 MiB；满后拒绝新消息 */
+    max_bytes?: number;
+}
+
+/** 发送队列配置（与 Rust `queue::TxQueueConfig` 一致） */
+export interface HmipTxQueueConfig {
+    classes?: {
+        name: string;
+        /** 调度权重（按字节加权轮询），默认 1 */
+        weight?: number;
+        /** 最大排队帧数，默认 256 */
+        capacity?: number;
+        /** 未显式指定队列时，这些 channel 上的帧进入本队列 */
+        channels?: number[];
+    }[];
+    /** 帧排队超过该时长才发出时计为一次 starved，默认 1000 */
+    starvation_ms?: number;
+}
+
+/** 发送侧 seq 配置（与 Rust `sequence::SeqConfig` 一致） */
+export interface HmipSeqConfig {
+    /** connection：整个连接一个计数器；channel：每个 channel 独立计数 */
+    scope?: "connection" | "channel";
+    /** reset：每次建立连接从 1 开始；persist：跨重连延续 */
+    on_reconnect?: "reset" | "persist";
+}
+
 export interface SerialConfig {
     port: string;
     baudRate: number;
     dataBits: 5 | 6 | 7 | 8;
     stopBits: 1 | 2;
     parity: "none" | "odd" | "even";
+    hmip?: HmipLinkConfig;
 }
 
 export interface TcpConfig {
     host: string;
     port: number;
     timeoutMs: number;
+    /** 缺省为明文 TCP */
+    tls?: TcpTlsConfig | null;
+    hmip?: HmipLinkConfig;
+}
+
+/** TCP 客户端 TLS 选项（与 Rust `tls::TlsConfig` 一致，字段使用 snake_case；均为本地 PEM 文件路径） */
+export interface TcpTlsConfig {
+    /** 信任的 CA；缺省使用公共根证书 */
+    ca_path?: string | null;
+    /** 客户端证书与私钥，同时配置时启用双向 TLS */
+    cert_path?: string | null;
+    key_path?: string | null;
+    /** 校验证书时使用的服务端名称，缺省为 host */
+    server_name?: string | null;
+    /** 服务端叶子证书 SHA-256 指纹（hex，可用 `:` 分隔）；配置后不做 CA 与名称校验 */
+    pinned_sha256?: string | null;
+}
+
+/** `error` 事件的 reason（TLS 失败原因；普通 IO 错误不带 reason） */
+export type CommErrorReason =
+    | "tls_config"
+    | "tls_handshake_timeout"
+    | "tls_handshake_failed"
+    | "tls_certificate_untrusted"
+    | "tls_certificate_expired"
+    | "tls_server_name_mismatch"
+    | "tls_fingerprint_mismatch"
+    | "tls_client_certificate_rejected"
+    | "tls_protocol_error";
+
+/** UDP 传输参数（与 Rust `udp::UdpConfig` 一致，字段使用 snake_case） */
+export interface UdpConfig {
+    bind_host: string;
+    /** 0 表示由系统分配 */
+    bind_port: number;
+    /** 指定时只与该对端收发；缺省时接收任意来源，回复最近一个数据报的来源 */
+    remote?: { host: string; port: number } | null;
+    /** 加入组播组 */
+    multicast?: {
+        group: string;
+        /** IPv4 为本地接口地址，IPv6 为接口索引 */
+        interface?: string | null;
+        loopback?: boolean;
+        ttl?: number | null;
+    } | null;
+    hmip?: HmipLinkConfig;
+}
+
+/** Unix 域套接字参数（与 Rust `unix::UnixConfig` 一致，字段使用 snake_case；仅 Unix 平台可用） */
+export interface UnixConfig {
+    /** 守护进程监听的 socket 文件路径，如 `/run/motion/hmip.sock` */
+    path: string;
+    timeout_ms: number;
+    hmip?: HmipLinkConfig;
+}
+
+/** WebSocket 客户端参数（与 Rust `ws::WsConfig` 一致，字段使用 snake_case） */
+export interface WsConfig {
+    /** ws:// 或 wss:// 地址 */
+    url: string;
+    timeout_ms: number;
+    /** wss:// 的 TLS 选项，缺省按公共根证书校验 */
+    tls?: TcpTlsConfig | null;
+    /** Ping/Pong 保活，缺省不启用；默认间隔 10000ms、等待 5000ms */
+    ping?: { interval_ms?: number; timeout_ms?: number } | null;
+    hmip?: HmipLinkConfig;
 }
 
 export interface CommState {
@@ -24,43 +171,139 @@ export interface CommState {
 
 export type CommTransport = "serial" | "tcp";
 
+/**
+ * 连接 ID：`connect` 时由前端指定（字母、数字、`_` `-` `.`，最长 64），
+ * 之后作为各命令的 `transport` 参数；`serial` / `tcp` 为 connect_serial / connect_tcp 所用
+ */
+export type ConnectionId = CommTransport | (string & {});
+
+/**
+ * 事件中的 transport 字段：主动连接为其连接 ID（serial/tcp 或 `connect` 指定的 ID）；
+ * TCP 监听模式为 tcp_server（监听器本身）与 tcp_server:<peer_addr>（各接入对端）
+ */
+export type CommEventTransport = ConnectionId | "tcp_server" | `tcp_server:${string}`;
+
+/** `connect` 的连接参数（按 kind 区分传输类型） */
+export type ConnectionConfig =
+    | ({ kind: "serial" } & SerialConfig)
+    | ({ kind: "tcp" } & TcpConfig)
+    | ({ kind: "udp" } & UdpConfig)
+    | ({ kind: "unix" } & UnixConfig)
+    | ({ kind: "ws" } & WsConfig);
+
+/** `list_connections` 返回的连接信息 */
+export interface ConnectionInfo {
+    id: ConnectionId;
+    kind: ConnectionConfig["kind"];
+    /** 连接目标，如 `COM3 @ 115200`、`127.0.0.1:502` */
+    target: string;
+    /** false 表示正在重连 */
+    connected: boolean;
+    created_at_ms: number;
+}
+
+/** TCP 监听模式参数（与 Rust `server::TcpServerConfig` 一致，字段使用 snake_case） */
+export interface TcpServerConfig {
+    bind_host: string;
+    port: number;
+    max_clients: number;
+    hmip?: HmipLinkConfig;
+}
+
+/** `list_tcp_peers` 返回的对端信息 */
+export interface TcpPeerInfo {
+    addr: string;
+    /** 该对端事件中的 transport（tcp_server:<addr>） */
+    transport: `tcp_server:${string}`;
+    connected_at_ms: number;
+    session: {
+        peer_name: string;
+        peer_capabilities: number;
+        capabilities: number;
+        wire_version: number;
+        established_at_ms: number;
+    } | null;
+}
+
 export type CommTransportStatus = "disconnected" | "connected" | "reconnecting";
 
 export type CommEvent =
     | {
           type: "connected";
-          transport: CommTransport;
+          transport: CommEventTransport;
           timestamp_ms: number;
       }
     | {
           type: "disconnected";
-          transport: CommTransport;
+          transport: CommEventTransport;
+          timestamp_ms: number;
+      }
+    | {
+          type: "session_established";
+          transport: CommEventTransport;
+          peer_name: string;
+          peer_capabilities: number;
+          capabilities: number;
+          wire_version: number;
           timestamp_ms: number;
       }
     | {
           type: "reconnecting";
-          transport: CommTransport;
+          transport: CommEventTransport;
           attempt: number;
           delay_ms: number;
           timestamp_ms: number;
       }
     | {
           type: "rx";
-          transport: CommTransport;
+          transport: CommEventTransport;
           data_base64: string;
+          /** WebSocket 文本消息为消息全文，其它为可读内容预览 */
           text?: string | null;
+          /** 数据报来源地址（仅 UDP） */
+          source?: string;
           size: number;
           timestamp_ms: number;
       }
     | {
           type: "tx";
-          transport: CommTransport;
+          transport: CommEventTransport;
           size: number;
           timestamp_ms: number;
       }
     | {
           type: "error";
-          transport: CommTransport;
+          transport: CommEventTransport;
           message: string;
+          reason?: CommErrorReason;
+          timestamp_ms: number;
+      }
+    | {
+          /** TCP 监听已开始（对端接入/断开以 tcp_server:<addr> 的 connected/disconnected 上报） */
+          type: "server_listening";
+          transport: "tcp_server";
+          local_addr: string;
+          max_clients: number;
+          timestamp_ms: number;
+      }
+    | {
+          /** 对端接入被拒绝（超过 max_clients） */
+          type: "peer_rejected";
+          transport: "tcp_server";
+          peer: string;
+          reason: string;
+          timestamp_ms: number;
+      }
+    | {
+          /** 写队列中的帧未发出即被丢弃 */
+          type: "tx_dropped";
+          transport: CommEventTransport;
+          queue: string;
+          reason: "queue_full" | "expired" | "flushed" | "closed";
+          size: number;
+          /** 丢弃前已排队的时长（queue_full 时为 0） */
+          waited_ms: number;
+          /** 请求帧的 request_id（调用方同时收到错误） */
+          request_id?: number | null;
           timestamp_ms: number;
       };
@@ -8,7 +8,40 @@
  * @module types/hmip
  */
 
-import type { CommTransport } from "./comm";
+import type { CommEventTransport, HmipSeqConfig } from "./comm";
+
+/** 后端 schema 解码结果：成功时为 fields，失败时为 error */
+export interface HmipDecodedBody {
+    schema: string;
+    fields?: Record<string, unknown>;
+    error?: string;
+}
+
+/** 后端完整 payload 缓存句柄（按 `id` 拉取；transport/seq 仅供对照） */
+export interface HmipPayloadHandle {
+    id: number;
+    transport: CommEventTransport;
+    seq: number;
+    len: number;
+}
+
+/** `fetch_hmip_payload` 返回值 */
+export interface HmipPayloadChunk {
+    id: number;
+    transport: CommEventTransport;
+    seq: number;
+    total_len: number;
+    offset: number;
+    data_base64: string;
+}
+
+/** `load_hmip_schema` / `get_hmip_schema_info` 返回值 */
+export interface HmipSchemaInfo {
+    path: string | null;
+    methods: number;
+    events: number;
+    msg_types: number;
+}
 
 export type HmipMessageSummary =
     | {
@@ -33,6 +66,8 @@ export type HmipMessageSummary =
           body_len: number;
           body_base64: string | null;
           body_truncated: boolean;
+          /** 按后端 schema 解码的 body（未注册 schema 时缺省） */
+          decoded?: HmipDecodedBody;
       }
     | {
           kind: "response";
@@ -41,6 +76,8 @@ export type HmipMessageSummary =
           body_len: number;
           body_base64: string | null;
           body_truncated: boolean;
+          /** 按后端 schema 解码的 body（未注册 schema 时缺省） */
+          decoded?: HmipDecodedBody;
       }
     | {
           kind: "event";
@@ -49,6 +86,8 @@ export type HmipMessageSummary =
           body_len: number;
           body_base64: string | null;
           body_truncated: boolean;
+          /** 按后端 schema 解码的 body（未注册 schema 时缺省） */
+          decoded?: HmipDecodedBody;
       }
     | {
           kind: "error";
@@ -61,19 +100,107 @@ export type HmipMessageSummary =
           payload_len: number;
           payload_base64: string | null;
           payload_truncated: boolean;
+          decoded?: HmipDecodedBody;
       };
 
 export type HmipEvent =
     | {
           type: "decode_error";
-          transport: CommTransport;
+          transport: CommEventTransport;
           message: string;
           dropped_bytes: number;
           timestamp_ms: number;
       }
+    | {
+          type: "auth_rejected";
+          transport: CommEventTransport;
+          channel: number;
+          seq: number;
+          msg_type: number;
+          reason: string;
+          timestamp_ms: number;
+      }
+    | {
+          type: "replay_rejected";
+          transport: CommEventTransport;
+          channel: number;
+          seq: number;
+          msg_type: number;
+          highest_seq: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "seq_gap";
+          transport: CommEventTransport;
+          channel: number;
+          seq: number;
+          expected: number;
+          missing: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "seq_duplicate";
+          transport: CommEventTransport;
+          channel: number;
+          seq: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "seq_out_of_order";
+          transport: CommEventTransport;
+          channel: number;
+          seq: number;
+          expected: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "seq_reset";
+          transport: CommEventTransport;
+          channel: number;
+          seq: number;
+          previous: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "unsupported_version";
+          transport: CommEventTransport;
+          version: number;
+          dropped_bytes: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "fragment_dropped";
+          transport: CommEventTransport;
+          channel: number;
+          msg_type: number;
+          fragment_id: number;
+          reason: string;
+          dropped_bytes: number;
+          timestamp_ms: number;
+      }
+    | {
+          /** 开始回放抓包（其后事件按原 transport 推送） */
+          type: "replay_started";
+          path: string;
+          captured_at_ms: number;
+          speed: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "replay_finished";
+          path: string;
+          /** 已回放的 rx 记录数 */
+          records: number;
+          /** 是否被 stop_hmip_replay 手动停止 */
+          stopped: boolean;
+          error: string | null;
+          timestamp_ms: number;
+      }
     | {
           type: "message";
-          transport: CommTransport;
+          transport: CommEventTransport;
+          /** 帧头版本（1/2） */
+          version: number;
           channel: number;
           seq: number;
           flags: number;
@@ -82,6 +209,8 @@ export type HmipEvent =
           payload_crc32: number | null;
           timestamp_ms: number;
           summary: HmipMessageSummary;
+          /** payload 超出预览上限时的完整数据句柄（见 `fetch_hmip_payload`） */
+          payload_handle?: HmipPayloadHandle;
       };
 
 /**
@@ -98,4 +227,271 @@ export interface HmipSendFrame {
     flags?: number;
     seq?: number;
     priority?: "high" | "normal";
+    /** 后端发送队列名（见 `hmip.tx_queues`），优先于 channel 映射与 priority */
+    queue?: string;
+    /** 排队超过该时长未发出则丢弃（分片消息的每个分片各自计时） */
+    ttlMs?: number;
+}
+
+/** 类型化发送的 CRC 策略（auto：认证帧省略 CRC，明文帧带 CRC） */
+export type HmipCrcPolicy = "always" | "never" | "auto";
+
+/**
+ * 类型化 HMIP 消息（与 Rust `proto::Message` 一一对应，payload 由后端编码）
+ *
+ * - timestamp_ms 省略时使用后端当前时间
+ * - request 的 request_id 省略时由后端按连接分配
+ */
+export type HmipMessageSpec =
+    | { type: "hello"; role?: "client" | "server"; capabilities?: number; name?: string }
+    | { type: "hello_ack"; capabilities?: number; name?: string }
+    | { type: "heartbeat"; timestamp_ms?: number }
+    | { type: "request"; request_id?: number; method: number; body?: number[] | Uint8Array }
+    | { type: "response"; request_id: number; status: number; body?: number[] | Uint8Array }
+    | { type: "event"; event_id: number; timestamp_ms?: number; body?: number[] | Uint8Array }
+    | { type: "error"; code: number; message?: string }
+    | { type: "raw"; msg_type: number; payload?: number[] | Uint8Array };
+
+/** `send_hmip_message` 参数 */
+export interface HmipSendMessage {
+    message: HmipMessageSpec;
+    channel?: number;
+    seq?: number;
+    /** 覆盖连接配置中的 CRC 策略 */
+    crc?: HmipCrcPolicy;
+    priority?: "high" | "normal";
+    queue?: string;
+    /** 排队超过该时长未发出则丢弃（例如断线期间积压的设定值） */
+    ttl_ms?: number;
 }
+
+/**
+ * 写入持久化发件箱的消息（`enqueue_hmip_outbox`，需连接配置 `hmip.outbox`）
+ *
+ * 断线期间同样可提交，连接就绪后按提交顺序发出；seq 在发出时分配。
+ */
+export interface HmipOutboxMessage {
+    message: HmipMessageSpec;
+    channel?: number;
+    crc?: HmipCrcPolicy;
+}
+
+/** 发件箱状态（`get_hmip_outbox` 返回值） */
+export interface HmipOutboxInfo {
+    transport: string;
+    path: string;
+    pending: number;
+    pending_bytes: number;
+    file_bytes: number;
+    max_bytes: number;
+    /** 本次打开以来送达的条数 */
+    delivered: number;
+}
+
+/** `outbox-event` 事件 */
+export type HmipOutboxEvent =
+    | {
+          /** 打开发件箱时从磁盘恢复出未送达的消息 */
+          type: "recovered";
+          transport: string;
+          pending: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "stored";
+          transport: string;
+          id: number;
+          pending: number;
+          timestamp_ms: number;
+      }
+    | {
+          /** 已写出到链路 */
+          type: "delivered";
+          transport: string;
+          id: number;
+          seq: number;
+          stored_ms: number;
+          pending: number;
+          timestamp_ms: number;
+      }
+    | {
+          type: "cleared";
+          transport: string;
+          count: number;
+          timestamp_ms: number;
+      };
+
+/**
+ * HMIP 请求参数（前端 → Rust → 等待匹配的 Response）
+ *
+ * 对应 Rust 端命令：`send_hmip_request`
+ * - request_id 由后端分配
+ * - flags 未指定时默认开启 CRC32
+ */
+export interface HmipSendRequest {
+    method: number;
+    body: number[] | Uint8Array;
+    channel?: number;
+    flags?: number;
+    seq?: number;
+    /** 后端发送队列名；缺省按 channel 映射，未映射时进入 normal 队列 */
+    queue?: string;
+    /** 排队超过该时长未发出则丢弃 */
+    ttlMs?: number;
+    /** 后端等待 Response 的超时（ms） */
+    timeoutMs?: number;
+}
+
+/** `send_hmip_request` 的返回值（Response 的 status + 完整 body） */
+export interface HmipRequestResult {
+    request_id: number;
+    seq: number;
+    status: number;
+    body_len: number;
+    body_base64: string;
+}
+
+/** 接收侧单个 channel 的 seq 统计（`get_hmip_seq_stats` 返回值） */
+export interface HmipChannelSeqStats {
+    channel: number;
+    received: number;
+    last_seq: number;
+    gaps: number;
+    /** 仍未收到的帧数（gap 累加，迟到帧冲抵） */
+    missing: number;
+    duplicates: number;
+    out_of_order: number;
+    resets: number;
+}
+
+/** 发送队列统计（`get_hmip_tx_queues` 返回值，计数跨重连累计） */
+export interface HmipTxQueueStats {
+    name: string;
+    weight: number;
+    capacity: number;
+    depth: number;
+    max_depth: number;
+    enqueued: number;
+    sent: number;
+    sent_bytes: number;
+    /** 队列满导致的丢弃 */
+    dropped: number;
+    /** 超过 ttl_ms 被丢弃 */
+    expired: number;
+    /** 被 flush_hmip_tx_queue 清空 */
+    flushed: number;
+    /** 排队超过 starvation_ms 才发出的帧数 */
+    starved: number;
+    max_wait_ms: number;
+    /** 当前队首已等待的时长 */
+    head_wait_ms: number;
+}
+
+/** 发送侧 seq 分配状态（`get_hmip_tx_seq` 返回值） */
+export interface HmipTxSeqSnapshot {
+    config: Required<HmipSeqConfig>;
+    /** 尚未分配过的计数器不出现（其下一个 seq 为 1） */
+    counters: {
+        /** scope 为 connection 时为 null */
+        channel: number | null;
+        next_seq: number;
+        allocated: number;
+        /** u32 回绕次数 */
+        wraps: number;
+    }[];
+}
+
+/** 后端告警（由 HMIP ERROR/EVENT 按 `hmip_alarms.json` 映射，`list_alarms` 返回值） */
+export interface HmipAlarmRecord {
+    /** 本次激活的唯一 ID（`acknowledge_alarm` / `clear_alarm` 参数） */
+    id: number;
+    alarm_id: string;
+    transport: CommEventTransport;
+    severity: "alarm" | "warning" | "info";
+    text: string;
+    source: { kind: "error"; code: number } | { kind: "event"; event_id: number };
+    raised_at_ms: number;
+    last_seen_ms: number;
+    /** 激活期间的触发次数（含首次） */
+    occurrences: number;
+    acknowledged: boolean;
+}
+
+/** 后端 `alarm-event` 事件 */
+export type HmipAlarmEvent =
+    | { type: "raised"; alarm: HmipAlarmRecord }
+    | {
+          type: "cleared";
+          alarm: HmipAlarmRecord;
+          reason: "event" | "timeout" | "manual";
+          timestamp_ms: number;
+      }
+    | { type: "acknowledged"; alarm: HmipAlarmRecord; timestamp_ms: number };
+
+/** 告警映射规则概况（`load_hmip_alarm_rules` / `get_hmip_alarm_rules_info` 返回值） */
+export interface HmipAlarmRulesInfo {
+    path: string | null;
+    errors: number;
+    events: number;
+}
+
+/** HMI 侧已注册的 RPC 方法（`list_hmi_methods` 返回值） */
+export interface HmiMethodInfo {
+    method: number;
+    name: string;
+}
+
+/** `set_hmi_operator` 参数（设备通过 READ_OPERATOR 查询） */
+export interface HmiOperatorInfo {
+    id: string;
+    name: string;
+    role: string;
+}
+
+/** 文件传输参数（`start_hmip_download` / `start_hmip_upload`） */
+export interface HmipTransferOptions {
+    /** 每块字节数（默认 1024，上限 61440） */
+    chunk_size?: number;
+    channel?: number;
+    /** 单次调用超时 */
+    timeout_ms?: number;
+    /** 同一分块连续失败的重试次数（默认 10） */
+    max_retries?: number;
+    /** 后端发送队列名（例如放入低权重的 bulk 队列，避免挤占控制命令） */
+    queue?: string;
+}
+
+export interface HmipTransferInfo {
+    id: number;
+    transport: string;
+    direction: "download" | "upload";
+    remote_path: string;
+    local_path: string;
+    /** 未取得文件大小前为 0 */
+    total: number;
+    transferred: number;
+    started_at_ms: number;
+}
+
+/** `hmip-transfer` 事件 */
+export type HmipTransferEvent =
+    | {
+          type: "progress";
+          transfer: HmipTransferInfo;
+          timestamp_ms: number;
+      }
+    | {
+          type: "retrying";
+          id: number;
+          attempt: number;
+          error: string;
+          timestamp_ms: number;
+      }
+    | {
+          type: "finished";
+          transfer: HmipTransferInfo;
+          /** 取消时为 false 且 error 缺省 */
+          completed: boolean;
+          error?: string | null;
+          timestamp_ms: number;
+      };
@@ -119,6 +119,8 @@ export interface AlarmItem {
     message: string;
     timestamp: Date;
     acknowledged: boolean;
+    /** 来自后端告警表时的 ID（`alarm-event` / `list_alarms` 中的 id） */
+    backendId?: number;
 }
 
 /** 通信状态 */